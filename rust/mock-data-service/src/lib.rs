@@ -0,0 +1,90 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+pub mod service;
+
+use crate::service::{Fixtures, MockRawDataService};
+use anyhow::{Context, Result};
+use aptos_protos::indexer::v1::raw_data_server::RawDataServer;
+use serde::{Deserialize, Serialize};
+use server_framework::RunnableConfig;
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use tracing::info;
+
+/// 256MB, same as the processor.
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024 * 256;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MockDataServiceConfig {
+    /// Address the GRPC server listens on, e.g. `0.0.0.0:50051`.
+    pub listen_address: SocketAddr,
+    /// Archive directory with the transactions to serve, e.g. recorded with the processor's
+    /// `record_config`.
+    pub fixtures_path: PathBuf,
+    /// Chain id sent with every response. Defaults to the chain id of the fixtures.
+    pub chain_id: Option<u64>,
+    /// Maximum number of transactions per response. Defaults to 1000.
+    #[serde(default = "MockDataServiceConfig::default_batch_size")]
+    pub batch_size: usize,
+    /// Delay before every response in milliseconds. Defaults to 0.
+    #[serde(default)]
+    pub latency_in_ms: u64,
+    /// Fail every stream with `UNAVAILABLE` after this many responses.
+    pub disconnect_after_batches: Option<u64>,
+    /// Versions that are never served, so the client sees a gap.
+    #[serde(default)]
+    pub gaps: Vec<VersionRange>,
+}
+
+impl MockDataServiceConfig {
+    pub const fn default_batch_size() -> usize {
+        1000
+    }
+}
+
+/// Inclusive range of versions.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VersionRange {
+    pub start_version: u64,
+    pub end_version: u64,
+}
+
+impl VersionRange {
+    pub fn contains(&self, version: u64) -> bool {
+        self.start_version <= version && version <= self.end_version
+    }
+}
+
+#[async_trait::async_trait]
+impl RunnableConfig for MockDataServiceConfig {
+    async fn run(&self) -> Result<()> {
+        let fixtures = Fixtures::load(&self.fixtures_path)
+            .await
+            .with_context(|| format!("Failed to load fixtures {}", self.fixtures_path.display()))?;
+        info!(
+            fixtures_path = self.fixtures_path.display().to_string(),
+            num_transactions = fixtures.transactions.len(),
+            chain_id = fixtures.chain_id,
+            listen_address = self.listen_address.to_string(),
+            "[Mock Data Service] Serving fixtures"
+        );
+        let service = MockRawDataService::new(Arc::new(fixtures), Arc::new(self.clone()));
+        tonic::transport::Server::builder()
+            .add_service(
+                RawDataServer::new(service)
+                    .accept_compressed(tonic::codec::CompressionEncoding::Gzip)
+                    .send_compressed(tonic::codec::CompressionEncoding::Gzip)
+                    .max_decoding_message_size(MAX_MESSAGE_SIZE)
+                    .max_encoding_message_size(MAX_MESSAGE_SIZE),
+            )
+            .serve(self.listen_address)
+            .await
+            .context("GRPC server stopped")
+    }
+
+    fn get_server_name(&self) -> String {
+        "mockds".to_string()
+    }
+}
@@ -0,0 +1,14 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use clap::Parser;
+use mock_data_service::MockDataServiceConfig;
+use server_framework::ServerArgs;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = ServerArgs::parse();
+    args.run::<MockDataServiceConfig>(tokio::runtime::Handle::current())
+        .await
+}
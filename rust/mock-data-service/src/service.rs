@@ -0,0 +1,281 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{MockDataServiceConfig, VersionRange};
+use anyhow::Result;
+use aptos_protos::{
+    indexer::v1::{raw_data_server::RawData, GetTransactionsRequest, TransactionsResponse},
+    transaction::v1::Transaction,
+};
+use futures::Stream;
+use processor::archive::ArchiveReader;
+use std::{
+    path::Path,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+/// GRPC connection id, read by the processor for logging.
+const GRPC_CONNECTION_ID: &str = "x-aptos-connection-id";
+
+type TransactionsResponseStream =
+    Pin<Box<dyn Stream<Item = Result<TransactionsResponse, Status>> + Send>>;
+
+/// Transactions served by the mock, ordered by version.
+pub struct Fixtures {
+    pub transactions: Vec<Transaction>,
+    pub chain_id: Option<u64>,
+}
+
+impl Fixtures {
+    pub async fn load(path: &Path) -> Result<Self> {
+        let mut reader = ArchiveReader::new(path, 0, None)?;
+        let mut transactions = vec![];
+        let mut chain_id = None;
+        while let Some(batch) = reader.next().await {
+            let batch = batch?;
+            chain_id = chain_id.or(batch.chain_id);
+            transactions.extend(batch.transactions);
+        }
+        transactions.sort_by_key(|transaction| transaction.version);
+        transactions.dedup_by_key(|transaction| transaction.version);
+        Ok(Self {
+            transactions,
+            chain_id,
+        })
+    }
+
+    /// Index of the first transaction at or after `version`.
+    fn index_of(&self, version: u64) -> usize {
+        self.transactions
+            .partition_point(|transaction| transaction.version < version)
+    }
+
+    /// Up to `batch_size` transactions starting at `from_index`, leaving out the gaps, and the
+    /// index to continue from.
+    ///
+    /// A batch ends at a gap and the next one starts after it, so that the gap shows up between
+    /// two batches, which is where the processor detects gaps.
+    fn next_batch(
+        &self,
+        from_index: usize,
+        end_version: Option<u64>,
+        batch_size: usize,
+        gaps: &[VersionRange],
+    ) -> (Vec<Transaction>, usize) {
+        let mut batch: Vec<Transaction> = vec![];
+        let mut index = from_index;
+        while batch.len() < batch_size {
+            let transaction = match self.transactions.get(index) {
+                Some(transaction) => transaction,
+                None => break,
+            };
+            if end_version.map_or(false, |end_version| transaction.version > end_version) {
+                break;
+            }
+            let follows_batch = batch
+                .last()
+                .map_or(true, |last| last.version + 1 == transaction.version);
+            let in_gap = gaps.iter().any(|gap| gap.contains(transaction.version));
+            if !batch.is_empty() && (in_gap || !follows_batch) {
+                break;
+            }
+            index += 1;
+            if in_gap {
+                continue;
+            }
+            batch.push(transaction.clone());
+        }
+        (batch, index)
+    }
+
+    /// Whether the fixtures reach `end_version`, i.e. a stream up to it can be completed.
+    fn covers(&self, end_version: u64) -> bool {
+        self.transactions
+            .last()
+            .map_or(false, |transaction| transaction.version >= end_version)
+    }
+}
+
+struct StreamState {
+    next_index: usize,
+    end_version: Option<u64>,
+    num_batches: u64,
+    disconnected: bool,
+}
+
+pub struct MockRawDataService {
+    fixtures: Arc<Fixtures>,
+    config: Arc<MockDataServiceConfig>,
+    next_connection_id: AtomicU64,
+}
+
+impl MockRawDataService {
+    pub fn new(fixtures: Arc<Fixtures>, config: Arc<MockDataServiceConfig>) -> Self {
+        Self {
+            fixtures,
+            config,
+            next_connection_id: AtomicU64::new(0),
+        }
+    }
+
+    fn chain_id(&self) -> Option<u64> {
+        self.config.chain_id.or(self.fixtures.chain_id)
+    }
+}
+
+#[tonic::async_trait]
+impl RawData for MockRawDataService {
+    type GetTransactionsStream = TransactionsResponseStream;
+
+    async fn get_transactions(
+        &self,
+        request: Request<GetTransactionsRequest>,
+    ) -> Result<Response<Self::GetTransactionsStream>, Status> {
+        let request = request.into_inner();
+        let starting_version = request.starting_version.unwrap_or_default();
+        let end_version = request
+            .transactions_count
+            .map(|count| starting_version.saturating_add(count).saturating_sub(1));
+        let connection_id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        info!(
+            connection_id = connection_id,
+            starting_version = starting_version,
+            end_version = end_version,
+            "[Mock Data Service] New stream"
+        );
+
+        let fixtures = self.fixtures.clone();
+        let config = self.config.clone();
+        let chain_id = self.chain_id();
+        let state = StreamState {
+            next_index: fixtures.index_of(starting_version),
+            end_version,
+            num_batches: 0,
+            disconnected: false,
+        };
+        let stream = futures::stream::unfold(state, move |mut state| {
+            let fixtures = fixtures.clone();
+            let config = config.clone();
+            async move {
+                if state.disconnected {
+                    return None;
+                }
+                if config
+                    .disconnect_after_batches
+                    .map_or(false, |num_batches| state.num_batches >= num_batches)
+                {
+                    info!(connection_id, "[Mock Data Service] Injecting disconnect");
+                    state.disconnected = true;
+                    return Some((
+                        Err(Status::unavailable(
+                            "[Mock Data Service] Injected disconnect",
+                        )),
+                        state,
+                    ));
+                }
+                let (transactions, next_index) = fixtures.next_batch(
+                    state.next_index,
+                    state.end_version,
+                    config.batch_size,
+                    &config.gaps,
+                );
+                if transactions.is_empty() {
+                    if state
+                        .end_version
+                        .map_or(false, |end_version| fixtures.covers(end_version))
+                    {
+                        info!(connection_id, "[Mock Data Service] Stream completed");
+                        return None;
+                    }
+                    // Like the real data service, wait for transactions that are not there yet.
+                    info!(
+                        connection_id,
+                        "[Mock Data Service] Out of fixtures, keeping the stream open"
+                    );
+                    futures::future::pending::<()>().await;
+                }
+                tokio::time::sleep(Duration::from_millis(config.latency_in_ms)).await;
+                state.next_index = next_index;
+                state.num_batches += 1;
+                Some((
+                    Ok(TransactionsResponse {
+                        transactions,
+                        chain_id,
+                    }),
+                    state,
+                ))
+            }
+        });
+
+        let mut response = Response::new(Box::pin(stream) as Self::GetTransactionsStream);
+        response.metadata_mut().insert(
+            GRPC_CONNECTION_ID,
+            connection_id
+                .to_string()
+                .parse()
+                .expect("Connection id is a valid header value"),
+        );
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures(versions: std::ops::RangeInclusive<u64>) -> Fixtures {
+        Fixtures {
+            transactions: versions
+                .map(|version| Transaction {
+                    version,
+                    ..Transaction::default()
+                })
+                .collect(),
+            chain_id: Some(1),
+        }
+    }
+
+    fn versions(batch: &[Transaction]) -> Vec<u64> {
+        batch
+            .iter()
+            .map(|transaction| transaction.version)
+            .collect()
+    }
+
+    #[test]
+    fn test_next_batch_ends_at_gaps() {
+        let fixtures = fixtures(10..=19);
+        let gaps = [VersionRange {
+            start_version: 13,
+            end_version: 14,
+        }];
+        let index = fixtures.index_of(11);
+        let (batch, index) = fixtures.next_batch(index, Some(17), 4, &gaps);
+        assert_eq!(versions(&batch), vec![11, 12]);
+        let (batch, index) = fixtures.next_batch(index, Some(17), 4, &gaps);
+        assert_eq!(versions(&batch), vec![15, 16, 17]);
+        let (batch, _) = fixtures.next_batch(index, Some(17), 4, &gaps);
+        assert!(batch.is_empty());
+        assert!(fixtures.covers(17));
+        assert!(!fixtures.covers(20));
+    }
+
+    #[test]
+    fn test_next_batch_ends_at_missing_fixtures() {
+        let mut fixtures = fixtures(10..=19);
+        fixtures
+            .transactions
+            .retain(|transaction| transaction.version != 12);
+        let (batch, index) = fixtures.next_batch(0, None, 4, &[]);
+        assert_eq!(versions(&batch), vec![10, 11]);
+        let (batch, _) = fixtures.next_batch(index, None, 4, &[]);
+        assert_eq!(versions(&batch), vec![13, 14, 15, 16]);
+    }
+}
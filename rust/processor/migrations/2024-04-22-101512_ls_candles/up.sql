@@ -0,0 +1,26 @@
+CREATE TABLE public.ls_candles (
+	pool_id varchar(64) NOT NULL,
+	interval_secs bigint NOT NULL,
+	start_time bigint NOT NULL,
+	"open" numeric NOT NULL,
+	high numeric NOT NULL,
+	low numeric NOT NULL,
+	"close" numeric NOT NULL,
+	base_volume numeric NOT NULL,
+	quote_volume numeric NOT NULL,
+	trades bigint NOT NULL,
+	first_version bigint NOT NULL,
+	last_version bigint NOT NULL,
+	last_sequence_number bigint NOT NULL,
+	CONSTRAINT ls_candles_pk PRIMARY KEY (pool_id, interval_secs, start_time)
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_candles.start_time IS 'start of the interval, tx.timestamp in sec rounded down to interval_secs';
+COMMENT ON COLUMN public.ls_candles."open" IS 'y_val / x_val of the pool after the first swap of the interval';
+COMMENT ON COLUMN public.ls_candles.base_volume IS 'sum of x_in + x_out';
+COMMENT ON COLUMN public.ls_candles.quote_volume IS 'sum of y_in + y_out';
+COMMENT ON COLUMN public.ls_candles.last_sequence_number IS 'sequence number of the last SwapEvent merged into the candle';
+
+CREATE INDEX ls_candles_interval_start_time_idx ON ls_candles (interval_secs, start_time);
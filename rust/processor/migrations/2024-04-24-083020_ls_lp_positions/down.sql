@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS ls_lp_activities;
+DROP TABLE IF EXISTS ls_lp_positions;
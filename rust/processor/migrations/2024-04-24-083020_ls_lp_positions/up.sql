@@ -0,0 +1,38 @@
+CREATE TABLE public.ls_lp_activities (
+	id varchar NOT NULL,
+	account varchar(66) NOT NULL,
+	pool_id varchar(64) NOT NULL,
+	"tp" event_type NOT NULL,
+	"version" bigint NOT NULL,
+	tx_hash varchar(64) NOT NULL,
+	"timestamp" bigint NOT NULL,
+	x_val numeric NOT NULL,
+	y_val numeric NOT NULL,
+	lp_tokens numeric NOT NULL,
+	lp_balance numeric,
+	CONSTRAINT ls_lp_activities_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE public.ls_lp_positions (
+	account varchar(66) NOT NULL,
+	pool_id varchar(64) NOT NULL,
+	lp_balance numeric NOT NULL,
+	cost_x numeric NOT NULL,
+	cost_y numeric NOT NULL,
+	last_version bigint NOT NULL,
+	CONSTRAINT ls_lp_positions_pk PRIMARY KEY (account, pool_id)
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_lp_activities.id IS 'same as ls_events.id';
+COMMENT ON COLUMN public.ls_lp_activities.account IS 'sender of the transaction';
+COMMENT ON COLUMN public.ls_lp_activities.lp_tokens IS 'lp_tokens_received | lp_tokens_burned';
+COMMENT ON COLUMN public.ls_lp_activities.lp_balance IS 'LP coin balance of the account after the transaction, from its CoinStore';
+COMMENT ON COLUMN public.ls_lp_positions.cost_x IS 'X added for the current LP balance, reduced proportionally when LP tokens are burned';
+COMMENT ON COLUMN public.ls_lp_positions.cost_y IS 'Y added for the current LP balance, reduced proportionally when LP tokens are burned';
+
+CREATE INDEX ls_lp_activities_account_idx ON ls_lp_activities (account);
+CREATE INDEX ls_lp_activities_pool_id_idx ON ls_lp_activities (pool_id);
+CREATE INDEX ls_lp_activities_version_idx ON ls_lp_activities ("version");
+CREATE INDEX ls_lp_positions_pool_id_idx ON ls_lp_positions (pool_id);
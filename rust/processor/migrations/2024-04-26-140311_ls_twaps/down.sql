@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS ls_oracle_observations;
+DROP TABLE IF EXISTS ls_twaps;
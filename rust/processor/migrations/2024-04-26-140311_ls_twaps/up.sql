@@ -0,0 +1,32 @@
+CREATE TABLE public.ls_oracle_observations (
+	id varchar NOT NULL,
+	pool_id varchar(64) NOT NULL,
+	"version" bigint NOT NULL,
+	"timestamp" bigint NOT NULL,
+	price_x_cumulative numeric(39) NOT NULL,
+	price_y_cumulative numeric(39) NOT NULL,
+	CONSTRAINT ls_oracle_observations_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE public.ls_twaps (
+	pool_id varchar(64) NOT NULL,
+	window_secs bigint NOT NULL,
+	twap_x numeric NOT NULL,
+	twap_y numeric NOT NULL,
+	start_version bigint NOT NULL,
+	end_version bigint NOT NULL,
+	start_timestamp bigint NOT NULL,
+	end_timestamp bigint NOT NULL,
+	CONSTRAINT ls_twaps_pk PRIMARY KEY (pool_id, window_secs)
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_oracle_observations.id IS 'same as ls_events.id';
+COMMENT ON COLUMN public.ls_oracle_observations."timestamp" IS 'tx.timestamp in sec';
+COMMENT ON COLUMN public.ls_oracle_observations.price_x_cumulative IS 'last_price_x_cumulative, u128 sum of UQ64.64 prices of X in Y times seconds';
+COMMENT ON COLUMN public.ls_oracle_observations.price_y_cumulative IS 'last_price_y_cumulative, u128 sum of UQ64.64 prices of Y in X times seconds';
+COMMENT ON COLUMN public.ls_twaps.twap_x IS 'time-weighted average price of X in Y from start_timestamp to end_timestamp';
+COMMENT ON COLUMN public.ls_twaps.twap_y IS 'time-weighted average price of Y in X from start_timestamp to end_timestamp';
+
+CREATE INDEX ls_oracle_observations_pool_id_timestamp_idx ON ls_oracle_observations (pool_id, "timestamp");
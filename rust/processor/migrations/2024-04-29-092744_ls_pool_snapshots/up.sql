@@ -0,0 +1,22 @@
+CREATE TABLE public.ls_pool_snapshots (
+	pool_id varchar(64) NOT NULL,
+	"version" bigint NOT NULL,
+	"timestamp" bigint NOT NULL,
+	x_val numeric(21) NOT NULL,
+	y_val numeric(21) NOT NULL,
+	fee int8 NOT NULL,
+	dao_fee int8 NOT NULL,
+	lp_supply numeric,
+	CONSTRAINT ls_pool_snapshots_pk PRIMARY KEY (pool_id, "version")
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_pool_snapshots."version" IS 'tx.version of the LiquidityPool write resource';
+COMMENT ON COLUMN public.ls_pool_snapshots."timestamp" IS 'tx.timestamp in sec';
+COMMENT ON COLUMN public.ls_pool_snapshots.lp_supply IS 'supply of CoinInfo<LP<X, Y, Curve>> if written in the same tx';
+
+-- State as of a version: WHERE pool_id = $1 AND "version" <= $2 ORDER BY "version" DESC LIMIT 1 (primary key)
+-- State as of a timestamp: WHERE pool_id = $1 AND "timestamp" <= $2 ORDER BY "timestamp" DESC, "version" DESC LIMIT 1
+CREATE INDEX ls_pool_snapshots_pool_id_timestamp_idx ON ls_pool_snapshots (pool_id, "timestamp" DESC, "version" DESC);
+CREATE INDEX ls_pool_snapshots_version_idx ON ls_pool_snapshots ("version");
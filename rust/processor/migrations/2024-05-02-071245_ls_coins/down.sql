@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS ls_coins;
+
+ALTER TABLE ls_pools DROP COLUMN x_symbol;
+ALTER TABLE ls_pools DROP COLUMN y_symbol;
+ALTER TABLE ls_pools DROP COLUMN x_decimals;
+ALTER TABLE ls_pools DROP COLUMN y_decimals;
+ALTER TABLE ls_pools DROP COLUMN x_val_normalized;
+ALTER TABLE ls_pools DROP COLUMN y_val_normalized;
+ALTER TABLE ls_events DROP COLUMN x_val_normalized;
+ALTER TABLE ls_events DROP COLUMN y_val_normalized;
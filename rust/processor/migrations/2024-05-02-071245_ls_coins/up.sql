@@ -0,0 +1,27 @@
+CREATE TABLE public.ls_coins (
+	coin_type_hash varchar(64) NOT NULL,
+	coin_type varchar NOT NULL,
+	"name" varchar NOT NULL,
+	symbol varchar NOT NULL,
+	decimals int4 NOT NULL,
+	"version" bigint NOT NULL,
+	CONSTRAINT ls_coins_pk PRIMARY KEY (coin_type_hash)
+);
+
+COMMENT ON COLUMN public.ls_coins.coin_type_hash IS 'sha256(coin_type), same as coin_infos.coin_type_hash';
+COMMENT ON COLUMN public.ls_coins."version" IS 'tx.version of the CoinInfo the metadata was read from';
+
+-- Coin metadata
+ALTER TABLE ls_pools ADD COLUMN x_symbol varchar;
+ALTER TABLE ls_pools ADD COLUMN y_symbol varchar;
+ALTER TABLE ls_pools ADD COLUMN x_decimals int4;
+ALTER TABLE ls_pools ADD COLUMN y_decimals int4;
+ALTER TABLE ls_pools ADD COLUMN x_val_normalized numeric;
+ALTER TABLE ls_pools ADD COLUMN y_val_normalized numeric;
+ALTER TABLE ls_events ADD COLUMN x_val_normalized numeric;
+ALTER TABLE ls_events ADD COLUMN y_val_normalized numeric;
+
+COMMENT ON COLUMN public.ls_pools.x_val_normalized IS 'x_val / 10^x_decimals, null while the coin is unknown';
+COMMENT ON COLUMN public.ls_pools.y_val_normalized IS 'y_val / 10^y_decimals, null while the coin is unknown';
+COMMENT ON COLUMN public.ls_events.x_val_normalized IS 'x_val / 10^decimals of the X coin of the pool';
+COMMENT ON COLUMN public.ls_events.y_val_normalized IS 'y_val / 10^decimals of the Y coin of the pool';
@@ -0,0 +1,31 @@
+CREATE TABLE public.ls_trades (
+	"version" bigint NOT NULL,
+	tx_hash varchar(64) NOT NULL,
+	sender varchar(66) NOT NULL,
+	"timestamp" bigint NOT NULL,
+	hops int4 NOT NULL,
+	route jsonb NOT NULL,
+	coin_in varchar NOT NULL,
+	amount_in numeric NOT NULL,
+	coin_out varchar NOT NULL,
+	amount_out numeric NOT NULL,
+	price numeric,
+	amount_in_normalized numeric,
+	amount_out_normalized numeric,
+	price_normalized numeric,
+	entry_function varchar(1000),
+	payload jsonb,
+	CONSTRAINT ls_trades_pk PRIMARY KEY ("version")
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_trades.route IS '[{pool_id, coin_in, amount_in, coin_out, amount_out}] of the SwapEvents of the tx in event order';
+COMMENT ON COLUMN public.ls_trades.coin_in IS 'coin sold by the first swap';
+COMMENT ON COLUMN public.ls_trades.coin_out IS 'coin bought by the last swap';
+COMMENT ON COLUMN public.ls_trades.price IS 'amount_out / amount_in';
+COMMENT ON COLUMN public.ls_trades.payload IS 'function, type_arguments and arguments of the entry function';
+
+CREATE INDEX ls_trades_sender_idx ON ls_trades (sender);
+CREATE INDEX ls_trades_timestamp_idx ON ls_trades ("timestamp");
+CREATE INDEX ls_trades_route_idx ON ls_trades USING gin (route jsonb_path_ops);
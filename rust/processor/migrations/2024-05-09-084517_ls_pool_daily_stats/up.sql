@@ -0,0 +1,32 @@
+CREATE TABLE public.ls_pool_daily_stats (
+	pool_id varchar(64) NOT NULL,
+	"day" bigint NOT NULL,
+	x_volume numeric NOT NULL,
+	y_volume numeric NOT NULL,
+	x_lp_fee numeric NOT NULL,
+	y_lp_fee numeric NOT NULL,
+	x_dao_fee numeric NOT NULL,
+	y_dao_fee numeric NOT NULL,
+	swaps bigint NOT NULL,
+	x_val numeric NOT NULL,
+	y_val numeric NOT NULL,
+	fee bigint NOT NULL,
+	dao_fee bigint NOT NULL,
+	last_swap_version bigint NOT NULL,
+	last_swap_sequence_number bigint NOT NULL,
+	reserves_version bigint NOT NULL,
+	CONSTRAINT ls_pool_daily_stats_pk PRIMARY KEY (pool_id, "day")
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_pool_daily_stats."day" IS 'start of the UTC day, tx.timestamp in sec rounded down to 86400';
+COMMENT ON COLUMN public.ls_pool_daily_stats.x_volume IS 'sum of x_in + x_out of the swaps';
+COMMENT ON COLUMN public.ls_pool_daily_stats.x_lp_fee IS 'fee on x_in left in the pool, with the fee settings at each swap';
+COMMENT ON COLUMN public.ls_pool_daily_stats.x_dao_fee IS 'dao_fee percent of the fee on x_in, with the fee settings at each swap';
+COMMENT ON COLUMN public.ls_pool_daily_stats.x_val IS 'reserve of the pool at the end of the day (TVL)';
+COMMENT ON COLUMN public.ls_pool_daily_stats.fee IS 'fee of the pool at the end of the day';
+COMMENT ON COLUMN public.ls_pool_daily_stats.last_swap_sequence_number IS 'sequence number of the last SwapEvent merged into the day';
+COMMENT ON COLUMN public.ls_pool_daily_stats.reserves_version IS 'version of the pool snapshot x_val, y_val, fee and dao_fee are from';
+
+CREATE INDEX ls_pool_daily_stats_day_idx ON ls_pool_daily_stats ("day");
@@ -0,0 +1,20 @@
+CREATE TABLE public.coin_prices (
+	coin_type_hash varchar(64) NOT NULL,
+	coin_type varchar(5000) NOT NULL,
+	"version" bigint NOT NULL,
+	"timestamp" bigint NOT NULL,
+	price_usd numeric NOT NULL,
+	liquidity_usd numeric NOT NULL,
+	confidence numeric NOT NULL,
+	CONSTRAINT coin_prices_pk PRIMARY KEY (coin_type_hash, "version")
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.coin_prices.coin_type_hash IS 'same as coin_infos.coin_type_hash';
+COMMENT ON COLUMN public.coin_prices."version" IS 'version of the pool change the price comes from, the price holds until the next version of the coin';
+COMMENT ON COLUMN public.coin_prices.price_usd IS 'USD per whole coin, from the ls_pools reserves';
+COMMENT ON COLUMN public.coin_prices.liquidity_usd IS 'USD value of the reserves of the priced side of the pools the price comes from';
+COMMENT ON COLUMN public.coin_prices.confidence IS 'liquidity_usd / full_confidence_liquidity_usd of the ls_processor config, at most 1';
+
+CREATE INDEX coin_prices_coin_type_version_idx ON coin_prices (coin_type, "version");
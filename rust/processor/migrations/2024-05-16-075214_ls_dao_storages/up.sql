@@ -0,0 +1,20 @@
+ALTER TYPE event_type ADD VALUE IF NOT EXISTS 'storage_created_event';
+ALTER TYPE event_type ADD VALUE IF NOT EXISTS 'coin_withdrawn_event';
+
+CREATE TABLE public.ls_dao_storages (
+	pool_id varchar(64) NOT NULL,
+	x_val numeric NOT NULL,
+	y_val numeric NOT NULL,
+	deposited_x numeric NOT NULL,
+	deposited_y numeric NOT NULL,
+	withdrawn_x numeric NOT NULL,
+	withdrawn_y numeric NOT NULL,
+	last_version bigint NOT NULL,
+	CONSTRAINT ls_dao_storages_pk PRIMARY KEY (pool_id)
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_dao_storages.x_val IS 'coin_x of the dao_storage::Storage resource at last_version';
+COMMENT ON COLUMN public.ls_dao_storages.deposited_x IS 'sum of x_val of CoinDepositedEvent';
+COMMENT ON COLUMN public.ls_dao_storages.withdrawn_x IS 'sum of x_val of CoinWithdrawnEvent';
@@ -0,0 +1,30 @@
+CREATE TABLE public.ls_flashloans (
+	id varchar NOT NULL,
+	pool_id varchar(64) NOT NULL,
+	"version" bigint NOT NULL,
+	tx_hash varchar(64) NOT NULL,
+	"timestamp" bigint NOT NULL,
+	borrower varchar(66) NOT NULL,
+	entry_function varchar(1000),
+	x_borrowed numeric NOT NULL,
+	y_borrowed numeric NOT NULL,
+	x_repaid numeric NOT NULL,
+	y_repaid numeric NOT NULL,
+	x_fee numeric NOT NULL,
+	y_fee numeric NOT NULL,
+	fee bigint,
+	dao_fee bigint,
+	CONSTRAINT ls_flashloans_pk PRIMARY KEY (id)
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_flashloans.id IS 'same as ls_events.id';
+COMMENT ON COLUMN public.ls_flashloans.borrower IS 'sender of the transaction';
+COMMENT ON COLUMN public.ls_flashloans.x_borrowed IS 'x_out of FlashloanEvent';
+COMMENT ON COLUMN public.ls_flashloans.x_repaid IS 'x_in of FlashloanEvent';
+COMMENT ON COLUMN public.ls_flashloans.x_fee IS 'x_in - x_out, the change of the pool reserve before the DAO fee is deposited';
+COMMENT ON COLUMN public.ls_flashloans.fee IS 'fee of the pool at the flashloan';
+
+CREATE INDEX ls_flashloans_pool_id_version_idx ON ls_flashloans (pool_id, "version");
+CREATE INDEX ls_flashloans_borrower_idx ON ls_flashloans (borrower);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS dex_liquidity_changes;
+DROP TABLE IF EXISTS dex_swaps;
+DROP TABLE IF EXISTS dex_pools;
@@ -0,0 +1,56 @@
+CREATE TABLE public.dex_pools (
+	protocol varchar(32) NOT NULL,
+	pool_id varchar(128) NOT NULL,
+	coin_x varchar(5000) NOT NULL,
+	coin_y varchar(5000) NOT NULL,
+	pool_type varchar(5000),
+	x_val numeric NOT NULL,
+	y_val numeric NOT NULL,
+	fee_bps bigint,
+	last_version bigint NOT NULL,
+	CONSTRAINT dex_pools_pk PRIMARY KEY (protocol, pool_id)
+);
+
+CREATE TABLE public.dex_swaps (
+	protocol varchar(32) NOT NULL,
+	id varchar NOT NULL,
+	pool_id varchar(128) NOT NULL,
+	"version" bigint NOT NULL,
+	tx_hash varchar(64) NOT NULL,
+	sender varchar(66) NOT NULL,
+	"timestamp" bigint NOT NULL,
+	coin_in varchar(5000) NOT NULL,
+	amount_in numeric NOT NULL,
+	coin_out varchar(5000) NOT NULL,
+	amount_out numeric NOT NULL,
+	CONSTRAINT dex_swaps_pk PRIMARY KEY (protocol, id)
+);
+
+CREATE TABLE public.dex_liquidity_changes (
+	protocol varchar(32) NOT NULL,
+	id varchar NOT NULL,
+	pool_id varchar(128) NOT NULL,
+	account varchar(66) NOT NULL,
+	"version" bigint NOT NULL,
+	tx_hash varchar(64) NOT NULL,
+	"timestamp" bigint NOT NULL,
+	x_val numeric NOT NULL,
+	y_val numeric NOT NULL,
+	lp_tokens numeric NOT NULL,
+	CONSTRAINT dex_liquidity_changes_pk PRIMARY KEY (protocol, id)
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.dex_pools.protocol IS 'adapter of the dex_processor, e.g. liquidswap';
+COMMENT ON COLUMN public.dex_pools.pool_id IS 'id of the pool in the protocol, ls_pools.id for liquidswap';
+COMMENT ON COLUMN public.dex_pools.pool_type IS 'e.g. the curve of a liquidswap pool';
+COMMENT ON COLUMN public.dex_pools.last_version IS 'version of x_val, y_val and fee_bps, 0 until the reserves are seen';
+COMMENT ON COLUMN public.dex_swaps.id IS 'version + "_" + index of the event in the transaction';
+COMMENT ON COLUMN public.dex_liquidity_changes.id IS 'version + "_" + index of the event in the transaction';
+COMMENT ON COLUMN public.dex_liquidity_changes.x_val IS 'positive when added, negative when removed';
+
+CREATE INDEX dex_swaps_pool_id_version_idx ON dex_swaps (protocol, pool_id, "version");
+CREATE INDEX dex_swaps_sender_idx ON dex_swaps (sender);
+CREATE INDEX dex_liquidity_changes_pool_id_version_idx ON dex_liquidity_changes (protocol, pool_id, "version");
+CREATE INDEX dex_liquidity_changes_account_idx ON dex_liquidity_changes (account);
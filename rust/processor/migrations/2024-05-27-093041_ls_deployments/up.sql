@@ -0,0 +1,16 @@
+CREATE TABLE public.ls_deployments (
+	address varchar(66) NOT NULL,
+	version_ls varchar NOT NULL,
+	deployer varchar(66) NOT NULL,
+	"version" bigint NOT NULL,
+	tx_hash varchar(64) NOT NULL,
+	"timestamp" bigint NOT NULL,
+	CONSTRAINT ls_deployments_pk PRIMARY KEY (address)
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_deployments.address IS 'standardized address the liquidity_pool and dao_storage modules are published at';
+COMMENT ON COLUMN public.ls_deployments.version_ls IS 'label of the deployer in the deployers config, as ls_pools.version_ls';
+COMMENT ON COLUMN public.ls_deployments.deployer IS 'configured deployer that published the modules, itself or as the sender';
+COMMENT ON COLUMN public.ls_deployments."version" IS 'first publish of the modules seen';
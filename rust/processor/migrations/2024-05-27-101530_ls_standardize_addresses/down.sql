@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+-- Irreversible: the leading zeros the addresses were stored without can't be told apart from the
+-- ones of addresses that were already standardized, so they stay standardized
+SELECT 1;
@@ -0,0 +1,6 @@
+-- Addresses used to be stored without leading zeros
+UPDATE ls_events SET sender = '0x' || lpad(substring(sender FROM 3), 64, '0') WHERE length(sender) < 66;
+UPDATE ls_lp_activities SET account = '0x' || lpad(substring(account FROM 3), 64, '0') WHERE length(account) < 66;
+UPDATE ls_lp_positions SET account = '0x' || lpad(substring(account FROM 3), 64, '0') WHERE length(account) < 66;
+UPDATE ls_trades SET sender = '0x' || lpad(substring(sender FROM 3), 64, '0') WHERE length(sender) < 66;
+UPDATE ls_flashloans SET borrower = '0x' || lpad(substring(borrower FROM 3), 64, '0') WHERE length(borrower) < 66;
@@ -0,0 +1,30 @@
+CREATE TABLE public.ls_trader_stats (
+	account varchar(66) NOT NULL,
+	pool_id varchar(64) NOT NULL,
+	x_bought numeric NOT NULL,
+	x_sold numeric NOT NULL,
+	y_spent numeric NOT NULL,
+	y_received numeric NOT NULL,
+	position_x numeric NOT NULL,
+	cost_y numeric NOT NULL,
+	avg_entry_price numeric,
+	realized_pnl numeric NOT NULL,
+	trades bigint NOT NULL,
+	last_version bigint NOT NULL,
+	CONSTRAINT ls_trader_stats_pk PRIMARY KEY (account, pool_id)
+);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_trader_stats.account IS 'sender of the swap transactions';
+COMMENT ON COLUMN public.ls_trader_stats.x_bought IS 'sum of x_out of SwapEvent';
+COMMENT ON COLUMN public.ls_trader_stats.x_sold IS 'sum of x_in of SwapEvent';
+COMMENT ON COLUMN public.ls_trader_stats.y_spent IS 'sum of y_in of SwapEvent';
+COMMENT ON COLUMN public.ls_trader_stats.y_received IS 'sum of y_out of SwapEvent';
+COMMENT ON COLUMN public.ls_trader_stats.position_x IS 'X bought in the pool and not sold yet';
+COMMENT ON COLUMN public.ls_trader_stats.cost_y IS 'Y paid for position_x';
+COMMENT ON COLUMN public.ls_trader_stats.avg_entry_price IS 'cost_y / position_x, null without a position';
+COMMENT ON COLUMN public.ls_trader_stats.realized_pnl IS 'in Y, proceeds of X sold minus its cost at avg_entry_price';
+COMMENT ON COLUMN public.ls_trader_stats.trades IS 'number of SwapEvent';
+
+CREATE INDEX ls_trader_stats_pool_id_realized_pnl_idx ON ls_trader_stats (pool_id, realized_pnl);
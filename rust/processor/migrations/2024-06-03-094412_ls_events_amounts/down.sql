@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.ls_events
+	DROP COLUMN IF EXISTS x_in,
+	DROP COLUMN IF EXISTS x_out,
+	DROP COLUMN IF EXISTS y_in,
+	DROP COLUMN IF EXISTS y_out,
+	DROP COLUMN IF EXISTS lp_tokens,
+	DROP COLUMN IF EXISTS x_reserve,
+	DROP COLUMN IF EXISTS y_reserve;
@@ -0,0 +1,15 @@
+ALTER TABLE public.ls_events
+	ADD COLUMN x_in numeric,
+	ADD COLUMN x_out numeric,
+	ADD COLUMN y_in numeric,
+	ADD COLUMN y_out numeric,
+	ADD COLUMN lp_tokens numeric,
+	ADD COLUMN x_reserve numeric,
+	ADD COLUMN y_reserve numeric;
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_events.x_in IS 'x_in of SwapEvent and FlashloanEvent, added_x_val of LiquidityAddedEvent';
+COMMENT ON COLUMN public.ls_events.x_out IS 'x_out of SwapEvent and FlashloanEvent, returned_x_val of LiquidityRemovedEvent';
+COMMENT ON COLUMN public.ls_events.lp_tokens IS 'lp_tokens_received | lp_tokens_burned';
+COMMENT ON COLUMN public.ls_events.x_reserve IS 'reserve of the pool after the event, null if the transaction did not write the LiquidityPool resource';
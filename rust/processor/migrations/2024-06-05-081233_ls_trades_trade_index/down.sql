@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM public.ls_trades WHERE trade_index > 0;
+ALTER TABLE public.ls_trades DROP CONSTRAINT ls_trades_pk;
+ALTER TABLE public.ls_trades ADD CONSTRAINT ls_trades_pk PRIMARY KEY ("version");
+ALTER TABLE public.ls_trades DROP COLUMN trade_index;
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_trades.route IS '[{pool_id, coin_in, amount_in, coin_out, amount_out}] of the SwapEvents of the tx in event order';
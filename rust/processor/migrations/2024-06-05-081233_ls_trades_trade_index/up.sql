@@ -0,0 +1,8 @@
+ALTER TABLE public.ls_trades ADD COLUMN trade_index int4 NOT NULL DEFAULT 0;
+ALTER TABLE public.ls_trades DROP CONSTRAINT ls_trades_pk;
+ALTER TABLE public.ls_trades ADD CONSTRAINT ls_trades_pk PRIMARY KEY ("version", trade_index);
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_trades.trade_index IS 'index of the trade in the tx, a tx has several trades if a swap does not sell the coin the swap before bought';
+COMMENT ON COLUMN public.ls_trades.route IS '[{pool_id, coin_in, amount_in, coin_out, amount_out}] of the chained SwapEvents of the trade in event order';
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.ls_candles RENAME COLUMN last_event_index TO last_sequence_number;
+ALTER TABLE public.ls_pool_daily_stats RENAME COLUMN last_swap_event_index TO last_swap_sequence_number;
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_candles."open" IS 'y_val / x_val of the pool after the first swap of the interval';
+COMMENT ON COLUMN public.ls_candles.last_sequence_number IS 'sequence number of the last SwapEvent merged into the candle';
+COMMENT ON COLUMN public.ls_pool_daily_stats.last_swap_sequence_number IS 'sequence number of the last SwapEvent merged into the day';
@@ -0,0 +1,8 @@
+ALTER TABLE public.ls_candles RENAME COLUMN last_sequence_number TO last_event_index;
+ALTER TABLE public.ls_pool_daily_stats RENAME COLUMN last_swap_sequence_number TO last_swap_event_index;
+
+-- Column comments
+
+COMMENT ON COLUMN public.ls_candles."open" IS 'price of X in Y, in whole coins, from the reserves after the first swap of the interval';
+COMMENT ON COLUMN public.ls_candles.last_event_index IS 'index in the tx of the last SwapEvent merged into the candle';
+COMMENT ON COLUMN public.ls_pool_daily_stats.last_swap_event_index IS 'index in the tx of the last SwapEvent merged into the day';
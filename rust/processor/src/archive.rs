@@ -0,0 +1,533 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Local archives of the transaction stream.
+//!
+//! An archive is a directory of files, each holding a sequence of length-delimited
+//! `TransactionsResponse` protobufs, optionally compressed as a whole. The compression is taken
+//! from the file extension (`.gz`, `.zst`). Files are read in file name order.
+//!
+//! Archives recorded by the processor name their files after the version range they hold and
+//! keep a `manifest.json` with the chain id and a checksum per file.
+use anyhow::{Context, Result};
+use aptos_protos::indexer::v1::TransactionsResponse;
+use kanal::AsyncReceiver;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveCompression {
+    None,
+    #[default]
+    Gzip,
+    Zstd,
+}
+
+impl ArchiveCompression {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => ArchiveCompression::Gzip,
+            Some("zst") | Some("zstd") => ArchiveCompression::Zstd,
+            _ => ArchiveCompression::None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveCompression::None => "",
+            ArchiveCompression::Gzip => ".gz",
+            ArchiveCompression::Zstd => ".zst",
+        }
+    }
+
+    pub fn compress(&self, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            ArchiveCompression::None => Ok(bytes),
+            ArchiveCompression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(&bytes)?;
+                Ok(encoder.finish()?)
+            },
+            ArchiveCompression::Zstd => Ok(zstd::encode_all(bytes.as_slice(), 0)?),
+        }
+    }
+
+    pub fn decompress(&self, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            ArchiveCompression::None => Ok(bytes),
+            ArchiveCompression::Gzip => {
+                let mut decompressed = vec![];
+                flate2::read::GzDecoder::new(bytes.as_slice())
+                    .read_to_end(&mut decompressed)
+                    .context("Failed to decompress gzip archive")?;
+                Ok(decompressed)
+            },
+            ArchiveCompression::Zstd => {
+                zstd::decode_all(bytes.as_slice()).context("Failed to decompress zstd archive")
+            },
+        }
+    }
+}
+
+/// Archive files in the directory, in the order they are replayed. Hidden files, directories and
+/// the manifest are skipped.
+pub fn list_archive_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Could not read archive directory {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let is_hidden = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .map_or(true, |file_name| file_name.starts_with('.'));
+        let is_manifest = path
+            .extension()
+            .map_or(false, |extension| extension == "json");
+        if path.is_file() && !is_hidden && !is_manifest {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Decodes all the batches of an archive file, after checking it against the checksum from the
+/// manifest if there is one.
+pub fn read_archive_file(
+    path: &Path,
+    expected_sha256: Option<&str>,
+) -> Result<Vec<TransactionsResponse>> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Could not read archive file {}", path.display()))?;
+    if let Some(expected_sha256) = expected_sha256 {
+        let sha256 = hex::encode(Sha256::digest(&bytes));
+        anyhow::ensure!(
+            sha256 == expected_sha256,
+            "Checksum mismatch for archive file {}: expected {}, got {}",
+            path.display(),
+            expected_sha256,
+            sha256
+        );
+    }
+    let bytes = ArchiveCompression::from_path(path)
+        .decompress(bytes)
+        .with_context(|| format!("Corrupted archive file {}", path.display()))?;
+    let mut buf = bytes.as_slice();
+    let mut batches = vec![];
+    while !buf.is_empty() {
+        let batch = TransactionsResponse::decode_length_delimited(&mut buf).with_context(|| {
+            format!(
+                "Corrupted batch #{} in archive file {}",
+                batches.len(),
+                path.display()
+            )
+        })?;
+        batches.push(batch);
+    }
+    Ok(batches)
+}
+
+/// Replays the batches of an archive directory, limited to `[starting_version, ending_version]`.
+pub struct ArchiveReader {
+    files: VecDeque<PathBuf>,
+    checksums: HashMap<String, String>,
+    batches: VecDeque<TransactionsResponse>,
+    starting_version: u64,
+    ending_version: Option<u64>,
+    finished: bool,
+}
+
+impl ArchiveReader {
+    pub fn new(dir: &Path, starting_version: u64, ending_version: Option<u64>) -> Result<Self> {
+        let checksums = ArchiveManifest::load(dir)?
+            .map(|manifest| {
+                manifest
+                    .files
+                    .into_iter()
+                    .map(|file| (file.file_name, file.sha256))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            files: list_archive_files(dir)?.into(),
+            checksums,
+            batches: VecDeque::new(),
+            starting_version,
+            ending_version,
+            finished: false,
+        })
+    }
+
+    /// Next non-empty batch, or `None` once the archive is exhausted or the ending version was
+    /// passed. Transactions outside of the version range are dropped.
+    pub async fn next(&mut self) -> Option<Result<TransactionsResponse>> {
+        while !self.finished {
+            let mut batch = match self.batches.pop_front() {
+                Some(batch) => batch,
+                None => {
+                    let path = self.files.pop_front()?;
+                    let expected_sha256 = path
+                        .file_name()
+                        .and_then(|file_name| file_name.to_str())
+                        .and_then(|file_name| self.checksums.get(file_name))
+                        .cloned();
+                    // Decompressing and decoding a whole file is CPU bound.
+                    let batches = match tokio::task::spawn_blocking(move || {
+                        read_archive_file(&path, expected_sha256.as_deref())
+                    })
+                    .await
+                    {
+                        Ok(Ok(batches)) => batches,
+                        Ok(Err(e)) => return Some(Err(e)),
+                        Err(e) => return Some(Err(e.into())),
+                    };
+                    self.batches = batches.into();
+                    continue;
+                },
+            };
+            if let (Some(ending_version), Some(first)) =
+                (self.ending_version, batch.transactions.first())
+            {
+                if first.version > ending_version {
+                    self.finished = true;
+                    break;
+                }
+            }
+            let starting_version = self.starting_version;
+            let ending_version = self.ending_version;
+            batch.transactions.retain(|transaction| {
+                transaction.version >= starting_version
+                    && ending_version
+                        .map_or(true, |ending_version| transaction.version <= ending_version)
+            });
+            if !batch.transactions.is_empty() {
+                return Some(Ok(batch));
+            }
+        }
+        None
+    }
+}
+
+/// Settings of the record mode, which archives every batch received from the stream.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecordConfig {
+    /// Directory of the archive. It is created if needed; recording into an existing archive
+    /// appends to it.
+    pub path: PathBuf,
+
+    /// Defaults to gzip.
+    #[serde(default)]
+    pub compression: ArchiveCompression,
+
+    /// Size of the uncompressed batches after which a new file is started. Defaults to 64.
+    #[serde(default = "RecordConfig::default_max_file_size_in_mb")]
+    pub max_file_size_in_mb: u64,
+}
+
+impl RecordConfig {
+    pub const fn default_max_file_size_in_mb() -> u64 {
+        64
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ArchiveManifest {
+    pub chain_id: Option<u64>,
+    pub start_version: Option<u64>,
+    pub end_version: Option<u64>,
+    pub files: Vec<ArchiveFileInfo>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchiveFileInfo {
+    pub file_name: String,
+    pub start_version: u64,
+    pub end_version: u64,
+    pub num_transactions: u64,
+    pub size_in_bytes: u64,
+    /// Hex encoded SHA-256 of the file as written, i.e. after compression.
+    pub sha256: String,
+}
+
+impl ArchiveManifest {
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let manifest = std::fs::read(&path)
+            .with_context(|| format!("Could not read archive manifest {}", path.display()))?;
+        serde_json::from_slice(&manifest)
+            .with_context(|| format!("Invalid archive manifest {}", path.display()))
+            .map(Some)
+    }
+
+    /// Writes the manifest next to the archive files. The previous manifest is replaced
+    /// atomically, so it never references a file that is not complete.
+    fn save(&self, dir: &Path) -> Result<()> {
+        let tmp_path = dir.join(format!(".{}.tmp", MANIFEST_FILE_NAME));
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp_path, dir.join(MANIFEST_FILE_NAME))?;
+        Ok(())
+    }
+}
+
+/// Writes batches into rotating archive files named `<start_version>-<end_version>.pb<ext>`.
+pub struct ArchiveWriter {
+    dir: PathBuf,
+    compression: ArchiveCompression,
+    max_file_size_in_bytes: usize,
+    manifest: ArchiveManifest,
+    buffer: Vec<u8>,
+    buffer_start_version: Option<u64>,
+    buffer_end_version: u64,
+    buffer_num_transactions: u64,
+}
+
+impl ArchiveWriter {
+    pub fn new(config: &RecordConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.path).with_context(|| {
+            format!(
+                "Could not create archive directory {}",
+                config.path.display()
+            )
+        })?;
+        Ok(Self {
+            dir: config.path.clone(),
+            compression: config.compression,
+            max_file_size_in_bytes: (config.max_file_size_in_mb as usize) * 1024 * 1024,
+            manifest: ArchiveManifest::load(&config.path)?.unwrap_or_default(),
+            buffer: vec![],
+            buffer_start_version: None,
+            buffer_end_version: 0,
+            buffer_num_transactions: 0,
+        })
+    }
+
+    /// Last version that is archived or buffered.
+    fn last_version(&self) -> Option<u64> {
+        match self.buffer_start_version {
+            Some(_) => Some(self.buffer_end_version),
+            None => self.manifest.end_version,
+        }
+    }
+
+    /// Buffers the batch and starts a new file once the buffer is full. Transactions that are
+    /// already in the archive are dropped, e.g. when the stream is recorded again after a restart.
+    /// A batch that does not continue the archive is an error, replays would have a gap.
+    pub fn append(&mut self, mut batch: TransactionsResponse) -> Result<()> {
+        match (self.manifest.chain_id, batch.chain_id) {
+            (Some(chain_id), Some(batch_chain_id)) => anyhow::ensure!(
+                chain_id == batch_chain_id,
+                "Archive {} is for chain {} but the stream is for chain {}",
+                self.dir.display(),
+                chain_id,
+                batch_chain_id
+            ),
+            (None, batch_chain_id) => self.manifest.chain_id = batch_chain_id,
+            _ => {},
+        }
+        if let Some(last_version) = self.last_version() {
+            batch
+                .transactions
+                .retain(|transaction| transaction.version > last_version);
+        }
+        let (first, last) = match (batch.transactions.first(), batch.transactions.last()) {
+            (Some(first), Some(last)) => (first.version, last.version),
+            _ => return Ok(()),
+        };
+        if let Some(last_version) = self.last_version() {
+            anyhow::ensure!(
+                first == last_version + 1,
+                "Archive {} ends at version {} but the stream continues at version {}, \
+                 record to a new directory",
+                self.dir.display(),
+                last_version,
+                first
+            );
+        }
+        batch.encode_length_delimited(&mut self.buffer)?;
+        self.buffer_start_version.get_or_insert(first);
+        self.buffer_end_version = last;
+        self.buffer_num_transactions += batch.transactions.len() as u64;
+        if self.buffer.len() >= self.max_file_size_in_bytes {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered batches to a new file and records it in the manifest.
+    pub fn flush(&mut self) -> Result<()> {
+        let start_version = match self.buffer_start_version.take() {
+            Some(start_version) => start_version,
+            None => return Ok(()),
+        };
+        let end_version = self.buffer_end_version;
+        let bytes = self
+            .compression
+            .compress(std::mem::take(&mut self.buffer))?;
+        let file_name = format!(
+            "{:020}-{:020}.pb{}",
+            start_version,
+            end_version,
+            self.compression.extension()
+        );
+        std::fs::write(self.dir.join(&file_name), &bytes).with_context(|| {
+            format!(
+                "Could not write archive file {}",
+                self.dir.join(&file_name).display()
+            )
+        })?;
+        self.manifest.files.push(ArchiveFileInfo {
+            file_name,
+            start_version,
+            end_version,
+            num_transactions: std::mem::take(&mut self.buffer_num_transactions),
+            size_in_bytes: bytes.len() as u64,
+            sha256: hex::encode(Sha256::digest(&bytes)),
+        });
+        self.manifest.start_version.get_or_insert(start_version);
+        self.manifest.end_version = Some(end_version);
+        self.manifest.save(&self.dir)
+    }
+}
+
+/// Archives the batches it receives until the channel is closed, then flushes the last file.
+pub async fn create_recorder_loop(
+    receiver: AsyncReceiver<TransactionsResponse>,
+    mut writer: ArchiveWriter,
+) -> Result<()> {
+    info!(
+        archive_path = writer.dir.display().to_string(),
+        "[Parser] Recording transactions to archive"
+    );
+    while let Ok(batch) = receiver.recv().await {
+        // Compressing and writing a file is blocking.
+        writer =
+            tokio::task::spawn_blocking(move || writer.append(batch).map(|_| writer)).await??;
+    }
+    let writer = tokio::task::spawn_blocking(move || writer.flush().map(|_| writer)).await??;
+    info!(
+        archive_path = writer.dir.display().to_string(),
+        start_version = writer.manifest.start_version,
+        end_version = writer.manifest.end_version,
+        "[Parser] Finished recording transactions to archive"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_protos::transaction::v1::Transaction;
+
+    fn batch(versions: std::ops::RangeInclusive<u64>) -> TransactionsResponse {
+        TransactionsResponse {
+            transactions: versions
+                .map(|version| Transaction {
+                    version,
+                    ..Transaction::default()
+                })
+                .collect(),
+            chain_id: Some(1),
+        }
+    }
+
+    fn versions(batch: &TransactionsResponse) -> Vec<u64> {
+        batch.transactions.iter().map(|t| t.version).collect()
+    }
+
+    #[tokio::test]
+    async fn test_replays_version_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut plain = vec![];
+        batch(0..=4).encode_length_delimited(&mut plain).unwrap();
+        batch(5..=9).encode_length_delimited(&mut plain).unwrap();
+        std::fs::write(dir.path().join("0.pb"), plain).unwrap();
+
+        let mut gzipped = vec![];
+        batch(10..=14)
+            .encode_length_delimited(&mut gzipped)
+            .unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&gzipped).unwrap();
+        std::fs::write(dir.path().join("1.pb.gz"), encoder.finish().unwrap()).unwrap();
+
+        let mut reader = ArchiveReader::new(dir.path(), 3, Some(11)).unwrap();
+        let mut replayed = vec![];
+        while let Some(batch) = reader.next().await {
+            replayed.push(versions(&batch.unwrap()));
+        }
+        assert_eq!(
+            replayed,
+            vec![vec![3, 4], vec![5, 6, 7, 8, 9], vec![10, 11]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recorded_archive_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RecordConfig {
+            path: dir.path().to_path_buf(),
+            compression: ArchiveCompression::Zstd,
+            max_file_size_in_mb: 0,
+        };
+        let mut writer = ArchiveWriter::new(&config).unwrap();
+        writer.append(batch(0..=4)).unwrap();
+        writer.append(batch(5..=9)).unwrap();
+        writer.flush().unwrap();
+
+        // Recording again skips what is already archived.
+        let mut writer = ArchiveWriter::new(&config).unwrap();
+        writer.append(batch(8..=12)).unwrap();
+        writer.flush().unwrap();
+
+        let manifest = ArchiveManifest::load(dir.path()).unwrap().unwrap();
+        assert_eq!(manifest.chain_id, Some(1));
+        assert_eq!(manifest.start_version, Some(0));
+        assert_eq!(manifest.end_version, Some(12));
+        assert_eq!(manifest.files.len(), 3);
+        assert_eq!(
+            manifest.files[0].file_name,
+            "00000000000000000000-00000000000000000004.pb.zst"
+        );
+
+        let mut reader = ArchiveReader::new(dir.path(), 0, None).unwrap();
+        let mut replayed = vec![];
+        while let Some(batch) = reader.next().await {
+            replayed.extend(versions(&batch.unwrap()));
+        }
+        assert_eq!(replayed, (0..=12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_append_refuses_a_gap() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RecordConfig {
+            path: dir.path().to_path_buf(),
+            compression: ArchiveCompression::Zstd,
+            max_file_size_in_mb: 0,
+        };
+        let mut writer = ArchiveWriter::new(&config).unwrap();
+        writer.append(batch(0..=4)).unwrap();
+        writer.flush().unwrap();
+
+        // E.g. recording again with a starting version past the end of the archive
+        let mut writer = ArchiveWriter::new(&config).unwrap();
+        assert!(writer.append(batch(7..=9)).is_err());
+        writer.append(batch(5..=9)).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Maintenance commands for the tables of the `ls_processor`.
+
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand};
+use processor::{
+    processors::ls_processor::{backfill::backfill, bench::bench_inserts, reconcile::reconcile},
+    utils::database::new_db_pool,
+};
+
+#[derive(Debug, Parser)]
+struct Args {
+    /// Same as `postgres_connection_string` of the processor config.
+    #[clap(long)]
+    postgres_connection_string: String,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Report pools whose reserves in `ls_pools` differ from the sum of their `ls_events`.
+    Reconcile,
+    /// Derive the gross amounts and reserves columns of `ls_events` rows from their stored event
+    /// body and `ls_pool_snapshots`.
+    Backfill {
+        /// Only events after this version
+        #[clap(long, default_value_t = 0)]
+        from_version: i64,
+        /// Transactions per query
+        #[clap(long, default_value_t = 10_000)]
+        batch_size: i64,
+    },
+    /// Compare the throughput of the inserts of `ls_pools` and `ls_events` before and after they
+    /// moved to `execute_in_chunks`. Runs in a transaction that is rolled back.
+    BenchInserts {
+        #[clap(long, default_value_t = 1_000)]
+        pools: usize,
+        #[clap(long, default_value_t = 10_000)]
+        events: usize,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let pool = new_db_pool(&args.postgres_connection_string, Some(1)).await?;
+    let mut conn = pool.get().await?;
+
+    match args.command {
+        Command::Reconcile => {
+            let divergences = reconcile(&mut conn).await?;
+            for divergence in &divergences {
+                println!("{divergence}");
+            }
+            if !divergences.is_empty() {
+                bail!("{} pools diverge", divergences.len());
+            }
+            println!("All pools add up");
+        },
+        Command::Backfill {
+            from_version,
+            batch_size,
+        } => {
+            let report = backfill(&mut conn, from_version, batch_size).await?;
+            println!(
+                "{} events up to version {}: {} with amounts, {} with reserves",
+                report.events, report.last_version, report.with_amounts, report.with_reserves
+            );
+        },
+        Command::BenchInserts { pools, events } => {
+            for bench in bench_inserts(&mut conn, pools, events).await? {
+                println!("{bench}");
+            }
+        },
+    }
+
+    Ok(())
+}
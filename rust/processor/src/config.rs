@@ -2,23 +2,45 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    gap_detector::DEFAULT_GAP_DETECTION_BATCH_SIZE, processors::ProcessorConfig, worker::Worker,
+    archive::RecordConfig,
+    gap_detector::DEFAULT_GAP_DETECTION_BATCH_SIZE,
+    grpc_stream::{DataServiceEndpoint, TransactionSource},
+    processors::ProcessorConfig,
+    utils::retry::{exponential_backoff, BatchRetryConfig},
+    worker::Worker,
 };
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use server_framework::RunnableConfig;
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
 use url::Url;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct IndexerGrpcProcessorConfig {
-    pub processor_config: ProcessorConfig,
+    pub processor_config: Option<ProcessorConfig>,
+    /// Additional processors that share the same stream with `processor_config`.
+    #[serde(default)]
+    pub processor_configs: Vec<ProcessorConfig>,
     pub postgres_connection_string: String,
-    // TODO: Add TLS support.
     pub indexer_grpc_data_service_address: Url,
+    /// Data service endpoints to fail over to, in order of preference, when
+    /// `indexer_grpc_data_service_address` is unhealthy.
+    #[serde(default)]
+    pub indexer_grpc_data_service_fallback_addresses: Vec<Url>,
     #[serde(flatten)]
     pub grpc_http2_config: IndexerGrpcHttp2Config,
+    #[serde(default)]
+    pub grpc_reconnection_config: GrpcReconnectionConfig,
+    /// TLS settings for the data service. Without them `https` addresses are verified against
+    /// the system roots and `http` addresses are used in plaintext.
+    pub grpc_tls_config: Option<GrpcTlsConfig>,
+    /// Where transactions are read from. Defaults to the data service.
+    #[serde(default)]
+    pub transaction_source: TransactionSourceConfig,
+    /// Record every batch received from the stream into a local archive.
+    pub record_config: Option<RecordConfig>,
     pub auth_token: String,
     pub starting_version: Option<u64>,
     pub ending_version: Option<u64>,
@@ -27,22 +49,58 @@ pub struct IndexerGrpcProcessorConfig {
     #[serde(default = "IndexerGrpcProcessorConfig::default_gap_detection_batch_size")]
     pub gap_detection_batch_size: u64,
     pub enable_verbose_logging: Option<bool>,
+    #[serde(default)]
+    pub batch_retry_config: BatchRetryConfig,
+    pub enable_atomic_batch_writes: Option<bool>,
 }
 
 impl IndexerGrpcProcessorConfig {
     pub const fn default_gap_detection_batch_size() -> u64 {
         DEFAULT_GAP_DETECTION_BATCH_SIZE
     }
+
+    /// All the processors to run, `processor_config` first.
+    pub fn all_processor_configs(&self) -> Vec<ProcessorConfig> {
+        self.processor_config
+            .iter()
+            .chain(self.processor_configs.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// All the data service endpoints, `indexer_grpc_data_service_address` first.
+    pub fn data_service_addresses(&self) -> Vec<Url> {
+        std::iter::once(&self.indexer_grpc_data_service_address)
+            .chain(self.indexer_grpc_data_service_fallback_addresses.iter())
+            .cloned()
+            .collect()
+    }
 }
 
 #[async_trait::async_trait]
 impl RunnableConfig for IndexerGrpcProcessorConfig {
     async fn run(&self) -> Result<()> {
+        let transaction_source = match &self.transaction_source {
+            TransactionSourceConfig::Grpc => TransactionSource::Grpc {
+                endpoints: self
+                    .data_service_addresses()
+                    .into_iter()
+                    .map(|address| DataServiceEndpoint::new(address, self.grpc_tls_config.as_ref()))
+                    .collect::<Result<Vec<_>>>()
+                    .context("Invalid data service TLS config")?,
+                reconnection_config: self.grpc_reconnection_config.clone(),
+            },
+            TransactionSourceConfig::File { path } => TransactionSource::File(
+                path.canonicalize()
+                    .with_context(|| format!("Could not open archive {}", path.display()))?,
+            ),
+        };
         let mut worker = Worker::new(
-            self.processor_config.clone(),
+            self.all_processor_configs(),
             self.postgres_connection_string.clone(),
-            self.indexer_grpc_data_service_address.clone(),
+            transaction_source,
             self.grpc_http2_config.clone(),
+            self.record_config.clone(),
             self.auth_token.clone(),
             self.starting_version,
             self.ending_version,
@@ -50,21 +108,27 @@ impl RunnableConfig for IndexerGrpcProcessorConfig {
             self.db_pool_size,
             self.gap_detection_batch_size,
             self.enable_verbose_logging,
+            self.batch_retry_config.clone(),
+            self.enable_atomic_batch_writes,
         )
         .await
         .context("Failed to build worker")?;
-        worker.run().await;
+        worker.run().await.context("Worker stopped with an error")?;
         Ok(())
     }
 
     fn get_server_name(&self) -> String {
         // Get the part before the first _ and trim to 12 characters.
-        let before_underscore = self
-            .processor_config
-            .name()
-            .split('_')
-            .next()
-            .unwrap_or("unknown");
+        let processor_configs = self.all_processor_configs();
+        let before_underscore = match processor_configs.as_slice() {
+            [processor_config] => processor_config
+                .name()
+                .split('_')
+                .next()
+                .unwrap_or("unknown"),
+            [] => "unknown",
+            _ => "multi",
+        };
         before_underscore[..before_underscore.len().min(12)].to_string()
     }
 }
@@ -99,3 +163,134 @@ impl Default for IndexerGrpcHttp2Config {
         }
     }
 }
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct GrpcReconnectionConfig {
+    /// Delay before the first reconnection attempt in milliseconds. Defaults to 100.
+    pub initial_delay_in_ms: u64,
+
+    /// Upper bound for the delay between two reconnection attempts in milliseconds.
+    /// Defaults to 10000.
+    pub max_delay_in_ms: u64,
+
+    /// Factor applied to the delay after every failed attempt. Defaults to 2.
+    pub backoff_multiplier: f64,
+
+    /// Fraction of the delay that is randomized, between 0 and 1. Defaults to 0.2.
+    pub jitter_ratio: f64,
+
+    /// How long the fetcher keeps trying to get a working stream, across all endpoints, before
+    /// it gives up. The budget starts with the first failure and is reset once a batch is
+    /// received. Defaults to 300.
+    pub max_reconnection_time_in_secs: u64,
+
+    /// How long a failed endpoint is ranked below healthy ones. Defaults to 60.
+    pub endpoint_cooldown_in_secs: u64,
+}
+
+impl GrpcReconnectionConfig {
+    /// Delay to wait after the given failed attempt (1-based) before reconnecting.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        exponential_backoff(
+            self.initial_delay_in_ms,
+            self.max_delay_in_ms,
+            self.backoff_multiplier,
+            self.jitter_ratio,
+            attempt,
+        )
+    }
+
+    pub fn max_reconnection_time(&self) -> Duration {
+        Duration::from_secs(self.max_reconnection_time_in_secs)
+    }
+
+    pub fn endpoint_cooldown(&self) -> Duration {
+        Duration::from_secs(self.endpoint_cooldown_in_secs)
+    }
+}
+
+impl Default for GrpcReconnectionConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay_in_ms: 100,
+            max_delay_in_ms: 10_000,
+            backoff_multiplier: 2.0,
+            jitter_ratio: 0.2,
+            max_reconnection_time_in_secs: 300,
+            endpoint_cooldown_in_secs: 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransactionSourceConfig {
+    /// Stream from `indexer_grpc_data_service_address` and its fallbacks.
+    #[default]
+    Grpc,
+    /// Replay batches archived in a local directory, e.g. to run a processor against fixtures.
+    /// The processor stops once the archive, or the range up to `ending_version`, was replayed.
+    File { path: PathBuf },
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct GrpcTlsConfig {
+    /// PEM bundle of CA certificates trusted in addition to the system roots, e.g. a private CA.
+    pub ca_certificate_path: Option<PathBuf>,
+
+    /// PEM certificate presented to the data service for mutual TLS. Requires `client_key_path`.
+    pub client_certificate_path: Option<PathBuf>,
+
+    /// PEM private key of `client_certificate_path`.
+    pub client_key_path: Option<PathBuf>,
+
+    /// Name sent as SNI and expected in the server certificate, when it differs from the host
+    /// of the data service address.
+    pub domain_name: Option<String>,
+
+    /// Allow `http` data service addresses. Once this section is set TLS is required otherwise.
+    pub allow_plaintext: bool,
+}
+
+impl GrpcTlsConfig {
+    /// Loads the certificates into a tonic TLS config.
+    pub fn client_tls_config(&self) -> Result<ClientTlsConfig> {
+        let mut tls_config = ClientTlsConfig::new();
+        if let Some(ca_certificate_path) = &self.ca_certificate_path {
+            let ca_certificate = std::fs::read(ca_certificate_path).with_context(|| {
+                format!(
+                    "Could not read CA certificate {}",
+                    ca_certificate_path.display()
+                )
+            })?;
+            tls_config = tls_config.ca_certificate(Certificate::from_pem(ca_certificate));
+        }
+        match (&self.client_certificate_path, &self.client_key_path) {
+            (Some(client_certificate_path), Some(client_key_path)) => {
+                let client_certificate =
+                    std::fs::read(client_certificate_path).with_context(|| {
+                        format!(
+                            "Could not read client certificate {}",
+                            client_certificate_path.display()
+                        )
+                    })?;
+                let client_key = std::fs::read(client_key_path).with_context(|| {
+                    format!("Could not read client key {}", client_key_path.display())
+                })?;
+                tls_config =
+                    tls_config.identity(Identity::from_pem(client_certificate, client_key));
+            },
+            (None, None) => {},
+            _ => anyhow::bail!("client_certificate_path and client_key_path must be set together"),
+        }
+        if let Some(domain_name) = &self.domain_name {
+            tls_config = tls_config.domain_name(domain_name.clone());
+        }
+        Ok(tls_config)
+    }
+}
@@ -3,7 +3,7 @@
 
 use crate::{
     processors::{ProcessingResult, Processor, ProcessorTrait},
-    worker::PROCESSOR_SERVICE_TYPE,
+    worker::{WorkerError, PROCESSOR_SERVICE_TYPE},
 };
 use ahash::AHashMap;
 use kanal::AsyncReceiver;
@@ -68,12 +68,15 @@ impl GapDetector {
     }
 }
 
+/// Tracks processed batches and periodically stores the last contiguous version in
+/// `processor_status`. When the channel is closed (i.e. the worker is shutting down) the latest
+/// contiguous version is flushed before returning, so no progress is lost to the update throttle.
 pub async fn create_gap_detector_status_tracker_loop(
     gap_detector_receiver: AsyncReceiver<ProcessingResult>,
     processor: Arc<Processor>,
     starting_version: u64,
     gap_detection_batch_size: u64,
-) {
+) -> Result<(), WorkerError> {
     let processor_name = processor.name();
     info!(
         processor_name = processor_name,
@@ -83,6 +86,8 @@ pub async fn create_gap_detector_status_tracker_loop(
 
     let mut gap_detector = GapDetector::new(starting_version);
     let mut last_update_time = std::time::Instant::now();
+    // Last batch that was processed contiguously but not yet written to `processor_status`
+    let mut pending_status_update: Option<ProcessingResult> = None;
 
     loop {
         let result = match gap_detector_receiver.recv().await {
@@ -94,7 +99,16 @@ pub async fn create_gap_detector_status_tracker_loop(
                     error = ?e,
                     "[Parser] Gap detector channel has been closed",
                 );
-                return;
+                if let Some(batch) = pending_status_update {
+                    update_processor_status(&processor, &batch).await?;
+                    info!(
+                        processor_name,
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        last_success_version = batch.end_version,
+                        "[Parser] Flushed last processed version",
+                    );
+                }
+                return Ok(());
             },
         };
 
@@ -105,23 +119,24 @@ pub async fn create_gap_detector_status_tracker_loop(
                         processor_name,
                         gap_start_version = res.next_version_to_process,
                         num_gaps = res.num_gaps,
-                        "[Parser] Processed {gap_detection_batch_size} batches with a gap. Panicking."
-                    );
-                    panic!(
-                        "[Parser] Processed {gap_detection_batch_size} batches with a gap. Panicking."
+                        "[Parser] Processed {gap_detection_batch_size} batches with a gap. Stopping."
                     );
+                    if let Some(batch) = pending_status_update {
+                        update_processor_status(&processor, &batch).await?;
+                    }
+                    return Err(WorkerError::GapDetectionLimitReached {
+                        gap_start_version: res.next_version_to_process,
+                        num_gaps: res.num_gaps,
+                    });
                 }
 
                 if let Some(res_last_success_batch) = res.last_success_batch {
                     if last_update_time.elapsed().as_secs() >= UPDATE_PROCESSOR_STATUS_SECS {
-                        processor
-                            .update_last_processed_version(
-                                res_last_success_batch.end_version,
-                                res_last_success_batch.last_transaction_timstamp.clone(),
-                            )
-                            .await
-                            .unwrap();
+                        update_processor_status(&processor, &res_last_success_batch).await?;
                         last_update_time = std::time::Instant::now();
+                        pending_status_update = None;
+                    } else {
+                        pending_status_update = Some(res_last_success_batch);
                     }
                 }
             },
@@ -130,14 +145,24 @@ pub async fn create_gap_detector_status_tracker_loop(
                     processor_name,
                     service_type = PROCESSOR_SERVICE_TYPE,
                     error = ?e,
-                    "[Parser] Gap detector task has panicked"
+                    "[Parser] Gap detector task has failed"
                 );
-                panic!();
+                return Err(WorkerError::TaskFailed(e));
             },
         }
     }
 }
 
+async fn update_processor_status(
+    processor: &Processor,
+    batch: &ProcessingResult,
+) -> Result<(), WorkerError> {
+    processor
+        .update_last_processed_version(batch.end_version, batch.last_transaction_timstamp.clone())
+        .await
+        .map_err(WorkerError::Database)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1,21 +1,29 @@
 use crate::{
+    archive::ArchiveReader,
+    config::{GrpcReconnectionConfig, GrpcTlsConfig},
     utils::{
         counters::{
-            ProcessorStep, FETCHER_THREAD_CHANNEL_SIZE, LATEST_PROCESSED_VERSION,
-            NUM_TRANSACTIONS_PROCESSED_COUNT, PROCESSED_BYTES_COUNT, TRANSACTION_UNIX_TIMESTAMP,
+            ProcessorStep, FETCHER_THREAD_CHANNEL_SIZE, GRPC_ENDPOINT_FAILURE_COUNT,
+            LATEST_PROCESSED_VERSION, NUM_TRANSACTIONS_PROCESSED_COUNT, PROCESSED_BYTES_COUNT,
+            TRANSACTION_UNIX_TIMESTAMP,
         },
         util::{timestamp_to_iso, timestamp_to_unixtime},
     },
-    worker::TransactionsPBResponse,
+    worker::{TransactionsPBResponse, WorkerError},
 };
+use anyhow::Context;
 use aptos_protos::indexer::v1::{
     raw_data_client::RawDataClient, GetTransactionsRequest, TransactionsResponse,
 };
 use futures_util::StreamExt;
 use kanal::AsyncSender;
 use prost::Message;
-use std::time::Duration;
-use tonic::{Response, Streaming};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tonic::{transport::ClientTlsConfig, Response, Streaming};
 use tracing::{error, info};
 use url::Url;
 
@@ -26,8 +34,6 @@ const GRPC_API_GATEWAY_API_KEY_HEADER: &str = "authorization";
 const GRPC_REQUEST_NAME_HEADER: &str = "x-aptos-request-name";
 /// GRPC connection id
 const GRPC_CONNECTION_ID: &str = "x-aptos-connection-id";
-/// We will try to reconnect to GRPC 5 times in case upstream connection is being updated
-pub const RECONNECTION_MAX_RETRIES: u64 = 65;
 /// 256MB
 pub const MAX_RESPONSE_SIZE: usize = 1024 * 1024 * 256;
 
@@ -56,13 +62,14 @@ pub fn grpc_request_builder(
 
 pub async fn get_stream(
     indexer_grpc_data_service_address: Url,
+    client_tls_config: Option<ClientTlsConfig>,
     indexer_grpc_http2_ping_interval: Duration,
     indexer_grpc_http2_ping_timeout: Duration,
     starting_version: u64,
     ending_version: Option<u64>,
     auth_token: String,
     processor_name: String,
-) -> Response<Streaming<TransactionsResponse>> {
+) -> anyhow::Result<Response<Streaming<TransactionsResponse>>> {
     info!(
         processor_name = processor_name,
         service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
@@ -75,20 +82,17 @@ pub async fn get_stream(
     let channel = tonic::transport::Channel::from_shared(
         indexer_grpc_data_service_address.to_string(),
     )
-    .expect(
+    .context(
         "[Parser] Failed to build GRPC channel, perhaps because the data service URL is invalid",
-    )
+    )?
     .http2_keep_alive_interval(indexer_grpc_http2_ping_interval)
     .keep_alive_timeout(indexer_grpc_http2_ping_timeout);
 
-    // If the scheme is https, add a TLS config.
-    let channel = if indexer_grpc_data_service_address.scheme() == "https" {
-        let config = tonic::transport::channel::ClientTlsConfig::new();
-        channel
+    let channel = match client_tls_config {
+        Some(config) => channel
             .tls_config(config)
-            .expect("[Parser] Failed to create TLS config")
-    } else {
-        channel
+            .context("[Parser] Failed to create TLS config")?,
+        None => channel,
     };
 
     info!(
@@ -115,7 +119,7 @@ pub async fn get_stream(
                 error = ?e,
                 "[Parser] Error connecting to GRPC client"
             );
-            panic!("[Parser] Error connecting to GRPC client");
+            return Err(e).context("[Parser] Error connecting to GRPC client");
         },
     };
     let count = ending_version.map(|v| (v as i64 - starting_version as i64 + 1) as u64);
@@ -132,16 +136,17 @@ pub async fn get_stream(
     rpc_client
         .get_transactions(request)
         .await
-        .expect("[Parser] Failed to get grpc response. Is the server running?")
+        .context("[Parser] Failed to get grpc response. Is the server running?")
 }
 
 pub async fn get_chain_id(
     indexer_grpc_data_service_address: Url,
+    client_tls_config: Option<ClientTlsConfig>,
     indexer_grpc_http2_ping_interval: Duration,
     indexer_grpc_http2_ping_timeout: Duration,
     auth_token: String,
     processor_name: String,
-) -> u64 {
+) -> anyhow::Result<u64> {
     info!(
         processor_name = processor_name,
         service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
@@ -150,6 +155,7 @@ pub async fn get_chain_id(
     );
     let response = get_stream(
         indexer_grpc_data_service_address.clone(),
+        client_tls_config,
         indexer_grpc_http2_ping_interval,
         indexer_grpc_http2_ping_timeout,
         1,
@@ -157,11 +163,8 @@ pub async fn get_chain_id(
         auth_token.clone(),
         processor_name.to_string(),
     )
-    .await;
-    let connection_id = match response.metadata().get(GRPC_CONNECTION_ID) {
-        Some(connection_id) => connection_id.to_str().unwrap().to_string(),
-        None => "".to_string(),
-    };
+    .await?;
+    let connection_id = get_connection_id(&response);
     let mut resp_stream = response.into_inner();
     info!(
         processor_name = processor_name,
@@ -172,7 +175,7 @@ pub async fn get_chain_id(
     );
 
     match resp_stream.next().await {
-        Some(Ok(r)) => r.chain_id.expect("[Parser] Chain Id doesn't exist."),
+        Some(Ok(r)) => r.chain_id.context("[Parser] Chain Id doesn't exist."),
         Some(Err(rpc_error)) => {
             error!(
                 processor_name = processor_name,
@@ -182,7 +185,7 @@ pub async fn get_chain_id(
                 error = ?rpc_error,
                 "[Parser] Error receiving datastream response for chain id"
             );
-            panic!("[Parser] Error receiving datastream response for chain id");
+            Err(rpc_error).context("[Parser] Error receiving datastream response for chain id")
         },
         None => {
             error!(
@@ -192,84 +195,510 @@ pub async fn get_chain_id(
                 connection_id,
                 "[Parser] Stream ended before getting response fo for chain id"
             );
-            panic!("[Parser] Stream ended before getting response fo for chain id");
+            anyhow::bail!("[Parser] Stream ended before getting response fo for chain id");
         },
     }
 }
 
+fn get_connection_id(response: &Response<Streaming<TransactionsResponse>>) -> String {
+    response
+        .metadata()
+        .get(GRPC_CONNECTION_ID)
+        .and_then(|connection_id| connection_id.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// A data service address together with the TLS settings used to connect to it.
+#[derive(Clone, Debug)]
+pub struct DataServiceEndpoint {
+    pub address: Url,
+    /// `None` means plaintext.
+    pub client_tls_config: Option<ClientTlsConfig>,
+}
+
+impl DataServiceEndpoint {
+    /// Without a TLS config the scheme decides, as before: `https` uses the system roots and
+    /// `http` is plaintext. With a TLS config, TLS is required unless plaintext is allowed.
+    pub fn new(address: Url, grpc_tls_config: Option<&GrpcTlsConfig>) -> anyhow::Result<Self> {
+        let is_https = match address.scheme() {
+            "https" => true,
+            "http" => false,
+            scheme => anyhow::bail!(
+                "[Parser] Unsupported scheme {} in data service address {}",
+                scheme,
+                address
+            ),
+        };
+        let client_tls_config = match grpc_tls_config {
+            None => is_https.then(ClientTlsConfig::new),
+            Some(grpc_tls_config) if is_https => Some(
+                grpc_tls_config
+                    .client_tls_config()
+                    .with_context(|| format!("[Parser] Invalid TLS config for {}", address))?,
+            ),
+            Some(grpc_tls_config) => {
+                anyhow::ensure!(
+                    grpc_tls_config.allow_plaintext,
+                    "[Parser] Data service address {} is not https. Set allow_plaintext in grpc_tls_config to connect without TLS",
+                    address
+                );
+                None
+            },
+        };
+        Ok(Self {
+            address,
+            client_tls_config,
+        })
+    }
+}
+
+/// rustls reports handshake failures, e.g. an unknown CA or a rejected client certificate, as
+/// `InvalidData` IO errors.
+fn is_tls_handshake_error(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<std::io::Error>())
+        .any(|e| e.kind() == std::io::ErrorKind::InvalidData)
+}
+
+/// Health of a single data service endpoint.
+#[derive(Debug)]
+struct EndpointHealth {
+    endpoint: DataServiceEndpoint,
+    consecutive_failures: u32,
+    last_failure: Option<Instant>,
+}
+
+/// Data service endpoints in order of preference. Every failure lowers the score of an endpoint
+/// until it has been left alone for the cooldown, so the fetcher moves on to the next healthy
+/// endpoint and comes back to the preferred ones once they had time to recover.
+#[derive(Debug)]
+pub struct DataServiceEndpoints {
+    endpoints: Vec<EndpointHealth>,
+    cooldown: Duration,
+}
+
+impl DataServiceEndpoints {
+    pub fn new(endpoints: Vec<DataServiceEndpoint>, cooldown: Duration) -> Self {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|endpoint| EndpointHealth {
+                    endpoint,
+                    consecutive_failures: 0,
+                    last_failure: None,
+                })
+                .collect(),
+            cooldown,
+        }
+    }
+
+    /// Number of recent failures of the endpoint. Lower is healthier.
+    fn score(&self, index: usize) -> u32 {
+        let endpoint = &self.endpoints[index];
+        match endpoint.last_failure {
+            Some(last_failure) if last_failure.elapsed() < self.cooldown => {
+                endpoint.consecutive_failures
+            },
+            _ => 0,
+        }
+    }
+
+    /// Index of the healthiest endpoint. Ties go to the one listed first.
+    pub fn pick(&self) -> usize {
+        (0..self.endpoints.len())
+            .min_by_key(|index| (self.score(*index), *index))
+            .expect("[Parser] At least one data service address must be configured")
+    }
+
+    pub fn endpoint(&self, index: usize) -> &DataServiceEndpoint {
+        &self.endpoints[index].endpoint
+    }
+
+    pub fn record_success(&mut self, index: usize) {
+        let endpoint = &mut self.endpoints[index];
+        endpoint.consecutive_failures = 0;
+        endpoint.last_failure = None;
+    }
+
+    pub fn record_failure(&mut self, index: usize) {
+        // Failures older than the cooldown are forgiven.
+        let score = self.score(index);
+        let endpoint = &mut self.endpoints[index];
+        endpoint.consecutive_failures = score + 1;
+        endpoint.last_failure = Some(Instant::now());
+    }
+}
+
+/// Opens streams against the data service endpoints, failing over and backing off according to
+/// the reconnection config.
+struct StreamConnector {
+    endpoints: DataServiceEndpoints,
+    reconnection_config: GrpcReconnectionConfig,
+    indexer_grpc_http2_ping_interval: Duration,
+    indexer_grpc_http2_ping_timeout: Duration,
+    request_ending_version: Option<u64>,
+    auth_token: String,
+    processor_name: String,
+    /// Endpoint of the current stream.
+    current_endpoint: usize,
+    /// Failed attempts since the last batch was received.
+    failed_attempts: u32,
+    /// When the first of these failed attempts happened.
+    outage_started_at: Option<Instant>,
+    /// Whether any endpoint has sent a batch since the fetcher started.
+    has_received_batch: bool,
+}
+
+impl StreamConnector {
+    /// A batch was received, so the current endpoint is healthy again. Only a batch counts: an
+    /// endpoint that accepts connections but drops them right away keeps backing off.
+    fn record_success(&mut self) {
+        self.has_received_batch = true;
+        self.endpoints.record_success(self.current_endpoint);
+        self.failed_attempts = 0;
+        self.outage_started_at = None;
+    }
+
+    fn record_failure(&mut self) {
+        GRPC_ENDPOINT_FAILURE_COUNT
+            .with_label_values(&[
+                &self.processor_name,
+                self.endpoints
+                    .endpoint(self.current_endpoint)
+                    .address
+                    .as_str(),
+            ])
+            .inc();
+        self.endpoints.record_failure(self.current_endpoint);
+        self.failed_attempts += 1;
+        self.outage_started_at.get_or_insert_with(Instant::now);
+    }
+
+    /// Connects to the healthiest endpoint, starting the stream at `starting_version`. Returns
+    /// `None` if a shutdown was requested while waiting to reconnect.
+    async fn connect(
+        &mut self,
+        starting_version: u64,
+        shutdown_receiver: &mut tokio::sync::watch::Receiver<bool>,
+    ) -> Result<Option<(String, String, Streaming<TransactionsResponse>)>, WorkerError> {
+        loop {
+            if let Some(outage_started_at) = self.outage_started_at {
+                let outage_duration = outage_started_at.elapsed();
+                if outage_duration >= self.reconnection_config.max_reconnection_time() {
+                    error!(
+                        processor_name = self.processor_name,
+                        service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                        failed_attempts = self.failed_attempts,
+                        outage_duration_in_secs = outage_duration.as_secs_f64(),
+                        "[Parser] Could not reconnect to any data service endpoint. Will not retry.",
+                    );
+                    return Err(WorkerError::Fetcher(anyhow::anyhow!(
+                        "[Parser] Could not reconnect to any data service endpoint after {} attempts in {:.1}s. Will not retry.",
+                        self.failed_attempts,
+                        outage_duration.as_secs_f64()
+                    )));
+                }
+            }
+            if self.failed_attempts > 0 {
+                let delay = self.reconnection_config.delay(self.failed_attempts);
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {},
+                    Ok(()) = shutdown_receiver.changed() => return Ok(None),
+                }
+            }
+
+            self.current_endpoint = self.endpoints.pick();
+            let endpoint = self.endpoints.endpoint(self.current_endpoint).clone();
+            let stream_address = endpoint.address.clone();
+            info!(
+                processor_name = self.processor_name,
+                service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                stream_address = stream_address.to_string(),
+                starting_version = starting_version,
+                ending_version = self.request_ending_version,
+                reconnection_retries = self.failed_attempts,
+                "[Parser] Connecting to GRPC stream"
+            );
+            match get_stream(
+                stream_address.clone(),
+                endpoint.client_tls_config.clone(),
+                self.indexer_grpc_http2_ping_interval,
+                self.indexer_grpc_http2_ping_timeout,
+                starting_version,
+                self.request_ending_version,
+                self.auth_token.clone(),
+                self.processor_name.clone(),
+            )
+            .await
+            {
+                Ok(response) => {
+                    let connection_id = get_connection_id(&response);
+                    info!(
+                        processor_name = self.processor_name,
+                        service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                        stream_address = stream_address.to_string(),
+                        connection_id,
+                        starting_version = starting_version,
+                        ending_version = self.request_ending_version,
+                        reconnection_retries = self.failed_attempts,
+                        "[Parser] Successfully connected to GRPC stream"
+                    );
+                    return Ok(Some((
+                        stream_address.to_string(),
+                        connection_id,
+                        response.into_inner(),
+                    )));
+                },
+                Err(e) => {
+                    tracing::warn!(
+                        processor_name = self.processor_name,
+                        service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                        stream_address = stream_address.to_string(),
+                        starting_version = starting_version,
+                        error = ?e,
+                        "[Parser] Failed to connect to GRPC stream"
+                    );
+                    // A handshake that fails before we ever got data is a configuration problem
+                    // rather than an outage, so there is no point in retrying.
+                    if !self.has_received_batch
+                        && endpoint.client_tls_config.is_some()
+                        && is_tls_handshake_error(&e)
+                    {
+                        return Err(WorkerError::Fetcher(e.context(format!(
+                            "[Parser] TLS handshake with data service {} failed. Check the CA certificate, client certificate and domain name in grpc_tls_config",
+                            stream_address
+                        ))));
+                    }
+                    self.record_failure();
+                },
+            }
+        }
+    }
+}
+
+/// Where the fetcher reads batches of transactions from.
+#[derive(Clone, Debug)]
+pub enum TransactionSource {
+    /// The data service, failing over between the endpoints in order of preference.
+    Grpc {
+        endpoints: Vec<DataServiceEndpoint>,
+        reconnection_config: GrpcReconnectionConfig,
+    },
+    /// A directory of archived batches, see `crate::archive`.
+    File(PathBuf),
+}
+
+impl TransactionSource {
+    /// Address of the source, used in logs.
+    pub fn address(&self) -> anyhow::Result<Url> {
+        match self {
+            TransactionSource::Grpc { endpoints, .. } => endpoints
+                .first()
+                .map(|endpoint| endpoint.address.clone())
+                .context("[Parser] At least one data service address must be configured"),
+            TransactionSource::File(path) => Url::from_directory_path(path).map_err(|_| {
+                anyhow::anyhow!(
+                    "[Parser] Archive path {} must be an absolute path",
+                    path.display()
+                )
+            }),
+        }
+    }
+
+    /// A live source keeps streaming forever; a file source stops once it has been replayed.
+    pub fn is_live(&self) -> bool {
+        matches!(self, TransactionSource::Grpc { .. })
+    }
+}
+
+/// An opened `TransactionSource`.
+enum TransactionStream {
+    Grpc {
+        connector: StreamConnector,
+        stream: Streaming<TransactionsResponse>,
+    },
+    File(ArchiveReader),
+}
+
+impl TransactionStream {
+    async fn next(&mut self) -> Option<anyhow::Result<TransactionsResponse>> {
+        match self {
+            TransactionStream::Grpc { stream, .. } => stream
+                .next()
+                .await
+                .map(|response| response.map_err(anyhow::Error::from)),
+            TransactionStream::File(reader) => reader.next().await,
+        }
+    }
+
+    fn record_success(&mut self) {
+        if let TransactionStream::Grpc { connector, .. } = self {
+            connector.record_success();
+        }
+    }
+
+    /// Reopens the stream at `next_version_to_fetch` after it failed and returns the new stream
+    /// address and connection id, or `None` if a shutdown was requested.
+    async fn reconnect(
+        &mut self,
+        next_version_to_fetch: u64,
+        shutdown_receiver: &mut tokio::sync::watch::Receiver<bool>,
+    ) -> Result<Option<(String, String)>, WorkerError> {
+        match self {
+            TransactionStream::Grpc { connector, stream } => {
+                connector.record_failure();
+                Ok(connector
+                    .connect(next_version_to_fetch, shutdown_receiver)
+                    .await?
+                    .map(|(stream_address, connection_id, new_stream)| {
+                        *stream = new_stream;
+                        (stream_address, connection_id)
+                    }))
+            },
+            TransactionStream::File(_) => Err(WorkerError::Fetcher(anyhow::anyhow!(
+                "[Parser] Archive cannot be reopened"
+            ))),
+        }
+    }
+}
+
 /// Gets a batch of transactions from the stream. Batch size is set in the grpc server.
 /// The number of batches depends on our config
 /// There could be several special scenarios:
-/// 1. If we lose the connection, we will try reconnecting X times within Y seconds before crashing.
+/// 1. If we lose the connection, we reconnect with exponential backoff, failing over to the next
+/// healthy data service endpoint, and resume from the next version to fetch. We crash once
+/// the reconnection time budget is used up.
 /// 2. If we specified an end version and we hit that, we will stop fetching, but we will make sure that
 /// all existing transactions are processed
+/// 3. If a shutdown is requested, we stop fetching right away and close the channel.
+/// 4. If we replay an archive, we stop once all of it was sent, as if we hit the end version.
+///
+/// With an `archive_sender`, every batch is also handed to the archive recorder.
 pub async fn create_fetcher_loop(
-    txn_sender: AsyncSender<TransactionsPBResponse>,
-    indexer_grpc_data_service_address: Url,
+    txn_sender: AsyncSender<Arc<TransactionsPBResponse>>,
+    transaction_source: TransactionSource,
     indexer_grpc_http2_ping_interval: Duration,
     indexer_grpc_http2_ping_timeout: Duration,
+    archive_sender: Option<AsyncSender<TransactionsResponse>>,
     starting_version: u64,
     request_ending_version: Option<u64>,
     auth_token: String,
     processor_name: String,
     batch_start_version: u64,
     buffer_size: usize,
-) {
+    mut shutdown_receiver: tokio::sync::watch::Receiver<bool>,
+) -> Result<(), WorkerError> {
     let mut grpc_channel_recv_latency = std::time::Instant::now();
     let mut next_version_to_fetch = batch_start_version;
-    let mut reconnection_retries = 0;
-    info!(
-        processor_name = processor_name,
-        service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-        stream_address = indexer_grpc_data_service_address.to_string(),
-        start_version = starting_version,
-        end_version = request_ending_version,
-        "[Parser] Connecting to GRPC stream",
-    );
-    let mut response = get_stream(
-        indexer_grpc_data_service_address.clone(),
-        indexer_grpc_http2_ping_interval,
-        indexer_grpc_http2_ping_timeout,
-        starting_version,
-        request_ending_version,
-        auth_token.clone(),
-        processor_name.to_string(),
-    )
-    .await;
-    let mut connection_id = match response.metadata().get(GRPC_CONNECTION_ID) {
-        Some(connection_id) => connection_id.to_str().unwrap().to_string(),
-        None => "".to_string(),
+    let (mut source, mut stream_address, mut connection_id) = match transaction_source {
+        TransactionSource::Grpc {
+            endpoints,
+            reconnection_config,
+        } => {
+            let mut connector = StreamConnector {
+                endpoints: DataServiceEndpoints::new(
+                    endpoints,
+                    reconnection_config.endpoint_cooldown(),
+                ),
+                reconnection_config,
+                indexer_grpc_http2_ping_interval,
+                indexer_grpc_http2_ping_timeout,
+                request_ending_version,
+                auth_token,
+                processor_name: processor_name.clone(),
+                current_endpoint: 0,
+                failed_attempts: 0,
+                outage_started_at: None,
+                has_received_batch: false,
+            };
+            info!(
+                processor_name = processor_name,
+                service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                start_version = starting_version,
+                end_version = request_ending_version,
+                "[Parser] Connecting to GRPC stream",
+            );
+            match connector
+                .connect(starting_version, &mut shutdown_receiver)
+                .await?
+            {
+                Some((stream_address, connection_id, stream)) => (
+                    TransactionStream::Grpc { connector, stream },
+                    stream_address,
+                    connection_id,
+                ),
+                None => return Ok(()),
+            }
+        },
+        TransactionSource::File(path) => {
+            info!(
+                processor_name = processor_name,
+                service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                archive_path = path.display().to_string(),
+                start_version = starting_version,
+                end_version = request_ending_version,
+                "[Parser] Reading transactions from archive",
+            );
+            let reader = ArchiveReader::new(&path, starting_version, request_ending_version)
+                .map_err(WorkerError::Fetcher)?;
+            (
+                TransactionStream::File(reader),
+                path.display().to_string(),
+                String::new(),
+            )
+        },
     };
-    let mut resp_stream = response.into_inner();
-    info!(
-        processor_name = processor_name,
-        service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-        stream_address = indexer_grpc_data_service_address.to_string(),
-        connection_id,
-        start_version = starting_version,
-        end_version = request_ending_version,
-        "[Parser] Successfully connected to GRPC stream",
-    );
 
     let mut last_fetched_version = batch_start_version as i64 - 1;
     let mut batch_start_version = batch_start_version;
+    let mut source_exhausted = false;
     loop {
-        let is_success = match resp_stream.next().await {
+        let next_response = tokio::select! {
+            response = source.next() => response,
+            Ok(()) = shutdown_receiver.changed() => {
+                info!(
+                    processor_name = processor_name,
+                    service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                    stream_address = stream_address,
+                    connection_id,
+                    next_version_to_fetch = next_version_to_fetch,
+                    "[Parser] Shutdown requested, stopping fetcher."
+                );
+                return Ok(());
+            },
+        };
+        let is_success = match next_response {
             Some(Ok(r)) => {
-                reconnection_retries = 0;
-                let start_version = r.transactions.as_slice().first().unwrap().version;
-                let start_txn_timestamp =
-                    r.transactions.as_slice().first().unwrap().timestamp.clone();
-                let end_version = r.transactions.as_slice().last().unwrap().version;
-                let end_txn_timestamp = r.transactions.as_slice().last().unwrap().timestamp.clone();
+                source.record_success();
+                let (first, last) = match (r.transactions.first(), r.transactions.last()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => {
+                        return Err(WorkerError::Fetcher(anyhow::anyhow!(
+                            "[Parser] Received an empty batch at version {}",
+                            next_version_to_fetch
+                        )))
+                    },
+                };
+                let start_version = first.version;
+                let start_txn_timestamp = first.timestamp.clone();
+                let end_version = last.version;
+                let end_txn_timestamp = last.timestamp.clone();
+                let chain_id: u64 = r.chain_id.ok_or_else(|| {
+                    WorkerError::Fetcher(anyhow::anyhow!(
+                        "[Parser] Chain Id doesn't exist in the batch {}-{}",
+                        start_version,
+                        end_version
+                    ))
+                })?;
                 next_version_to_fetch = end_version + 1;
                 let size_in_bytes = r.encoded_len() as u64;
-                let chain_id: u64 = r.chain_id.expect("[Parser] Chain Id doesn't exist.");
 
                 info!(
                     processor_name = processor_name,
                     service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                    stream_address = indexer_grpc_data_service_address.to_string(),
+                    stream_address = stream_address,
                     connection_id,
                     start_version,
                     end_version,
@@ -302,11 +731,29 @@ pub async fn create_fetcher_loop(
                         current_fetched_version = current_fetched_version,
                         "[Parser] Received batch with gap from GRPC stream"
                     );
-                    panic!("[Parser] Received batch with gap from GRPC stream");
+                    return Err(WorkerError::BatchGap {
+                        last_fetched_version,
+                        current_fetched_version,
+                    });
                 }
                 last_fetched_version = end_version as i64;
                 batch_start_version = (last_fetched_version + 1) as u64;
 
+                if let Some(archive_sender) = &archive_sender {
+                    if archive_sender.send(r.clone()).await.is_err() {
+                        error!(
+                            processor_name = processor_name,
+                            stream_address = stream_address,
+                            start_version,
+                            end_version,
+                            "[Parser] Archive recorder stopped, cannot record transactions."
+                        );
+                        return Err(WorkerError::Fetcher(anyhow::anyhow!(
+                            "[Parser] Archive recorder stopped"
+                        )));
+                    }
+                }
+
                 LATEST_PROCESSED_VERSION
                     .with_label_values(&[
                         &processor_name,
@@ -354,24 +801,24 @@ pub async fn create_fetcher_loop(
                 let bytes_per_sec =
                     txn_pb.size_in_bytes as f64 / txn_channel_send_latency.elapsed().as_secs_f64();
 
-                match txn_sender.send(txn_pb).await {
+                match txn_sender.send(Arc::new(txn_pb)).await {
                     Ok(()) => {},
                     Err(e) => {
                         error!(
                             processor_name = processor_name,
-                            stream_address = indexer_grpc_data_service_address.to_string(),
+                            stream_address = stream_address,
                             connection_id,
                             channel_size = buffer_size - txn_sender.capacity(),
                             error = ?e,
                             "[Parser] Error sending GRPC response to channel."
                         );
-                        panic!("[Parser] Error sending GRPC response to channel.")
+                        return Err(WorkerError::ChannelClosed);
                     },
                 }
                 info!(
                     processor_name = processor_name,
                     service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                    stream_address = indexer_grpc_data_service_address.to_string(),
+                    stream_address = stream_address,
                     connection_id,
                     start_version = start_version,
                     end_version = end_version,
@@ -392,25 +839,30 @@ pub async fn create_fetcher_loop(
                 tracing::warn!(
                     processor_name = processor_name,
                     service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                    stream_address = indexer_grpc_data_service_address.to_string(),
+                    stream_address = stream_address,
                     connection_id,
                     start_version = starting_version,
                     end_version = request_ending_version,
                     error = ?rpc_error,
                     "[Parser] Error receiving datastream response."
                 );
+                if let TransactionStream::File(_) = source {
+                    return Err(WorkerError::Fetcher(rpc_error));
+                }
                 false
             },
             None => {
                 tracing::warn!(
                     processor_name = processor_name,
                     service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                    stream_address = indexer_grpc_data_service_address.to_string(),
+                    stream_address = stream_address,
                     connection_id,
                     start_version = starting_version,
                     end_version = request_ending_version,
                     "[Parser] Stream ended."
                 );
+                // An archive only ends once all of it was replayed.
+                source_exhausted = matches!(source, TransactionStream::File(_));
                 false
             },
         };
@@ -419,12 +871,12 @@ pub async fn create_fetcher_loop(
             next_version_to_fetch > ending_version
         } else {
             false
-        };
+        } || source_exhausted;
         if is_end {
             info!(
                 processor_name = processor_name,
                 service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                stream_address = indexer_grpc_data_service_address.to_string(),
+                stream_address = stream_address,
                 connection_id,
                 ending_version = request_ending_version,
                 next_version_to_fetch = next_version_to_fetch,
@@ -436,7 +888,7 @@ pub async fn create_fetcher_loop(
                 info!(
                     processor_name = processor_name,
                     service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                    stream_address = indexer_grpc_data_service_address.to_string(),
+                    stream_address = stream_address,
                     connection_id,
                     channel_size = buffer_size - channel_capacity,
                     "[Parser] Waiting for channel to be empty"
@@ -449,65 +901,95 @@ pub async fn create_fetcher_loop(
             info!(
                 processor_name = processor_name,
                 service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                stream_address = indexer_grpc_data_service_address.to_string(),
+                stream_address = stream_address,
                 connection_id,
                 "[Parser] The stream is ended."
             );
-            break;
+            return Ok(());
         } else {
             // The rest is to see if we need to reconnect
             if is_success {
                 continue;
             }
 
-            // Sleep for 100ms between reconnect tries
-            // TODO: Turn this into exponential backoff
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-
-            if reconnection_retries >= RECONNECTION_MAX_RETRIES {
-                error!(
-                    processor_name = processor_name,
-                    service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                    stream_address = indexer_grpc_data_service_address.to_string(),
-                    "[Parser] Reconnected more than 100 times. Will not retry.",
-                );
-                panic!("[Parser] Reconnected more than 100 times. Will not retry.")
-            }
-            reconnection_retries += 1;
-            info!(
-                processor_name = processor_name,
-                service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                stream_address = indexer_grpc_data_service_address.to_string(),
-                starting_version = next_version_to_fetch,
-                ending_version = request_ending_version,
-                reconnection_retries = reconnection_retries,
-                "[Parser] Reconnecting to GRPC stream"
-            );
-            response = get_stream(
-                indexer_grpc_data_service_address.clone(),
-                indexer_grpc_http2_ping_interval,
-                indexer_grpc_http2_ping_timeout,
-                next_version_to_fetch,
-                request_ending_version,
-                auth_token.clone(),
-                processor_name.to_string(),
-            )
-            .await;
-            connection_id = match response.metadata().get(GRPC_CONNECTION_ID) {
-                Some(connection_id) => connection_id.to_str().unwrap().to_string(),
-                None => "".to_string(),
+            // Resume from the first version that has not been sent to the channel yet, so the
+            // batches stay contiguous even when we switch to another endpoint.
+            (stream_address, connection_id) = match source
+                .reconnect(next_version_to_fetch, &mut shutdown_receiver)
+                .await?
+            {
+                Some(stream) => stream,
+                None => {
+                    info!(
+                        processor_name = processor_name,
+                        service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                        next_version_to_fetch = next_version_to_fetch,
+                        "[Parser] Shutdown requested, stopping fetcher."
+                    );
+                    return Ok(());
+                },
             };
-            resp_stream = response.into_inner();
-            info!(
-                processor_name = processor_name,
-                service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
-                stream_address = indexer_grpc_data_service_address.to_string(),
-                connection_id,
-                starting_version = next_version_to_fetch,
-                ending_version = request_ending_version,
-                reconnection_retries = reconnection_retries,
-                "[Parser] Successfully reconnected to GRPC stream"
-            );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(cooldown: Duration) -> DataServiceEndpoints {
+        DataServiceEndpoints::new(
+            vec![
+                DataServiceEndpoint::new(Url::parse("http://primary:50051").unwrap(), None)
+                    .unwrap(),
+                DataServiceEndpoint::new(Url::parse("http://fallback:50051").unwrap(), None)
+                    .unwrap(),
+            ],
+            cooldown,
+        )
+    }
+
+    #[test]
+    fn test_fails_over_to_healthiest_endpoint() {
+        let mut endpoints = endpoints(Duration::from_secs(60));
+        assert_eq!(endpoints.pick(), 0);
+
+        endpoints.record_failure(0);
+        assert_eq!(endpoints.pick(), 1);
+
+        // Both failed once, so the preferred one is tried again.
+        endpoints.record_failure(1);
+        assert_eq!(endpoints.pick(), 0);
+
+        endpoints.record_failure(0);
+        assert_eq!(endpoints.pick(), 1);
+        endpoints.record_success(1);
+        assert_eq!(endpoints.pick(), 1);
+    }
+
+    #[test]
+    fn test_plaintext_requires_opt_in_with_tls_config() {
+        let address = Url::parse("http://127.0.0.1:50051").unwrap();
+        let endpoint = DataServiceEndpoint::new(address.clone(), None).unwrap();
+        assert!(endpoint.client_tls_config.is_none());
+
+        let mut tls_config = GrpcTlsConfig::default();
+        assert!(DataServiceEndpoint::new(address.clone(), Some(&tls_config)).is_err());
+        tls_config.allow_plaintext = true;
+        let endpoint = DataServiceEndpoint::new(address, Some(&tls_config)).unwrap();
+        assert!(endpoint.client_tls_config.is_none());
+
+        let address = Url::parse("https://grpc.mainnet.aptoslabs.com:443").unwrap();
+        let endpoint = DataServiceEndpoint::new(address, None).unwrap();
+        assert!(endpoint.client_tls_config.is_some());
+    }
+
+    #[test]
+    fn test_failures_are_forgiven_after_cooldown() {
+        let mut endpoints = endpoints(Duration::ZERO);
+        endpoints.record_failure(0);
+        endpoints.record_failure(0);
+        assert_eq!(endpoints.score(0), 0);
+        assert_eq!(endpoints.pick(), 0);
+    }
+}
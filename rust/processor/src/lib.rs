@@ -13,6 +13,7 @@ extern crate diesel;
 
 pub use config::IndexerGrpcProcessorConfig;
 
+pub mod archive;
 mod config;
 pub mod gap_detector;
 pub mod grpc_stream;
@@ -8,7 +8,7 @@ use crate::{
     utils::database::{execute_in_chunks, PgDbPool},
 };
 use ahash::AHashMap;
-use anyhow::bail;
+use anyhow::Context;
 use aptos_protos::transaction::v1::Transaction;
 use async_trait::async_trait;
 use diesel::{pg::Pg, query_builder::QueryFragment};
@@ -85,7 +85,7 @@ impl ProcessorTrait for AccountTransactionsProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -93,7 +93,7 @@ impl ProcessorTrait for AccountTransactionsProcessor {
         let processing_start = std::time::Instant::now();
         let mut account_transactions = AHashMap::new();
 
-        for txn in &transactions {
+        for txn in transactions {
             account_transactions.extend(AccountTransaction::from_transaction(txn));
         }
         let mut account_transactions = account_transactions
@@ -134,7 +134,12 @@ impl ProcessorTrait for AccountTransactionsProcessor {
                     "[Parser] Error inserting transactions to db: {:?}",
                     err
                 );
-                bail!(format!("Error inserting transactions to db. Processor {}. Start {}. End {}. Error {:?}", self.name(), start_version, end_version, err))
+                Err(err).context(format!(
+                    "Error inserting transactions to db. Processor {}. Start {}. End {}.",
+                    self.name(),
+                    start_version,
+                    end_version
+                ))
             },
         }
     }
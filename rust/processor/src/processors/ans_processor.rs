@@ -327,7 +327,7 @@ impl ProcessorTrait for AnsProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _db_chain_id: Option<u64>,
@@ -344,7 +344,7 @@ impl ProcessorTrait for AnsProcessor {
             all_current_ans_primary_names_v2,
             all_ans_primary_names_v2,
         ) = parse_ans(
-            &transactions,
+            transactions,
             self.config.ans_v1_primary_names_table_handle.clone(),
             self.config.ans_v1_name_records_table_handle.clone(),
             self.config.ans_v2_contract_address.clone(),
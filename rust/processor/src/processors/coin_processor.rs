@@ -13,10 +13,10 @@ use crate::{
         fungible_asset_models::v2_fungible_asset_activities::CurrentCoinBalancePK,
     },
     schema,
-    utils::database::{execute_in_chunks, PgDbPool},
+    utils::database::{BatchWriteMode, BatchWriter, PgDbPool},
 };
 use ahash::AHashMap;
-use anyhow::bail;
+use anyhow::Context;
 use aptos_protos::transaction::v1::Transaction;
 use async_trait::async_trait;
 use diesel::{
@@ -32,11 +32,15 @@ pub const APTOS_COIN_TYPE_STR: &str = "0x1::aptos_coin::AptosCoin";
 
 pub struct CoinProcessor {
     connection_pool: PgDbPool,
+    write_mode: BatchWriteMode,
 }
 
 impl CoinProcessor {
-    pub fn new(connection_pool: PgDbPool) -> Self {
-        Self { connection_pool }
+    pub fn new(connection_pool: PgDbPool, write_mode: BatchWriteMode) -> Self {
+        Self {
+            connection_pool,
+            write_mode,
+        }
     }
 }
 
@@ -52,7 +56,7 @@ impl Debug for CoinProcessor {
 }
 
 async fn insert_to_db(
-    conn: PgDbPool,
+    writer: &mut BatchWriter,
     name: &'static str,
     start_version: u64,
     end_version: u64,
@@ -69,41 +73,37 @@ async fn insert_to_db(
         "Inserting to db",
     );
 
-    execute_in_chunks(
-        conn.clone(),
-        insert_coin_activities_query,
-        coin_activities,
-        CoinActivity::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_coin_infos_query,
-        coin_infos,
-        CoinInfo::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_coin_balances_query,
-        coin_balances,
-        CoinBalance::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_coin_balances_query,
-        current_coin_balances,
-        CurrentCoinBalance::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        inset_coin_supply_query,
-        coin_supply,
-        CoinSupply::field_count(),
-    )
-    .await?;
+    writer
+        .execute_in_chunks(
+            insert_coin_activities_query,
+            coin_activities,
+            CoinActivity::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(insert_coin_infos_query, coin_infos, CoinInfo::field_count())
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_coin_balances_query,
+            coin_balances,
+            CoinBalance::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_coin_balances_query,
+            current_coin_balances,
+            CurrentCoinBalance::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            inset_coin_supply_query,
+            coin_supply,
+            CoinSupply::field_count(),
+        )
+        .await?;
 
     Ok(())
 }
@@ -227,7 +227,7 @@ impl ProcessorTrait for CoinProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -241,7 +241,7 @@ impl ProcessorTrait for CoinProcessor {
             AHashMap::new();
         let mut all_coin_supply = vec![];
 
-        for txn in &transactions {
+        for txn in transactions {
             let (
                 mut coin_activities,
                 mut coin_balances,
@@ -272,17 +272,27 @@ impl ProcessorTrait for CoinProcessor {
         let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
         let db_insertion_start = std::time::Instant::now();
 
-        let tx_result = insert_to_db(
-            self.get_pool(),
-            self.name(),
-            start_version,
-            end_version,
-            all_coin_activities,
-            all_coin_infos,
-            all_coin_balances,
-            all_current_coin_balances,
-            all_coin_supply,
-        )
+        let tx_result = async {
+            let mut writer = BatchWriter::new(self.get_pool(), self.write_mode).await?;
+            insert_to_db(
+                &mut writer,
+                self.name(),
+                start_version,
+                end_version,
+                all_coin_activities,
+                all_coin_infos,
+                all_coin_balances,
+                all_current_coin_balances,
+                all_coin_supply,
+            )
+            .await?;
+            self.commit_batch(
+                writer,
+                end_version,
+                transactions.last().unwrap().timestamp.clone(),
+            )
+            .await
+        }
         .await;
 
         let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
@@ -303,7 +313,12 @@ impl ProcessorTrait for CoinProcessor {
                     "[Parser] Error inserting transactions to db: {:?}",
                     err
                 );
-                bail!(format!("Error inserting transactions to db. Processor {}. Start {}. End {}. Error {:?}", self.name(), start_version, end_version, err))
+                Err(err).context(format!(
+                    "Error inserting transactions to db. Processor {}. Start {}. End {}.",
+                    self.name(),
+                    start_version,
+                    end_version
+                ))
             },
         }
     }
@@ -12,7 +12,7 @@ use crate::{
         write_set_changes::{WriteSetChangeDetail, WriteSetChangeModel},
     },
     schema,
-    utils::database::{execute_in_chunks, PgDbPool},
+    utils::database::{BatchWriteMode, BatchWriter, PgDbPool},
 };
 use ahash::AHashMap;
 use anyhow::bail;
@@ -29,11 +29,15 @@ use tracing::error;
 
 pub struct DefaultProcessor {
     connection_pool: PgDbPool,
+    write_mode: BatchWriteMode,
 }
 
 impl DefaultProcessor {
-    pub fn new(connection_pool: PgDbPool) -> Self {
-        Self { connection_pool }
+    pub fn new(connection_pool: PgDbPool, write_mode: BatchWriteMode) -> Self {
+        Self {
+            connection_pool,
+            write_mode,
+        }
     }
 }
 
@@ -49,7 +53,7 @@ impl Debug for DefaultProcessor {
 }
 
 async fn insert_to_db(
-    conn: PgDbPool,
+    writer: &mut BatchWriter,
     name: &'static str,
     start_version: u64,
     end_version: u64,
@@ -71,62 +75,62 @@ async fn insert_to_db(
         "Inserting to db",
     );
 
-    execute_in_chunks(
-        conn.clone(),
-        insert_transactions_query,
-        txns,
-        TransactionModel::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_block_metadata_transactions_query,
-        block_metadata_transactions,
-        BlockMetadataTransactionModel::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_write_set_changes_query,
-        wscs,
-        WriteSetChangeModel::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_move_modules_query,
-        move_modules,
-        MoveModule::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_move_resources_query,
-        move_resources,
-        MoveResource::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_table_items_query,
-        table_items,
-        TableItem::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_table_items_query,
-        current_table_items,
-        CurrentTableItem::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_table_metadata_query,
-        table_metadata,
-        TableMetadata::field_count(),
-    )
-    .await?;
+    writer
+        .execute_in_chunks(
+            insert_transactions_query,
+            txns,
+            TransactionModel::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_block_metadata_transactions_query,
+            block_metadata_transactions,
+            BlockMetadataTransactionModel::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_write_set_changes_query,
+            wscs,
+            WriteSetChangeModel::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_move_modules_query,
+            move_modules,
+            MoveModule::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_move_resources_query,
+            move_resources,
+            MoveResource::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_table_items_query,
+            table_items,
+            TableItem::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_table_items_query,
+            current_table_items,
+            CurrentTableItem::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_table_metadata_query,
+            table_metadata,
+            TableMetadata::field_count(),
+        )
+        .await?;
 
     Ok(())
 }
@@ -287,14 +291,14 @@ impl ProcessorTrait for DefaultProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
     ) -> anyhow::Result<ProcessingResult> {
         let processing_start = std::time::Instant::now();
         let (txns, block_metadata_txns, write_set_changes, wsc_details) =
-            TransactionModel::from_transactions(&transactions);
+            TransactionModel::from_transactions(transactions);
 
         let mut block_metadata_transactions = vec![];
         for block_metadata_txn in block_metadata_txns {
@@ -338,22 +342,32 @@ impl ProcessorTrait for DefaultProcessor {
         let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
         let db_insertion_start = std::time::Instant::now();
 
-        let tx_result = insert_to_db(
-            self.get_pool(),
-            self.name(),
-            start_version,
-            end_version,
-            txns,
-            block_metadata_transactions,
-            write_set_changes,
-            (
-                move_modules,
-                move_resources,
-                table_items,
-                current_table_items,
-                table_metadata,
-            ),
-        )
+        let tx_result = async {
+            let mut writer = BatchWriter::new(self.get_pool(), self.write_mode).await?;
+            insert_to_db(
+                &mut writer,
+                self.name(),
+                start_version,
+                end_version,
+                txns,
+                block_metadata_transactions,
+                write_set_changes,
+                (
+                    move_modules,
+                    move_resources,
+                    table_items,
+                    current_table_items,
+                    table_metadata,
+                ),
+            )
+            .await?;
+            self.commit_batch(
+                writer,
+                end_version,
+                transactions.last().unwrap().timestamp.clone(),
+            )
+            .await
+        }
         .await;
 
         let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
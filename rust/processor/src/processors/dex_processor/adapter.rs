@@ -0,0 +1,174 @@
+use std::fmt::Debug;
+
+use anyhow::{anyhow, Result};
+use aptos_protos::transaction::v1::{Event, Transaction};
+use bigdecimal::BigDecimal;
+
+use crate::{
+    processors::ls_processor::mv::{event_index, unwrap_usr_tx},
+    utils::util::standardize_address,
+};
+
+use super::db::{TableDexLiquidityChange, TableDexPool, TableDexSwap};
+
+/// Coins and type of a pool.
+#[derive(Debug, Clone)]
+pub struct DexPoolId {
+    pub pool_id: String,
+    pub coin_x: String,
+    pub coin_y: String,
+    // e.g. the curve of a Liquidswap pool
+    pub pool_type: Option<String>,
+}
+
+/// State of a pool after a transaction.
+#[derive(Debug, Clone)]
+pub struct DexReserves {
+    pub x_val: BigDecimal,
+    pub y_val: BigDecimal,
+    pub fee_bps: Option<i64>,
+}
+
+/// Liquidity added to (positive) or removed from (negative) a pool.
+#[derive(Debug, Clone)]
+pub struct DexLiquidity {
+    pub x_val: BigDecimal,
+    pub y_val: BigDecimal,
+    pub lp_tokens: BigDecimal,
+}
+
+/// Rows of the shared schema from a transaction.
+#[derive(Debug)]
+pub(crate) enum DexEvent {
+    Pools(TableDexPool),
+    Swaps(TableDexSwap),
+    LiquidityChanges(TableDexLiquidityChange),
+}
+
+/// What the `dex_processor` needs to know about an AMM.
+///
+/// An adapter recognizes the pool events of its protocol and reads them; `events_from_tx` maps
+/// them to the shared schema the same way for every protocol.
+pub(crate) trait DexAdapter: Send + Sync + Debug {
+    /// Value of the `protocol` column
+    fn protocol(&self) -> &'static str;
+
+    /// Pool events of the protocol in a transaction, borrowed from the events of the transaction
+    fn pool_events<'a>(&self, tx: &'a Transaction) -> Vec<&'a Event>;
+
+    fn pool(&self, event: &Event) -> Result<DexPoolId>;
+
+    /// Reserves of the pool of the event after the transaction, if the transaction wrote them
+    fn reserves(&self, tx: &Transaction, event: &Event) -> Result<Option<DexReserves>>;
+
+    /// (x_in, x_out, y_in, y_out) if the event is a swap
+    fn swap(
+        &self,
+        event: &Event,
+    ) -> Result<Option<(BigDecimal, BigDecimal, BigDecimal, BigDecimal)>>;
+
+    fn liquidity_change(&self, event: &Event) -> Result<Option<DexLiquidity>>;
+
+    fn events_from_tx(&self, tx: &Transaction) -> Result<Vec<DexEvent>> {
+        let mut result = Vec::new();
+        let info = match DexTxInfo::from_tx(tx) {
+            Some(info) => info,
+            None => return Ok(result),
+        };
+        let tx_events = unwrap_usr_tx(tx)
+            .map(|user_tx| user_tx.events.as_slice())
+            .unwrap_or_default();
+
+        for event in self.pool_events(tx) {
+            let pool = self.pool(event)?;
+            let id = event_id(info.version, tx_events, event)?;
+
+            result.push(DexEvent::Pools(TableDexPool::new(
+                self.protocol(),
+                &pool,
+                self.reserves(tx, event)?,
+                info.version,
+            )));
+            if let Some(amounts) = self.swap(event)? {
+                result.push(DexEvent::Swaps(TableDexSwap::new(
+                    self.protocol(),
+                    &id,
+                    &pool,
+                    &info,
+                    amounts,
+                )));
+            }
+            if let Some(change) = self.liquidity_change(event)? {
+                result.push(DexEvent::LiquidityChanges(TableDexLiquidityChange {
+                    protocol: self.protocol().to_string(),
+                    id,
+                    pool_id: pool.pool_id.clone(),
+                    account: info.sender.clone(),
+                    version: info.version,
+                    tx_hash: info.tx_hash.clone(),
+                    timestamp: info.timestamp,
+                    x_val: change.x_val,
+                    y_val: change.y_val,
+                    lp_tokens: change.lp_tokens,
+                }));
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// version + "_" + index of the event in the transaction's events, see `event_index`
+fn event_id(version: i64, tx_events: &[Event], event: &Event) -> Result<String> {
+    let index = event_index(tx_events, event)
+        .ok_or(anyhow!("expected an event of transaction {}", version))?;
+    Ok(format!("{}_{}", version, index))
+}
+
+pub(crate) struct DexTxInfo {
+    pub(crate) version: i64,
+    pub(crate) tx_hash: String,
+    pub(crate) timestamp: i64,
+    pub(crate) sender: String,
+}
+
+impl DexTxInfo {
+    /// `None` for failed and non-user transactions
+    fn from_tx(tx: &Transaction) -> Option<Self> {
+        use aptos_protos::transaction::v1::transaction::TxnData;
+
+        let info = tx.info.as_ref()?;
+        if !info.success {
+            return None;
+        }
+        let sender = match tx.txn_data.as_ref()? {
+            TxnData::User(user_tx) => standardize_address(&user_tx.request.as_ref()?.sender),
+            _ => return None,
+        };
+
+        Some(DexTxInfo {
+            version: tx.version.try_into().ok()?,
+            tx_hash: hex::encode(&info.hash),
+            timestamp: tx.timestamp.as_ref()?.seconds,
+            sender,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_id_of_module_events() {
+        let module_event = Event {
+            type_str: "0x1::pool::Swapped".to_string(),
+            ..Default::default()
+        };
+        let tx_events = vec![module_event.clone(), module_event.clone()];
+
+        assert_eq!(event_id(7, &tx_events, &tx_events[0]).unwrap(), "7_0");
+        assert_eq!(event_id(7, &tx_events, &tx_events[1]).unwrap(), "7_1");
+        assert!(event_id(7, &tx_events, &module_event).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use bigdecimal::BigDecimal;
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, upsert::excluded, ExpressionMethods, Insertable,
+    Queryable, Selectable,
+};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{self, dex_liquidity_changes, dex_pools, dex_swaps};
+
+use super::adapter::{DexPoolId, DexReserves, DexTxInfo};
+
+/// A pool of any protocol with its reserves at `last_version`.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = dex_pools)]
+pub struct TableDexPool {
+    pub protocol: String,
+    pub pool_id: String,
+    pub coin_x: String,
+    pub coin_y: String,
+    pub pool_type: Option<String>,
+    pub x_val: BigDecimal,
+    pub y_val: BigDecimal,
+    pub fee_bps: Option<i64>,
+    // 0 until the reserves of the pool are seen
+    pub last_version: i64,
+}
+
+impl TableDexPool {
+    pub(crate) fn new(
+        protocol: &str,
+        pool: &DexPoolId,
+        reserves: Option<DexReserves>,
+        version: i64,
+    ) -> Self {
+        let (x_val, y_val, fee_bps, last_version) = match reserves {
+            Some(reserves) => (reserves.x_val, reserves.y_val, reserves.fee_bps, version),
+            None => (BigDecimal::from(0), BigDecimal::from(0), None, 0),
+        };
+
+        TableDexPool {
+            protocol: protocol.to_string(),
+            pool_id: pool.pool_id.clone(),
+            coin_x: pool.coin_x.clone(),
+            coin_y: pool.coin_y.clone(),
+            pool_type: pool.pool_type.clone(),
+            x_val,
+            y_val,
+            fee_bps,
+            last_version,
+        }
+    }
+}
+
+/// A swap of any protocol, from the coin sold to the coin bought.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = dex_swaps)]
+pub struct TableDexSwap {
+    pub protocol: String,
+    pub id: String,
+    pub pool_id: String,
+    pub version: i64,
+    pub tx_hash: String,
+    pub sender: String,
+    pub timestamp: i64,
+    pub coin_in: String,
+    pub amount_in: BigDecimal,
+    pub coin_out: String,
+    pub amount_out: BigDecimal,
+}
+
+impl TableDexSwap {
+    /// The direction of the swap follows from its amounts, coin X is sold if x_in is set.
+    pub(crate) fn new(
+        protocol: &str,
+        id: &str,
+        pool: &DexPoolId,
+        info: &DexTxInfo,
+        (x_in, x_out, y_in, y_out): (BigDecimal, BigDecimal, BigDecimal, BigDecimal),
+    ) -> Self {
+        let (coin_in, amount_in, coin_out, amount_out) = if x_in > BigDecimal::from(0) {
+            (&pool.coin_x, x_in, &pool.coin_y, y_out)
+        } else {
+            (&pool.coin_y, y_in, &pool.coin_x, x_out)
+        };
+
+        TableDexSwap {
+            protocol: protocol.to_string(),
+            id: id.to_string(),
+            pool_id: pool.pool_id.clone(),
+            version: info.version,
+            tx_hash: info.tx_hash.clone(),
+            sender: info.sender.clone(),
+            timestamp: info.timestamp,
+            coin_in: coin_in.clone(),
+            amount_in,
+            coin_out: coin_out.clone(),
+            amount_out,
+        }
+    }
+}
+
+/// Liquidity added to (positive values) or removed from (negative values) a pool.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = dex_liquidity_changes)]
+pub struct TableDexLiquidityChange {
+    pub protocol: String,
+    pub id: String,
+    pub pool_id: String,
+    pub account: String,
+    pub version: i64,
+    pub tx_hash: String,
+    pub timestamp: i64,
+    pub x_val: BigDecimal,
+    pub y_val: BigDecimal,
+    pub lp_tokens: BigDecimal,
+}
+
+/// Latest state of every pool, a pool may only appear once in an upsert.
+pub(crate) fn latest_pools(mut pools: Vec<TableDexPool>) -> Vec<TableDexPool> {
+    pools.sort_by(|a, b| {
+        (&a.protocol, &a.pool_id, b.last_version).cmp(&(&b.protocol, &b.pool_id, a.last_version))
+    });
+    pools.dedup_by(|a, b| a.protocol == b.protocol && a.pool_id == b.pool_id);
+    pools
+}
+
+pub(crate) fn insert_dex_pools_query(
+    items_to_insert: Vec<TableDexPool>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::dex_pools::dsl::*;
+
+    (
+        diesel::insert_into(schema::dex_pools::table)
+            .values(items_to_insert)
+            .on_conflict((protocol, pool_id))
+            .do_update()
+            .set((
+                x_val.eq(excluded(x_val)),
+                y_val.eq(excluded(y_val)),
+                fee_bps.eq(excluded(fee_bps)),
+                last_version.eq(excluded(last_version)),
+            )),
+        Some(" WHERE dex_pools.last_version < EXCLUDED.last_version "),
+    )
+}
+
+pub(crate) fn insert_dex_swaps_query(
+    items_to_insert: Vec<TableDexSwap>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::dex_swaps::dsl::*;
+
+    (
+        diesel::insert_into(schema::dex_swaps::table)
+            .values(items_to_insert)
+            .on_conflict((protocol, id))
+            .do_nothing(),
+        None,
+    )
+}
+
+pub(crate) fn insert_dex_liquidity_changes_query(
+    items_to_insert: Vec<TableDexLiquidityChange>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::dex_liquidity_changes::dsl::*;
+
+    (
+        diesel::insert_into(schema::dex_liquidity_changes::table)
+            .values(items_to_insert)
+            .on_conflict((protocol, id))
+            .do_nothing(),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_direction() {
+        let pool = DexPoolId {
+            pool_id: "pool".to_string(),
+            coin_x: "X".to_string(),
+            coin_y: "Y".to_string(),
+            pool_type: None,
+        };
+        let info = DexTxInfo {
+            version: 1,
+            tx_hash: String::new(),
+            sender: "0x1".to_string(),
+            timestamp: 0,
+        };
+        let zero = || BigDecimal::from(0);
+
+        let swap = TableDexSwap::new(
+            "liquidswap",
+            "ev",
+            &pool,
+            &info,
+            (zero(), 10.into(), 20.into(), zero()),
+        );
+        assert_eq!((swap.coin_in.as_str(), swap.coin_out.as_str()), ("Y", "X"));
+        assert_eq!((swap.amount_in, swap.amount_out), (20.into(), 10.into()));
+    }
+
+    #[test]
+    fn test_latest_pools() {
+        let pool = DexPoolId {
+            pool_id: "pool".to_string(),
+            coin_x: "X".to_string(),
+            coin_y: "Y".to_string(),
+            pool_type: None,
+        };
+        let reserves = |x_val: i64| DexReserves {
+            x_val: x_val.into(),
+            y_val: 1.into(),
+            fee_bps: None,
+        };
+
+        let pools = latest_pools(vec![
+            TableDexPool::new("liquidswap", &pool, Some(reserves(1)), 1),
+            TableDexPool::new("liquidswap", &pool, Some(reserves(3)), 3),
+            TableDexPool::new("liquidswap", &pool, None, 2),
+        ]);
+        assert_eq!(pools.len(), 1);
+        assert_eq!((pools[0].last_version, &pools[0].x_val), (3, &3.into()));
+    }
+}
@@ -0,0 +1,127 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use aptos_protos::transaction::v1::{Event, Transaction};
+use bigdecimal::BigDecimal;
+
+use crate::{
+    processors::ls_processor::{
+        db::LsEventType,
+        events::ObjEventType,
+        info::PoolResourceFromTx,
+        mv::{filter_ls_events, EventLs, MoveStructTagLs},
+    },
+    utils::util::standardize_address,
+};
+
+use super::adapter::{DexAdapter, DexLiquidity, DexPoolId, DexReserves};
+
+/// Liquidswap pools, read with the logic of the `ls_processor`.
+#[derive(Debug)]
+pub(crate) struct LiquidswapAdapter {
+    // Vec<(VERSION_LS,ADDRESS)>, as in the ls_processor config
+    address: Vec<(String, String)>,
+}
+
+impl LiquidswapAdapter {
+    pub(crate) fn new(address: &[(String, String)]) -> Self {
+        LiquidswapAdapter {
+            address: address
+                .iter()
+                .map(|(version_ls, address)| (version_ls.clone(), standardize_address(address)))
+                .collect(),
+        }
+    }
+
+    /// Event type and data of a pool event
+    fn data(event: &Event) -> Result<(LsEventType, Option<ObjEventType>)> {
+        let mv_st = event.move_struct().ok_or(anyhow!("expected Move Struct"))?;
+        let event_type = LsEventType::from_str(&mv_st.name)?;
+        let data = match event_type {
+            LsEventType::SwapEvent
+            | LsEventType::LiquidityAddedEvent
+            | LsEventType::LiquidityRemovedEvent => {
+                let value = event.data_value()?;
+                Some(
+                    serde_json::from_value(value.clone())
+                        .map_err(|err| anyhow!("{err:?}\n{value:?}"))?,
+                )
+            },
+            _ => None,
+        };
+
+        Ok((event_type, data))
+    }
+}
+
+impl DexAdapter for LiquidswapAdapter {
+    fn protocol(&self) -> &'static str {
+        "liquidswap"
+    }
+
+    fn pool_events<'a>(&self, tx: &'a Transaction) -> Vec<&'a Event> {
+        filter_ls_events(&self.address, tx)
+            .map(|events| events.map(|(_version_ls, event)| event).collect())
+            .unwrap_or_default()
+    }
+
+    fn pool(&self, event: &Event) -> Result<DexPoolId> {
+        let pool_type = event
+            .move_struct()
+            .ok_or(anyhow!("expected Move Struct"))?
+            .pool_type()?;
+
+        Ok(DexPoolId {
+            pool_id: pool_type.hash(),
+            coin_x: pool_type.x_name,
+            coin_y: pool_type.y_name,
+            pool_type: Some(pool_type.curve),
+        })
+    }
+
+    fn reserves(&self, tx: &Transaction, event: &Event) -> Result<Option<DexReserves>> {
+        let mv_st = event.move_struct().ok_or(anyhow!("expected Move Struct"))?;
+        let pool = tx.pool_row_from_resources(mv_st, "", tx.version.try_into()?)?;
+
+        Ok(pool.map(|pool| DexReserves {
+            x_val: pool.x_val,
+            y_val: pool.y_val,
+            fee_bps: Some(pool.fee),
+        }))
+    }
+
+    fn swap(
+        &self,
+        event: &Event,
+    ) -> Result<Option<(BigDecimal, BigDecimal, BigDecimal, BigDecimal)>> {
+        let data = match Self::data(event)? {
+            (LsEventType::SwapEvent, Some(data)) => data,
+            _ => return Ok(None),
+        };
+
+        Ok(data.swap_amounts()?.map(|(x_in, x_out, y_in, y_out)| {
+            (x_in.into(), x_out.into(), y_in.into(), y_out.into())
+        }))
+    }
+
+    fn liquidity_change(&self, event: &Event) -> Result<Option<DexLiquidity>> {
+        let (event_type, data) = match Self::data(event)? {
+            (event_type, Some(data)) => (event_type, data),
+            _ => return Ok(None),
+        };
+        let (x_val, y_val, lp_tokens) = match data.lp_change()? {
+            Some(change) => change,
+            None => return Ok(None),
+        };
+        let sign = match event_type {
+            LsEventType::LiquidityRemovedEvent => -1,
+            _ => 1,
+        };
+
+        Ok(Some(DexLiquidity {
+            x_val: (sign * x_val).into(),
+            y_val: (sign * y_val).into(),
+            lp_tokens: (sign * lp_tokens).into(),
+        }))
+    }
+}
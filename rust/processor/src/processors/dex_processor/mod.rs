@@ -0,0 +1,195 @@
+use std::fmt::Debug;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tracing::{error, instrument};
+
+use aptos_protos::transaction::v1::Transaction;
+
+mod adapter;
+mod db;
+mod liquidswap;
+
+use crate::{
+    processors::{ProcessingResult, ProcessorName, ProcessorTrait},
+    utils::database::{BatchWriteMode, BatchWriter, PgDbPool},
+};
+
+use self::{
+    adapter::{DexAdapter, DexEvent},
+    db::{
+        insert_dex_liquidity_changes_query, insert_dex_pools_query, insert_dex_swaps_query,
+        latest_pools, TableDexLiquidityChange, TableDexPool, TableDexSwap,
+    },
+    liquidswap::LiquidswapAdapter,
+};
+
+/// Pools, swaps and liquidity changes of the configured AMMs in one schema.
+pub struct DexProcessor {
+    connection_pool: PgDbPool,
+    write_mode: BatchWriteMode,
+    adapters: Vec<Box<dyn DexAdapter>>,
+}
+
+impl DexProcessor {
+    pub fn new(
+        connection_pool: PgDbPool,
+        write_mode: BatchWriteMode,
+        config: DexProcessorConfig,
+    ) -> Self {
+        let adapters = config
+            .adapters
+            .iter()
+            .map(|adapter| match adapter {
+                DexAdapterConfig::Liquidswap { address } => {
+                    Box::new(LiquidswapAdapter::new(address)) as Box<dyn DexAdapter>
+                },
+            })
+            .collect();
+
+        Self {
+            connection_pool,
+            write_mode,
+            adapters,
+        }
+    }
+}
+
+async fn insert_to_db(
+    writer: &mut BatchWriter,
+    pools: Vec<TableDexPool>,
+    swaps: Vec<TableDexSwap>,
+    liquidity_changes: Vec<TableDexLiquidityChange>,
+) -> Result<(), diesel::result::Error> {
+    writer
+        .execute_in_chunks(
+            insert_dex_pools_query,
+            latest_pools(pools),
+            TableDexPool::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(insert_dex_swaps_query, swaps, TableDexSwap::field_count())
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_dex_liquidity_changes_query,
+            liquidity_changes,
+            TableDexLiquidityChange::field_count(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+impl Debug for DexProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.connection_pool.state();
+        write!(
+            f,
+            "DexProcessor {{ connections: {:?}  idle_connections: {:?} }}",
+            state.connections, state.idle_connections
+        )
+    }
+}
+
+#[async_trait]
+impl ProcessorTrait for DexProcessor {
+    fn name(&self) -> &'static str {
+        ProcessorName::DexProcessor.into()
+    }
+
+    #[instrument(level = "debug", skip(self, transactions))]
+    async fn process_transactions(
+        &self,
+        transactions: &[Transaction],
+        start_version: u64,
+        end_version: u64,
+        _: Option<u64>,
+    ) -> anyhow::Result<ProcessingResult> {
+        let processing_start = std::time::Instant::now();
+        let last_transaction_timstamp = transactions.last().and_then(|t| t.timestamp.clone());
+
+        let mut pools = Vec::new();
+        let mut swaps = Vec::new();
+        let mut liquidity_changes = Vec::new();
+        for adapter in &self.adapters {
+            for tx in transactions {
+                for ev in adapter.events_from_tx(tx)? {
+                    match ev {
+                        DexEvent::Pools(pool) => pools.push(pool),
+                        DexEvent::Swaps(swap) => swaps.push(swap),
+                        DexEvent::LiquidityChanges(change) => liquidity_changes.push(change),
+                    }
+                }
+            }
+        }
+
+        let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
+        if pools.is_empty() {
+            return Ok(ProcessingResult {
+                start_version,
+                end_version,
+                processing_duration_in_secs,
+                db_insertion_duration_in_secs: 0_f64,
+                last_transaction_timstamp,
+            });
+        }
+
+        let db_insertion_start = std::time::Instant::now();
+
+        let insert_result = async {
+            let mut writer = BatchWriter::new(self.get_pool(), self.write_mode).await?;
+            insert_to_db(&mut writer, pools, swaps, liquidity_changes).await?;
+            self.commit_batch(writer, end_version, last_transaction_timstamp.clone())
+                .await
+        }
+        .await;
+        insert_result
+            .map_err(|err| {
+                error!(
+                    start_version = start_version,
+                    end_version = end_version,
+                    processor_name = self.name(),
+                    ?err,
+                    "[Parser] Error inserting transactions to db",
+                );
+                err
+            })
+            .context(format!(
+                "Error inserting transactions to db. Processor {}. Start {}. End {}.",
+                self.name(),
+                start_version,
+                end_version
+            ))?;
+
+        let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
+
+        Ok(ProcessingResult {
+            start_version,
+            end_version,
+            processing_duration_in_secs,
+            db_insertion_duration_in_secs,
+            last_transaction_timstamp,
+        })
+    }
+
+    fn connection_pool(&self) -> &PgDbPool {
+        &self.connection_pool
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DexProcessorConfig {
+    adapters: Vec<DexAdapterConfig>,
+}
+
+/// A protocol to index and where it is deployed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "protocol", rename_all = "snake_case")]
+pub enum DexAdapterConfig {
+    // Vec<(VERSION_LS,ADDRESS)>, as `address` of the ls_processor
+    Liquidswap { address: Vec<(String, String)> },
+}
@@ -88,14 +88,14 @@ impl ProcessorTrait for EventsProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
     ) -> anyhow::Result<ProcessingResult> {
         let processing_start = std::time::Instant::now();
         let mut events = vec![];
-        for txn in &transactions {
+        for txn in transactions {
             let txn_version = txn.version as i64;
             let block_height = txn.block_height as i64;
             let txn_data = match txn.txn_data.as_ref() {
@@ -25,7 +25,7 @@ use crate::{
     },
 };
 use ahash::AHashMap;
-use anyhow::bail;
+use anyhow::Context;
 use aptos_protos::transaction::v1::{transaction::TxnData, write_set_change::Change, Transaction};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
@@ -219,7 +219,7 @@ impl ProcessorTrait for FungibleAssetProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -231,7 +231,7 @@ impl ProcessorTrait for FungibleAssetProcessor {
             fungible_asset_metadata,
             fungible_asset_balances,
             current_fungible_asset_balances,
-        ) = parse_v2_coin(&transactions, &mut conn).await;
+        ) = parse_v2_coin(transactions, &mut conn).await;
 
         let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
         let db_insertion_start = std::time::Instant::now();
@@ -264,7 +264,12 @@ impl ProcessorTrait for FungibleAssetProcessor {
                     "[Parser] Error inserting transactions to db: {:?}",
                     err
                 );
-                bail!(format!("Error inserting transactions to db. Processor {}. Start {}. End {}. Error {:?}", self.name(), start_version, end_version, err))
+                Err(err).context(format!(
+                    "Error inserting transactions to db. Processor {}. Start {}. End {}.",
+                    self.name(),
+                    start_version,
+                    end_version
+                ))
             },
         }
     }
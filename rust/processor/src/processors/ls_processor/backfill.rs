@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use diesel::{ExpressionMethods, QueryDsl, Queryable, Selectable, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+
+use crate::{
+    schema::{self, ls_events},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
+};
+
+use super::{
+    db::{backfill_ls_events_query, LsEventType, TableLsEvent, TableLsPoolSnapshot},
+    events::ObjEventType,
+};
+
+/// Rows of ls_events the backfill went through.
+#[derive(Debug, Default)]
+pub struct BackfillReport {
+    pub events: usize,
+    // Events with gross amounts
+    pub with_amounts: usize,
+    // Events with reserves, the others are from transactions without a ls_pool_snapshots row
+    pub with_reserves: usize,
+    // Last version done, to continue from
+    pub last_version: i64,
+}
+
+/// A stored ls_events row. `sq` is only optional in `TableLsEvent` for the processor to leave it to
+/// the database.
+#[derive(Selectable, Queryable, Debug)]
+#[diesel(table_name = ls_events)]
+struct LsEventRow {
+    id: String,
+    pool_id: String,
+    tp: LsEventType,
+    version: i64,
+    tx_hash: String,
+    sender: String,
+    event: serde_json::Value,
+    timestamp: i64,
+    x_val: Option<BigDecimal>,
+    y_val: Option<BigDecimal>,
+    fee: Option<i64>,
+    dao_fee: Option<i64>,
+    sq: i64,
+    x_val_normalized: Option<BigDecimal>,
+    y_val_normalized: Option<BigDecimal>,
+    x_in: Option<BigDecimal>,
+    x_out: Option<BigDecimal>,
+    y_in: Option<BigDecimal>,
+    y_out: Option<BigDecimal>,
+    lp_tokens: Option<BigDecimal>,
+    x_reserve: Option<BigDecimal>,
+    y_reserve: Option<BigDecimal>,
+}
+
+impl From<LsEventRow> for TableLsEvent {
+    fn from(row: LsEventRow) -> Self {
+        TableLsEvent {
+            id: row.id,
+            pool_id: row.pool_id,
+            tp: row.tp,
+            version: row.version,
+            tx_hash: row.tx_hash,
+            sender: row.sender,
+            event: row.event,
+            timestamp: row.timestamp,
+            x_val: row.x_val,
+            y_val: row.y_val,
+            fee: row.fee,
+            dao_fee: row.dao_fee,
+            sq: Some(row.sq),
+            x_val_normalized: row.x_val_normalized,
+            y_val_normalized: row.y_val_normalized,
+            x_in: row.x_in,
+            x_out: row.x_out,
+            y_in: row.y_in,
+            y_out: row.y_out,
+            lp_tokens: row.lp_tokens,
+            x_reserve: row.x_reserve,
+            y_reserve: row.y_reserve,
+        }
+    }
+}
+
+/// Re-derives the gross amounts of ls_events from the stored event body and the reserves after
+/// each event from ls_pool_snapshots, for the events after `from_version`, `batch_size`
+/// transactions at a time.
+///
+/// The events of a transaction are walked in the order they were inserted in, i.e. by `sq`.
+pub async fn backfill(
+    conn: &mut PgPoolConnection<'_>,
+    from_version: i64,
+    batch_size: i64,
+) -> Result<BackfillReport> {
+    let mut report = BackfillReport {
+        last_version: from_version,
+        ..Default::default()
+    };
+
+    loop {
+        let versions: Vec<i64> = schema::ls_events::table
+            .select(schema::ls_events::version)
+            .filter(schema::ls_events::version.gt(report.last_version))
+            .distinct()
+            .order(schema::ls_events::version.asc())
+            .limit(batch_size)
+            .load(conn)
+            .await?;
+        let (first, last) = match (versions.first(), versions.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => break,
+        };
+
+        let events = schema::ls_events::table
+            .filter(schema::ls_events::version.between(first, last))
+            .order((
+                schema::ls_events::version.asc(),
+                schema::ls_events::sq.asc(),
+            ))
+            .select(LsEventRow::as_select())
+            .load::<LsEventRow>(conn)
+            .await?
+            .into_iter()
+            .map(TableLsEvent::from)
+            .collect();
+        let snapshots = schema::ls_pool_snapshots::table
+            .filter(schema::ls_pool_snapshots::version.between(first, last))
+            .select(TableLsPoolSnapshot::as_select())
+            .load::<TableLsPoolSnapshot>(conn)
+            .await?;
+
+        let events = backfill_events(events, snapshots)?;
+        report.events += events.len();
+        report.with_amounts += events
+            .iter()
+            .filter(|event| event.x_in.is_some() || event.x_out.is_some())
+            .count();
+        report.with_reserves += events
+            .iter()
+            .filter(|event| event.x_reserve.is_some())
+            .count();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            backfill_ls_events_query,
+            events,
+            TableLsEvent::field_count(),
+        )
+        .await?;
+        report.last_version = last;
+    }
+
+    Ok(report)
+}
+
+/// `events` of whole transactions, ordered by version and sq
+fn backfill_events(
+    events: Vec<TableLsEvent>,
+    snapshots: Vec<TableLsPoolSnapshot>,
+) -> Result<Vec<TableLsEvent>> {
+    let mut reserves: HashMap<i64, HashMap<String, (BigDecimal, BigDecimal)>> = HashMap::new();
+    for snapshot in snapshots {
+        reserves
+            .entry(snapshot.version)
+            .or_default()
+            .insert(snapshot.pool_id, (snapshot.x_val, snapshot.y_val));
+    }
+
+    let mut txs: BTreeMap<i64, Vec<TableLsEvent>> = BTreeMap::new();
+    for mut event in events {
+        let data = ObjEventType::from_event(event.tp, &event.event)
+            .with_context(|| format!("event {}", event.id))?;
+        event.set_amounts(data.amounts()?);
+        txs.entry(event.version).or_default().push(event);
+    }
+
+    let no_reserves = HashMap::new();
+    Ok(txs
+        .into_iter()
+        .flat_map(|(version, mut events)| {
+            TableLsEvent::set_reserves(
+                events.iter_mut(),
+                reserves.get(&version).unwrap_or(&no_reserves),
+            );
+            events
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(
+        sq: i64,
+        tp: LsEventType,
+        body: serde_json::Value,
+        (x_val, y_val): (i64, i64),
+    ) -> TableLsEvent {
+        TableLsEvent {
+            id: format!("ev_{sq}"),
+            pool_id: "pool".to_string(),
+            tp,
+            version: 1,
+            tx_hash: String::new(),
+            sender: "0x1".to_string(),
+            event: body,
+            timestamp: 0,
+            x_val: Some(x_val.into()),
+            y_val: Some(y_val.into()),
+            fee: None,
+            dao_fee: None,
+            sq: Some(sq),
+            x_val_normalized: None,
+            y_val_normalized: None,
+            x_in: None,
+            x_out: None,
+            y_in: None,
+            y_out: None,
+            lp_tokens: None,
+            x_reserve: None,
+            y_reserve: None,
+        }
+    }
+
+    #[test]
+    fn test_backfill_events() {
+        let events = vec![
+            event(
+                1,
+                LsEventType::LiquidityAddedEvent,
+                serde_json::json!({
+                    "added_x_val": "100", "added_y_val": "200", "lp_tokens_received": "10"
+                }),
+                (100, 200),
+            ),
+            event(
+                2,
+                LsEventType::SwapEvent,
+                serde_json::json!({"x_in": "10", "x_out": "0", "y_in": "0", "y_out": "18"}),
+                (10, -18),
+            ),
+        ];
+        let snapshots = vec![TableLsPoolSnapshot {
+            pool_id: "pool".to_string(),
+            version: 1,
+            timestamp: 0,
+            x_val: 110.into(),
+            y_val: 182.into(),
+            fee: 30,
+            dao_fee: 33,
+            lp_supply: None,
+        }];
+
+        let events = backfill_events(events, snapshots).unwrap();
+        let added = &events[0];
+        assert_eq!(added.x_in, Some(100.into()));
+        assert_eq!(added.x_out, None);
+        assert_eq!(added.lp_tokens, Some(10.into()));
+        assert_eq!(
+            (added.x_reserve.clone(), added.y_reserve.clone()),
+            (Some(100.into()), Some(200.into()))
+        );
+        let swap = &events[1];
+        assert_eq!(
+            (swap.x_in.clone(), swap.y_out.clone()),
+            (Some(10.into()), Some(18.into()))
+        );
+        assert_eq!(
+            (swap.x_reserve.clone(), swap.y_reserve.clone()),
+            (Some(110.into()), Some(182.into()))
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    fmt::{Display, Formatter},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use bigdecimal::BigDecimal;
+use diesel::{query_dsl::methods::FilterDsl, ExpressionMethods};
+use diesel_async::{AsyncConnection, RunQueryDsl};
+
+use crate::{schema, utils::database::PgPoolConnection};
+
+use super::db::{InsertToDb, LsEventType, TableLsEvent, TableLsPool};
+
+// Events per insert before the tables moved to `execute_in_chunks`
+const FIXED_CHUNK_SIZE: usize = 100;
+
+/// Time to write the same number of rows the previous way and with `execute_in_chunks`.
+#[derive(Debug)]
+pub struct InsertBench {
+    pub table: &'static str,
+    pub rows: usize,
+    pub before: Duration,
+    pub after: Duration,
+}
+
+impl InsertBench {
+    fn rows_per_sec(&self, duration: Duration) -> f64 {
+        self.rows as f64 / duration.as_secs_f64()
+    }
+}
+
+impl Display for InsertBench {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} rows, before {:.0} rows/s ({:?}), after {:.0} rows/s ({:?}), x{:.1}",
+            self.table,
+            self.rows,
+            self.rows_per_sec(self.before),
+            self.before,
+            self.rows_per_sec(self.after),
+            self.after,
+            self.before.as_secs_f64() / self.after.as_secs_f64(),
+        )
+    }
+}
+
+/// Writes `pools` synthetic ls_pools rows and `events` ls_events rows, once with an upsert per
+/// pool and chunks of `FIXED_CHUNK_SIZE` events as the processor used to, and once the way it does
+/// now.
+///
+/// Everything runs in a test transaction, nothing is committed. The transaction is never closed,
+/// which is only fine because `ls_tools` exits right after: the connection must not go back to
+/// the pool.
+pub async fn bench_inserts(
+    conn: &mut PgPoolConnection<'_>,
+    pools: usize,
+    events: usize,
+) -> Result<Vec<InsertBench>> {
+    conn.begin_test_transaction().await?;
+
+    let start = Instant::now();
+    insert_pools_per_row(conn, &pool_rows("bench_before", pools)).await?;
+    let before = start.elapsed();
+    let start = Instant::now();
+    pool_rows("bench_after", pools).insert_to_db(conn).await?;
+    let after = start.elapsed();
+    let pools_bench = InsertBench {
+        table: "ls_pools",
+        rows: pools,
+        before,
+        after,
+    };
+
+    let start = Instant::now();
+    insert_events_fixed_chunks(conn, &event_rows("bench_before", events)).await?;
+    let before = start.elapsed();
+    let start = Instant::now();
+    event_rows("bench_after", events).insert_to_db(conn).await?;
+    let after = start.elapsed();
+    let events_bench = InsertBench {
+        table: "ls_events",
+        rows: events,
+        before,
+        after,
+    };
+
+    Ok(vec![pools_bench, events_bench])
+}
+
+fn pool_rows(prefix: &str, count: usize) -> Vec<TableLsPool> {
+    (0..count)
+        .map(|i| TableLsPool {
+            id: format!("{prefix}_{i}"),
+            version_ls: "v0".to_string(),
+            x_name: "0x1::aptos_coin::AptosCoin".to_string(),
+            y_name: format!("0x1::bench::Coin{i}"),
+            curve: "Uncorrelated".to_string(),
+            x_val: BigDecimal::from(1_000_000),
+            y_val: BigDecimal::from(2_000_000),
+            fee: 30,
+            dao_fee: 33,
+            last_version: i as i64 + 1,
+            x_symbol: Some("APT".to_string()),
+            y_symbol: None,
+            x_decimals: Some(8),
+            y_decimals: None,
+            x_val_normalized: Some(BigDecimal::from(1)),
+            y_val_normalized: None,
+        })
+        .collect()
+}
+
+fn event_rows(prefix: &str, count: usize) -> Vec<TableLsEvent> {
+    (0..count)
+        .map(|i| TableLsEvent {
+            id: format!("{prefix}_{i}"),
+            pool_id: format!("{prefix}_{}", i % 100),
+            tp: LsEventType::SwapEvent,
+            version: i as i64 + 1,
+            tx_hash: format!("{i:064x}"),
+            sender: "0x1".to_string(),
+            event: serde_json::json!({
+                "x_in": "1000", "x_out": "0", "y_in": "0", "y_out": "1990"
+            }),
+            timestamp: i as i64,
+            x_val: Some(BigDecimal::from(1000)),
+            y_val: Some(BigDecimal::from(-1990)),
+            fee: Some(30),
+            dao_fee: Some(33),
+            sq: Some(i as i64),
+            x_val_normalized: None,
+            y_val_normalized: None,
+            x_in: Some(BigDecimal::from(1000)),
+            x_out: Some(BigDecimal::from(0)),
+            y_in: Some(BigDecimal::from(0)),
+            y_out: Some(BigDecimal::from(1990)),
+            lp_tokens: None,
+            x_reserve: None,
+            y_reserve: None,
+        })
+        .collect()
+}
+
+async fn insert_pools_per_row(conn: &mut PgPoolConnection<'_>, rows: &[TableLsPool]) -> Result<()> {
+    for pool in rows {
+        diesel::insert_into(schema::ls_pools::table)
+            .values(pool)
+            .on_conflict(schema::ls_pools::id)
+            .do_update()
+            .set((
+                schema::ls_pools::x_val.eq(&pool.x_val),
+                schema::ls_pools::y_val.eq(&pool.y_val),
+                schema::ls_pools::fee.eq(&pool.fee),
+                schema::ls_pools::dao_fee.eq(&pool.dao_fee),
+                schema::ls_pools::last_version.eq(&pool.last_version),
+                schema::ls_pools::x_symbol.eq(&pool.x_symbol),
+                schema::ls_pools::y_symbol.eq(&pool.y_symbol),
+                schema::ls_pools::x_decimals.eq(&pool.x_decimals),
+                schema::ls_pools::y_decimals.eq(&pool.y_decimals),
+                schema::ls_pools::x_val_normalized.eq(&pool.x_val_normalized),
+                schema::ls_pools::y_val_normalized.eq(&pool.y_val_normalized),
+            ))
+            .filter(schema::ls_pools::last_version.lt(&pool.last_version))
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn insert_events_fixed_chunks(
+    conn: &mut PgPoolConnection<'_>,
+    rows: &[TableLsEvent],
+) -> Result<()> {
+    for rows in rows.chunks(FIXED_CHUNK_SIZE) {
+        diesel::insert_into(schema::ls_events::table)
+            .values(rows)
+            .on_conflict(schema::ls_events::id)
+            .do_nothing()
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,373 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, Zero};
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, upsert::excluded, ExpressionMethods, Insertable,
+    QueryDsl, Queryable, Selectable, SelectableHelper,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_candles},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
+};
+
+use super::{
+    coins::TableLsCoin,
+    db::{InsertToDb, TableLsPool, PRICE_SCALE},
+    events::LsEvent,
+};
+
+/// A swap with the price of the pool after it.
+#[derive(Debug, Clone)]
+pub struct LsSwap {
+    pub pool_id: String,
+    pub version: i64,
+    // Index of the event in the transaction
+    pub event_index: i64,
+    pub timestamp: i64,
+    // (x_val, y_val) of the pool after the swap
+    pub reserves: Option<(BigDecimal, BigDecimal)>,
+    // Y per X in whole coins after the swap, if the reserves and the decimals are known
+    pub price: Option<BigDecimal>,
+    // x_in + x_out
+    pub base_volume: BigDecimal,
+    // y_in + y_out
+    pub quote_volume: BigDecimal,
+    pub x_in: BigDecimal,
+    pub y_in: BigDecimal,
+    // Fee settings of the pool at the swap
+    pub fee: i64,
+    pub dao_fee: i64,
+}
+
+impl LsSwap {
+    /// The reserves are set from the event row, the fees by `LsEvent::set_swap_fees` and the price
+    /// by `LsCoins::enrich`.
+    pub(crate) fn new(
+        pool: &TableLsPool,
+        version: i64,
+        event_index: i64,
+        timestamp: i64,
+        (x_in, x_out, y_in, y_out): (i128, i128, i128, i128),
+    ) -> Self {
+        LsSwap {
+            pool_id: pool.id.clone(),
+            version,
+            event_index,
+            timestamp,
+            reserves: None,
+            price: None,
+            base_volume: (x_in + x_out).into(),
+            quote_volume: (y_in + y_out).into(),
+            x_in: x_in.into(),
+            y_in: y_in.into(),
+            fee: pool.fee,
+            dao_fee: pool.dao_fee,
+        }
+    }
+
+    /// No price if the pool has no X reserve after the swap.
+    pub(crate) fn set_price(&mut self, x_coin: &TableLsCoin, y_coin: &TableLsCoin) {
+        self.price = self.reserves.as_ref().and_then(|(x_val, y_val)| {
+            let x_val = x_coin.normalize(x_val);
+            (!x_val.is_zero()).then(|| (y_coin.normalize(y_val) / x_val).with_scale(PRICE_SCALE))
+        });
+    }
+
+    pub(crate) fn position(&self) -> (i64, i64) {
+        (self.version, self.event_index)
+    }
+}
+
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_candles)]
+pub struct TableLsCandle {
+    pub pool_id: String,
+    pub interval_secs: i64,
+    pub start_time: i64,
+    pub open: BigDecimal,
+    pub high: BigDecimal,
+    pub low: BigDecimal,
+    pub close: BigDecimal,
+    pub base_volume: BigDecimal,
+    pub quote_volume: BigDecimal,
+    pub trades: i64,
+    pub first_version: i64,
+    pub last_version: i64,
+    pub last_event_index: i64,
+}
+
+type CandleKey = (String, i64, i64);
+
+impl TableLsCandle {
+    fn new(swap: &LsSwap, price: &BigDecimal, interval_secs: i64) -> Self {
+        TableLsCandle {
+            pool_id: swap.pool_id.clone(),
+            interval_secs,
+            start_time: start_time(swap.timestamp, interval_secs),
+            open: price.clone(),
+            high: price.clone(),
+            low: price.clone(),
+            close: price.clone(),
+            base_volume: swap.base_volume.clone(),
+            quote_volume: swap.quote_volume.clone(),
+            trades: 1,
+            first_version: swap.version,
+            last_version: swap.version,
+            last_event_index: swap.event_index,
+        }
+    }
+
+    fn key(&self) -> CandleKey {
+        (self.pool_id.clone(), self.interval_secs, self.start_time)
+    }
+
+    /// Merges a swap that comes after the last one of the candle.
+    /// @return: false if the swap is already part of the candle
+    fn add(&mut self, swap: &LsSwap, price: &BigDecimal) -> bool {
+        if swap.position() <= (self.last_version, self.last_event_index) {
+            return false;
+        }
+
+        if price > &self.high {
+            self.high = price.clone();
+        }
+        if price < &self.low {
+            self.low = price.clone();
+        }
+        self.close = price.clone();
+        self.base_volume += &swap.base_volume;
+        self.quote_volume += &swap.quote_volume;
+        self.trades += 1;
+        self.last_version = swap.version;
+        self.last_event_index = swap.event_index;
+        true
+    }
+}
+
+#[inline]
+pub(crate) fn start_time(timestamp: i64, interval_secs: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(interval_secs)
+}
+
+/// Swaps of a batch with a price, merged into the candles of every interval.
+///
+/// A swap is only merged into a candle if it comes after the last swap of that candle, so
+/// reprocessing a batch does not count it twice.
+pub(crate) struct LsCandles {
+    swaps: Vec<LsSwap>,
+    intervals: Vec<i64>,
+}
+
+impl LsCandles {
+    pub(crate) fn new(events: &[LsEvent], intervals: &[i64]) -> Self {
+        let mut swaps = events
+            .iter()
+            .filter_map(|ev| match ev {
+                LsEvent::Swaps(swap) if swap.price.is_some() => Some(swap.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        swaps.sort_by_key(|swap| swap.position());
+
+        LsCandles {
+            swaps,
+            intervals: intervals.to_vec(),
+        }
+    }
+
+    fn build(self, existing: Vec<TableLsCandle>) -> Vec<TableLsCandle> {
+        let mut candles: HashMap<CandleKey, TableLsCandle> = existing
+            .into_iter()
+            .map(|candle| (candle.key(), candle))
+            .collect();
+        let mut updated = HashSet::new();
+
+        for swap in &self.swaps {
+            let price = match &swap.price {
+                Some(price) => price,
+                None => continue,
+            };
+            for &interval_secs in &self.intervals {
+                let key = (
+                    swap.pool_id.clone(),
+                    interval_secs,
+                    start_time(swap.timestamp, interval_secs),
+                );
+                let is_updated = match candles.get_mut(&key) {
+                    Some(candle) => candle.add(swap, price),
+                    None => {
+                        candles.insert(key.clone(), TableLsCandle::new(swap, price, interval_secs));
+                        true
+                    },
+                };
+                if is_updated {
+                    updated.insert(key);
+                }
+            }
+        }
+
+        candles
+            .into_iter()
+            .filter_map(|(key, candle)| updated.contains(&key).then_some(candle))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InsertToDb for LsCandles {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        if self.swaps.is_empty() || self.intervals.is_empty() {
+            return Ok(());
+        }
+
+        let pool_ids = self
+            .swaps
+            .iter()
+            .map(|swap| swap.pool_id.clone())
+            .collect::<HashSet<_>>();
+        let start_times = self
+            .swaps
+            .iter()
+            .flat_map(|swap| {
+                self.intervals
+                    .iter()
+                    .map(|&interval_secs| start_time(swap.timestamp, interval_secs))
+            })
+            .collect::<HashSet<_>>();
+
+        // Candles the swaps fall in, whose last (version, event index) skips swaps already merged.
+        // Start times of other pools' swaps may come along, only updated candles are written back.
+        let existing = schema::ls_candles::table
+            .filter(schema::ls_candles::pool_id.eq_any(pool_ids))
+            .filter(schema::ls_candles::start_time.eq_any(start_times))
+            .select(TableLsCandle::as_select())
+            .load::<TableLsCandle>(conn)
+            .await?;
+
+        let candles = self.build(existing);
+
+        let count = candles.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_candles_query,
+            candles,
+            TableLsCandle::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsCandle added/updated");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_candles_query(
+    items_to_insert: Vec<TableLsCandle>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_candles::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_candles::table)
+            .values(items_to_insert)
+            .on_conflict((pool_id, interval_secs, start_time))
+            .do_update()
+            .set((
+                open.eq(excluded(open)),
+                high.eq(excluded(high)),
+                low.eq(excluded(low)),
+                close.eq(excluded(close)),
+                base_volume.eq(excluded(base_volume)),
+                quote_volume.eq(excluded(quote_volume)),
+                trades.eq(excluded(trades)),
+                first_version.eq(excluded(first_version)),
+                last_version.eq(excluded(last_version)),
+                last_event_index.eq(excluded(last_event_index)),
+            )),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swap(version: i64, event_index: i64, timestamp: i64, price: i64) -> LsSwap {
+        LsSwap {
+            pool_id: "pool".to_string(),
+            version,
+            event_index,
+            timestamp,
+            reserves: None,
+            price: Some(price.into()),
+            base_volume: 10.into(),
+            quote_volume: (10 * price).into(),
+            x_in: 10.into(),
+            y_in: 0.into(),
+            fee: 30,
+            dao_fee: 33,
+        }
+    }
+
+    fn candles(swaps: Vec<LsSwap>, existing: Vec<TableLsCandle>) -> Vec<TableLsCandle> {
+        let mut candles = LsCandles {
+            swaps,
+            intervals: vec![60],
+        }
+        .build(existing);
+        candles.sort_by_key(|candle| candle.start_time);
+        candles
+    }
+
+    #[test]
+    fn test_candles_span_interval_boundary() {
+        let result = candles(
+            vec![
+                swap(1, 0, 110, 5),
+                swap(2, 1, 115, 7),
+                swap(2, 2, 119, 3),
+                swap(3, 3, 120, 4),
+            ],
+            vec![],
+        );
+
+        assert_eq!(result.len(), 2);
+        let first = &result[0];
+        assert_eq!(first.start_time, 60);
+        assert_eq!(
+            (&first.open, &first.high, &first.low, &first.close),
+            (&5.into(), &7.into(), &3.into(), &3.into())
+        );
+        assert_eq!(first.base_volume, 30.into());
+        assert_eq!(first.trades, 3);
+        assert_eq!(result[1].start_time, 120);
+        assert_eq!(result[1].trades, 1);
+    }
+
+    #[test]
+    fn test_candles_are_idempotent() {
+        let swaps = vec![swap(1, 0, 110, 5), swap(2, 1, 115, 7)];
+        let first = candles(swaps.clone(), vec![]);
+
+        // Same batch again
+        assert!(candles(swaps.clone(), first.clone()).is_empty());
+
+        // Batch overlapping the processed one
+        let mut overlapping = swaps;
+        overlapping.push(swap(3, 2, 116, 6));
+        let second = candles(overlapping, first);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].trades, 3);
+        assert_eq!(second[0].close, 6.into());
+        assert_eq!(second[0].base_volume, 30.into());
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use aptos_protos::transaction::v1::{write_set_change::Change, Transaction};
+use bigdecimal::BigDecimal;
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, ExpressionMethods, Insertable, QueryDsl, Queryable,
+    Selectable, SelectableHelper,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_coins},
+    utils::{
+        database::{execute_in_chunks_in_transaction, PgPoolConnection},
+        util::{hash_str, standardize_address},
+    },
+};
+
+use super::{db::InsertToDb, events::LsEvent, mv::move_type_to_string};
+
+const COIN_MODULE_NAME: &str = "coin";
+const COIN_INFO_NAME: &str = "CoinInfo";
+
+/// Metadata of a coin, from its 0x1::coin::CoinInfo.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_coins)]
+pub struct TableLsCoin {
+    // hash_str(coin_type), same as coin_infos.coin_type_hash
+    pub coin_type_hash: String,
+    pub coin_type: String,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: i32,
+    pub version: i64,
+}
+
+impl TableLsCoin {
+    /// Raw amount in whole coins.
+    pub(crate) fn normalize(&self, value: &BigDecimal) -> BigDecimal {
+        let (digits, scale) = value.as_bigint_and_exponent();
+        BigDecimal::new(digits, scale + i64::from(self.decimals))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinInfoResource {
+    name: String,
+    symbol: String,
+    decimals: i32,
+}
+
+/// Coin metadata for the ls tables, by coin type.
+///
+/// Coins are resolved from the CoinInfo write resources of the batch, then from `ls_coins`
+/// and last from `coin_infos` of the coin processor. Every CoinInfo write is kept in `ls_coins`,
+/// also in batches without LS events, so a coin created before its first pool is known then.
+pub(crate) struct LsCoins {
+    coins: HashMap<String, TableLsCoin>,
+    // Coin types loaded from `ls_coins`
+    stored: HashSet<String>,
+}
+
+impl LsCoins {
+    pub(crate) fn try_from_txs(transactions: &[Transaction]) -> Result<Self> {
+        let coin_addr = standardize_address("0x1");
+        let mut coins = HashMap::new();
+
+        for tx in transactions {
+            let changes = tx.info.iter().flat_map(|info| info.changes.iter());
+            for ch in changes.filter_map(|ch| ch.change.as_ref()) {
+                let resource = match ch {
+                    Change::WriteResource(resource) => resource,
+                    _ => continue,
+                };
+                let tp = match resource.r#type.as_ref() {
+                    Some(tp) => tp,
+                    None => continue,
+                };
+                if tp.module != COIN_MODULE_NAME
+                    || tp.name != COIN_INFO_NAME
+                    || standardize_address(&tp.address) != coin_addr
+                {
+                    continue;
+                }
+                let coin_type = match tp.generic_type_params.first().and_then(move_type_to_string) {
+                    Some(coin_type) => coin_type,
+                    None => continue,
+                };
+
+                let coin_info: CoinInfoResource = serde_json::from_str(&resource.data)
+                    .with_context(|| format!("Data: {}", &resource.data))?;
+                coins.insert(
+                    coin_type.clone(),
+                    TableLsCoin {
+                        coin_type_hash: hash_str(&coin_type),
+                        coin_type,
+                        name: coin_info.name,
+                        symbol: coin_info.symbol,
+                        decimals: coin_info.decimals,
+                        version: tx.version.try_into().context("tx version")?,
+                    },
+                );
+            }
+        }
+
+        Ok(LsCoins {
+            coins,
+            stored: HashSet::new(),
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+
+    pub(crate) fn get(&self, coin_type: &str) -> Option<&TableLsCoin> {
+        self.coins.get(coin_type)
+    }
+
+    /// Sets the coin metadata and the normalized amounts of the pools and events, and the prices
+    /// of the swaps. The gross amounts of the events are not normalized, see `TableLsEvent`.
+    pub(crate) async fn enrich(
+        &mut self,
+        events: &mut [LsEvent],
+        conn: &mut PgPoolConnection<'_>,
+    ) -> Result<()> {
+        let pool_coins = pool_coins(events, conn).await?;
+        let coin_types = pool_coins
+            .values()
+            .flat_map(|(x_name, y_name)| [x_name, y_name])
+            .collect::<HashSet<_>>();
+        self.load(coin_types, conn).await?;
+
+        for ev in events.iter_mut() {
+            match ev {
+                LsEvent::Pools(pool) => {
+                    let x_coin = self.coins.get(&pool.x_name);
+                    let y_coin = self.coins.get(&pool.y_name);
+                    pool.x_symbol = x_coin.map(|coin| coin.symbol.clone());
+                    pool.y_symbol = y_coin.map(|coin| coin.symbol.clone());
+                    pool.x_decimals = x_coin.map(|coin| coin.decimals);
+                    pool.y_decimals = y_coin.map(|coin| coin.decimals);
+                    pool.x_val_normalized = x_coin.map(|coin| coin.normalize(&pool.x_val));
+                    pool.y_val_normalized = y_coin.map(|coin| coin.normalize(&pool.y_val));
+                },
+                LsEvent::Events(event) => {
+                    let (x_name, y_name) = match pool_coins.get(&event.pool_id) {
+                        Some(names) => names,
+                        None => continue,
+                    };
+                    event.x_val_normalized = event
+                        .x_val
+                        .as_ref()
+                        .zip(self.coins.get(x_name))
+                        .map(|(x_val, coin)| coin.normalize(x_val));
+                    event.y_val_normalized = event
+                        .y_val
+                        .as_ref()
+                        .zip(self.coins.get(y_name))
+                        .map(|(y_val, coin)| coin.normalize(y_val));
+                },
+                LsEvent::Swaps(swap) => {
+                    let (x_name, y_name) = match pool_coins.get(&swap.pool_id) {
+                        Some(names) => names,
+                        None => continue,
+                    };
+                    if let (Some(x_coin), Some(y_coin)) =
+                        (self.coins.get(x_name), self.coins.get(y_name))
+                    {
+                        swap.set_price(x_coin, y_coin);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the coins not seen in the batch from `ls_coins`, then from `coin_infos`.
+    async fn load(
+        &mut self,
+        coin_types: HashSet<&String>,
+        conn: &mut PgPoolConnection<'_>,
+    ) -> Result<()> {
+        let mut missing = coin_types
+            .into_iter()
+            .filter(|coin_type| !self.coins.contains_key(*coin_type))
+            .map(|coin_type| (hash_str(coin_type), coin_type.clone()))
+            .collect::<HashMap<_, _>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let known = schema::ls_coins::table
+            .filter(schema::ls_coins::coin_type_hash.eq_any(missing.keys()))
+            .select(TableLsCoin::as_select())
+            .load::<TableLsCoin>(conn)
+            .await?;
+        for coin in known {
+            missing.remove(&coin.coin_type_hash);
+            self.stored.insert(coin.coin_type.clone());
+            self.coins.insert(coin.coin_type.clone(), coin);
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        use schema::coin_infos::dsl;
+        let coin_infos = dsl::coin_infos
+            .filter(dsl::coin_type_hash.eq_any(missing.keys()))
+            .select((
+                dsl::coin_type_hash,
+                dsl::name,
+                dsl::symbol,
+                dsl::decimals,
+                dsl::transaction_version_created,
+            ))
+            .load::<(String, String, String, i32, i64)>(conn)
+            .await?;
+        for (coin_type_hash, name, symbol, decimals, version) in coin_infos {
+            if let Some(coin_type) = missing.remove(&coin_type_hash) {
+                self.coins.insert(
+                    coin_type.clone(),
+                    TableLsCoin {
+                        coin_type_hash,
+                        coin_type,
+                        name,
+                        symbol,
+                        decimals,
+                        version,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// (x_name, y_name) of the pools of the events, from the batch or from `ls_pools`.
+async fn pool_coins(
+    events: &[LsEvent],
+    conn: &mut PgPoolConnection<'_>,
+) -> Result<HashMap<String, (String, String)>> {
+    let mut pool_coins = events
+        .iter()
+        .filter_map(|ev| match ev {
+            LsEvent::Pools(pool) => {
+                Some((pool.id.clone(), (pool.x_name.clone(), pool.y_name.clone())))
+            },
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    let missing = events
+        .iter()
+        .filter_map(|ev| match ev {
+            LsEvent::Events(event) if !pool_coins.contains_key(&event.pool_id) => {
+                Some(event.pool_id.clone())
+            },
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    if missing.is_empty() {
+        return Ok(pool_coins);
+    }
+
+    let known = schema::ls_pools::table
+        .filter(schema::ls_pools::id.eq_any(missing))
+        .select((
+            schema::ls_pools::id,
+            schema::ls_pools::x_name,
+            schema::ls_pools::y_name,
+        ))
+        .load::<(String, String, String)>(conn)
+        .await?;
+    pool_coins.extend(
+        known
+            .into_iter()
+            .map(|(id, x_name, y_name)| (id, (x_name, y_name))),
+    );
+
+    Ok(pool_coins)
+}
+
+#[async_trait]
+impl InsertToDb for LsCoins {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        let coins = self
+            .coins
+            .into_values()
+            .filter(|coin| !self.stored.contains(&coin.coin_type))
+            .collect::<Vec<_>>();
+
+        let count = coins.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_coins_query,
+            coins,
+            TableLsCoin::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsCoin added");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_coins_query(
+    items_to_insert: Vec<TableLsCoin>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_coins::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_coins::table)
+            .values(items_to_insert)
+            .on_conflict(coin_type_hash)
+            .do_nothing(),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_normalize() {
+        let coin = TableLsCoin {
+            coin_type_hash: String::new(),
+            coin_type: String::new(),
+            name: String::new(),
+            symbol: String::new(),
+            decimals: 8,
+            version: 0,
+        };
+
+        assert_eq!(
+            coin.normalize(&BigDecimal::from(-123_456_789_i64)),
+            BigDecimal::from_str("-1.23456789").unwrap()
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, Zero};
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, upsert::excluded, ExpressionMethods, Insertable,
+    QueryDsl, Queryable, Selectable, SelectableHelper,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_pool_daily_stats},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
+};
+
+use super::{
+    candles::{start_time, LsSwap},
+    db::{InsertToDb, TableLsPoolSnapshot},
+    events::LsEvent,
+};
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+// `fee` is in basis points of the input amount
+const FEE_SCALE: i64 = 10_000;
+// `dao_fee` is the percent of the fee that goes to the DAO
+const DAO_FEE_SCALE: i64 = 100;
+
+/// Volume, fees and closing reserves of a pool over a UTC day.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_pool_daily_stats)]
+pub struct TableLsPoolDailyStats {
+    pub pool_id: String,
+    pub day: i64,
+    // x_in + x_out
+    pub x_volume: BigDecimal,
+    // y_in + y_out
+    pub y_volume: BigDecimal,
+    pub x_lp_fee: BigDecimal,
+    pub y_lp_fee: BigDecimal,
+    pub x_dao_fee: BigDecimal,
+    pub y_dao_fee: BigDecimal,
+    pub swaps: i64,
+    // Reserves and fee settings of the pool at the end of the day
+    pub x_val: BigDecimal,
+    pub y_val: BigDecimal,
+    pub fee: i64,
+    pub dao_fee: i64,
+    pub last_swap_version: i64,
+    pub last_swap_event_index: i64,
+    pub reserves_version: i64,
+}
+
+type DailyKey = (String, i64);
+
+/// (lp_fee, dao_fee) of an input amount, rounded down like the pool does.
+fn fees(amount_in: &BigDecimal, fee: i64, dao_fee: i64) -> (BigDecimal, BigDecimal) {
+    let total = (amount_in * BigDecimal::from(fee) / BigDecimal::from(FEE_SCALE)).with_scale(0);
+    let dao = (&total * BigDecimal::from(dao_fee) / BigDecimal::from(DAO_FEE_SCALE)).with_scale(0);
+    (&total - &dao, dao)
+}
+
+impl TableLsPoolDailyStats {
+    fn new(pool_id: &str, day: i64) -> Self {
+        TableLsPoolDailyStats {
+            pool_id: pool_id.to_string(),
+            day,
+            x_volume: BigDecimal::zero(),
+            y_volume: BigDecimal::zero(),
+            x_lp_fee: BigDecimal::zero(),
+            y_lp_fee: BigDecimal::zero(),
+            x_dao_fee: BigDecimal::zero(),
+            y_dao_fee: BigDecimal::zero(),
+            swaps: 0,
+            x_val: BigDecimal::zero(),
+            y_val: BigDecimal::zero(),
+            fee: 0,
+            dao_fee: 0,
+            // Before any swap or reserves
+            last_swap_version: 0,
+            last_swap_event_index: -1,
+            reserves_version: -1,
+        }
+    }
+
+    fn key(&self) -> DailyKey {
+        (self.pool_id.clone(), self.day)
+    }
+
+    /// Merges a swap that comes after the last one of the day, with the fee settings at the swap.
+    /// @return: false if the swap is already part of the day
+    fn add_swap(&mut self, swap: &LsSwap) -> bool {
+        if swap.position() <= (self.last_swap_version, self.last_swap_event_index) {
+            return false;
+        }
+
+        let (x_lp_fee, x_dao_fee) = fees(&swap.x_in, swap.fee, swap.dao_fee);
+        let (y_lp_fee, y_dao_fee) = fees(&swap.y_in, swap.fee, swap.dao_fee);
+        self.x_volume += &swap.base_volume;
+        self.y_volume += &swap.quote_volume;
+        self.x_lp_fee += x_lp_fee;
+        self.y_lp_fee += y_lp_fee;
+        self.x_dao_fee += x_dao_fee;
+        self.y_dao_fee += y_dao_fee;
+        self.swaps += 1;
+        self.last_swap_version = swap.version;
+        self.last_swap_event_index = swap.event_index;
+        true
+    }
+
+    /// @return: false if the reserves of the day are already as new
+    fn set_reserves(&mut self, snapshot: &TableLsPoolSnapshot) -> bool {
+        if snapshot.version <= self.reserves_version {
+            return false;
+        }
+
+        self.x_val = snapshot.x_val.clone();
+        self.y_val = snapshot.y_val.clone();
+        self.fee = snapshot.fee;
+        self.dao_fee = snapshot.dao_fee;
+        self.reserves_version = snapshot.version;
+        true
+    }
+}
+
+/// Swaps and pool snapshots of a batch, merged into the daily stats of their pools.
+///
+/// Swaps already counted in a day and snapshots older than its reserves are skipped, so
+/// reprocessing a batch changes nothing.
+pub(crate) struct LsPoolDailyStats {
+    swaps: Vec<LsSwap>,
+    snapshots: Vec<TableLsPoolSnapshot>,
+}
+
+impl LsPoolDailyStats {
+    pub(crate) fn new(events: &[LsEvent]) -> Self {
+        let mut swaps = Vec::new();
+        let mut snapshots = Vec::new();
+        for ev in events {
+            match ev {
+                LsEvent::Swaps(swap) => swaps.push(swap.clone()),
+                LsEvent::PoolSnapshots(snapshot) => snapshots.push(snapshot.clone()),
+                _ => {},
+            }
+        }
+        swaps.sort_by_key(|swap| swap.position());
+        snapshots.sort_by_key(|snapshot| snapshot.version);
+
+        LsPoolDailyStats { swaps, snapshots }
+    }
+
+    fn keys(&self) -> HashSet<DailyKey> {
+        let swap_keys = self
+            .swaps
+            .iter()
+            .map(|swap| (swap.pool_id.clone(), start_time(swap.timestamp, DAY_SECS)));
+        let snapshot_keys = self.snapshots.iter().map(|snapshot| {
+            (
+                snapshot.pool_id.clone(),
+                start_time(snapshot.timestamp, DAY_SECS),
+            )
+        });
+        swap_keys.chain(snapshot_keys).collect()
+    }
+
+    fn build(self, existing: Vec<TableLsPoolDailyStats>) -> Vec<TableLsPoolDailyStats> {
+        let mut days: HashMap<DailyKey, TableLsPoolDailyStats> =
+            existing.into_iter().map(|day| (day.key(), day)).collect();
+        let mut updated = HashSet::new();
+
+        for swap in &self.swaps {
+            let key = (swap.pool_id.clone(), start_time(swap.timestamp, DAY_SECS));
+            let day = days
+                .entry(key.clone())
+                .or_insert_with(|| TableLsPoolDailyStats::new(&key.0, key.1));
+            if day.add_swap(swap) {
+                updated.insert(key);
+            }
+        }
+        for snapshot in &self.snapshots {
+            let key = (
+                snapshot.pool_id.clone(),
+                start_time(snapshot.timestamp, DAY_SECS),
+            );
+            let day = days
+                .entry(key.clone())
+                .or_insert_with(|| TableLsPoolDailyStats::new(&key.0, key.1));
+            if day.set_reserves(snapshot) {
+                updated.insert(key);
+            }
+        }
+
+        days.into_iter()
+            .filter_map(|(key, day)| updated.contains(&key).then_some(day))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InsertToDb for LsPoolDailyStats {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        if self.swaps.is_empty() && self.snapshots.is_empty() {
+            return Ok(());
+        }
+
+        let keys = self.keys();
+        let pool_ids = keys
+            .iter()
+            .map(|(pool_id, _)| pool_id)
+            .collect::<HashSet<_>>();
+        let days = keys.iter().map(|(_, day)| day).collect::<HashSet<_>>();
+
+        // Days of the swaps and snapshots, so a replayed swap or an older snapshot is not merged
+        // twice. Other (pool, day) pairs may come along, they are not written back.
+        let existing = schema::ls_pool_daily_stats::table
+            .filter(schema::ls_pool_daily_stats::pool_id.eq_any(pool_ids))
+            .filter(schema::ls_pool_daily_stats::day.eq_any(days))
+            .select(TableLsPoolDailyStats::as_select())
+            .load::<TableLsPoolDailyStats>(conn)
+            .await?;
+
+        let stats = self.build(existing);
+
+        let count = stats.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_pool_daily_stats_query,
+            stats,
+            TableLsPoolDailyStats::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsPoolDailyStats added/updated");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_pool_daily_stats_query(
+    items_to_insert: Vec<TableLsPoolDailyStats>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_pool_daily_stats::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_pool_daily_stats::table)
+            .values(items_to_insert)
+            .on_conflict((pool_id, day))
+            .do_update()
+            .set((
+                x_volume.eq(excluded(x_volume)),
+                y_volume.eq(excluded(y_volume)),
+                x_lp_fee.eq(excluded(x_lp_fee)),
+                y_lp_fee.eq(excluded(y_lp_fee)),
+                x_dao_fee.eq(excluded(x_dao_fee)),
+                y_dao_fee.eq(excluded(y_dao_fee)),
+                swaps.eq(excluded(swaps)),
+                x_val.eq(excluded(x_val)),
+                y_val.eq(excluded(y_val)),
+                fee.eq(excluded(fee)),
+                dao_fee.eq(excluded(dao_fee)),
+                last_swap_version.eq(excluded(last_swap_version)),
+                last_swap_event_index.eq(excluded(last_swap_event_index)),
+                reserves_version.eq(excluded(reserves_version)),
+            )),
+        // A day only moves forward, by its swaps or its reserves
+        Some(
+            " WHERE (ls_pool_daily_stats.last_swap_version, \
+             ls_pool_daily_stats.last_swap_event_index) \
+             <= (EXCLUDED.last_swap_version, EXCLUDED.last_swap_event_index) \
+             AND ls_pool_daily_stats.reserves_version <= EXCLUDED.reserves_version ",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::ls_processor::tests::replay_overlapping_batch;
+
+    fn swap(version: i64, timestamp: i64, x_in: i64, (fee, dao_fee): (i64, i64)) -> LsSwap {
+        LsSwap {
+            pool_id: "pool".to_string(),
+            version,
+            event_index: 0,
+            timestamp,
+            reserves: None,
+            price: None,
+            base_volume: x_in.into(),
+            quote_volume: x_in.into(),
+            x_in: x_in.into(),
+            y_in: 0.into(),
+            fee,
+            dao_fee,
+        }
+    }
+
+    fn snapshot(version: i64, timestamp: i64, x_val: i64, fee: i64) -> TableLsPoolSnapshot {
+        TableLsPoolSnapshot {
+            pool_id: "pool".to_string(),
+            version,
+            timestamp,
+            x_val: x_val.into(),
+            y_val: 0.into(),
+            fee,
+            dao_fee: 33,
+            lp_supply: None,
+        }
+    }
+
+    #[test]
+    fn test_fees_follow_fee_updates() {
+        let stats = LsPoolDailyStats {
+            // 0.3% then 0.1% after an UpdateFeeEvent in the same day
+            swaps: vec![
+                swap(1, 100, 10_000, (30, 33)),
+                swap(3, 200, 10_000, (10, 33)),
+            ],
+            snapshots: vec![
+                snapshot(1, 100, 110_000, 30),
+                snapshot(2, 150, 110_000, 10),
+                snapshot(3, 200, 120_000, 10),
+            ],
+        };
+
+        let days = stats.build(vec![]);
+        assert_eq!(days.len(), 1);
+        let day = &days[0];
+        // 30 and 10 in fees, 33% of each to the DAO
+        assert_eq!(day.x_dao_fee, (9 + 3).into());
+        assert_eq!(day.x_lp_fee, (21 + 7).into());
+        assert_eq!(day.x_volume, 20_000.into());
+        assert_eq!(day.swaps, 2);
+        assert_eq!((&day.x_val, day.fee), (&120_000.into(), 10));
+    }
+
+    #[test]
+    fn test_daily_stats_are_idempotent() {
+        let stats = || LsPoolDailyStats {
+            swaps: vec![swap(1, 100, 10_000, (30, 33))],
+            snapshots: vec![snapshot(1, 100, 110_000, 30)],
+        };
+
+        let first = stats().build(vec![]);
+        assert!(stats().build(first.clone()).is_empty());
+
+        // The next day starts over
+        let next = LsPoolDailyStats {
+            swaps: vec![swap(2, DAY_SECS + 1, 10_000, (30, 33))],
+            snapshots: vec![],
+        }
+        .build(first);
+        assert_eq!(next.len(), 1);
+        assert_eq!((next[0].day, next[0].swaps), (DAY_SECS, 1));
+    }
+
+    #[test]
+    fn test_batch_after_a_later_one() {
+        let days = replay_overlapping_batch(
+            LsPoolDailyStats {
+                swaps: vec![
+                    swap(1, 100, 10_000, (30, 33)),
+                    swap(3, 200, 10_000, (30, 33)),
+                ],
+                snapshots: vec![snapshot(3, 200, 120_000, 30)],
+            },
+            LsPoolDailyStats {
+                swaps: vec![
+                    swap(3, 200, 10_000, (30, 33)),
+                    swap(4, 300, 10_000, (30, 33)),
+                ],
+                snapshots: vec![snapshot(2, 150, 110_000, 30), snapshot(4, 300, 130_000, 30)],
+            },
+            LsPoolDailyStats::build,
+        );
+        assert_eq!(days.len(), 1);
+        let day = &days[0];
+        assert_eq!(day.swaps, 3);
+        assert_eq!(day.x_volume, 30_000.into());
+        assert_eq!((&day.x_val, day.reserves_version), (&130_000.into(), 4));
+    }
+}
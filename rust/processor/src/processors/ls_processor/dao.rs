@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, Zero};
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, upsert::excluded, ExpressionMethods, Insertable,
+    QueryDsl, Queryable, Selectable, SelectableHelper,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_dao_storages},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
+};
+
+use super::{db::InsertToDb, events::LsEvent};
+
+/// Coins moved into or out of the DAO storage of a pool by an event.
+#[derive(Debug, Clone)]
+pub struct LsDaoStorageChange {
+    pub pool_id: String,
+    pub version: i64,
+    pub deposited_x: BigDecimal,
+    pub deposited_y: BigDecimal,
+    pub withdrawn_x: BigDecimal,
+    pub withdrawn_y: BigDecimal,
+    // Coins in the storage after the transaction, if its Storage resource was written
+    pub balance: Option<(BigDecimal, BigDecimal)>,
+}
+
+/// Current coins in the DAO storage of a pool, with everything deposited and withdrawn so far.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_dao_storages)]
+pub struct TableLsDaoStorage {
+    pub pool_id: String,
+    pub x_val: BigDecimal,
+    pub y_val: BigDecimal,
+    pub deposited_x: BigDecimal,
+    pub deposited_y: BigDecimal,
+    pub withdrawn_x: BigDecimal,
+    pub withdrawn_y: BigDecimal,
+    pub last_version: i64,
+}
+
+impl TableLsDaoStorage {
+    fn new(pool_id: &str) -> Self {
+        TableLsDaoStorage {
+            pool_id: pool_id.to_string(),
+            x_val: BigDecimal::zero(),
+            y_val: BigDecimal::zero(),
+            deposited_x: BigDecimal::zero(),
+            deposited_y: BigDecimal::zero(),
+            withdrawn_x: BigDecimal::zero(),
+            withdrawn_y: BigDecimal::zero(),
+            last_version: 0,
+        }
+    }
+
+    fn apply(&mut self, change: &LsDaoStorageChange) {
+        self.deposited_x += &change.deposited_x;
+        self.deposited_y += &change.deposited_y;
+        self.withdrawn_x += &change.withdrawn_x;
+        self.withdrawn_y += &change.withdrawn_y;
+        match &change.balance {
+            Some((x_val, y_val)) => {
+                self.x_val = x_val.clone();
+                self.y_val = y_val.clone();
+            },
+            None => {
+                self.x_val += &change.deposited_x - &change.withdrawn_x;
+                self.y_val += &change.deposited_y - &change.withdrawn_y;
+            },
+        }
+        self.last_version = change.version;
+    }
+}
+
+/// DAO storage changes of a batch and the storages they update.
+///
+/// Changes at or before the `last_version` a storage was loaded with are already part of it,
+/// so reprocessing a batch does not apply them twice.
+pub(crate) struct LsDaoStorages {
+    changes: Vec<LsDaoStorageChange>,
+}
+
+impl LsDaoStorages {
+    pub(crate) fn new(events: &[LsEvent]) -> Self {
+        let mut changes = events
+            .iter()
+            .filter_map(|ev| match ev {
+                LsEvent::DaoStorageChanges(change) => Some(change.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|change| change.version);
+
+        LsDaoStorages { changes }
+    }
+
+    fn storages(&self, existing: Vec<TableLsDaoStorage>) -> Vec<TableLsDaoStorage> {
+        let mut storages: HashMap<String, (i64, TableLsDaoStorage)> = existing
+            .into_iter()
+            .map(|storage| (storage.pool_id.clone(), (storage.last_version, storage)))
+            .collect();
+        let mut updated = HashSet::new();
+
+        for change in &self.changes {
+            let (loaded_version, storage) = storages
+                .entry(change.pool_id.clone())
+                .or_insert_with(|| (0, TableLsDaoStorage::new(&change.pool_id)));
+            if change.version <= *loaded_version {
+                continue;
+            }
+            storage.apply(change);
+            updated.insert(change.pool_id.clone());
+        }
+
+        storages
+            .into_iter()
+            .filter_map(|(pool_id, (_, storage))| updated.contains(&pool_id).then_some(storage))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InsertToDb for LsDaoStorages {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+
+        let pool_ids = self
+            .changes
+            .iter()
+            .map(|change| change.pool_id.clone())
+            .collect::<HashSet<_>>();
+
+        let existing = schema::ls_dao_storages::table
+            .filter(schema::ls_dao_storages::pool_id.eq_any(pool_ids))
+            .select(TableLsDaoStorage::as_select())
+            .load::<TableLsDaoStorage>(conn)
+            .await?;
+
+        let storages = self.storages(existing);
+
+        let count = storages.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_dao_storages_query,
+            storages,
+            TableLsDaoStorage::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsDaoStorage added/updated");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_dao_storages_query(
+    items_to_insert: Vec<TableLsDaoStorage>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_dao_storages::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_dao_storages::table)
+            .values(items_to_insert)
+            .on_conflict(pool_id)
+            .do_update()
+            .set((
+                x_val.eq(excluded(x_val)),
+                y_val.eq(excluded(y_val)),
+                deposited_x.eq(excluded(deposited_x)),
+                deposited_y.eq(excluded(deposited_y)),
+                withdrawn_x.eq(excluded(withdrawn_x)),
+                withdrawn_y.eq(excluded(withdrawn_y)),
+                last_version.eq(excluded(last_version)),
+            )),
+        Some(" WHERE ls_dao_storages.last_version < EXCLUDED.last_version "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::ls_processor::tests::replay_overlapping_batch;
+
+    fn change(
+        version: i64,
+        (deposited_x, withdrawn_x): (i64, i64),
+        balance: Option<i64>,
+    ) -> LsDaoStorageChange {
+        LsDaoStorageChange {
+            pool_id: "pool".to_string(),
+            version,
+            deposited_x: deposited_x.into(),
+            deposited_y: BigDecimal::zero(),
+            withdrawn_x: withdrawn_x.into(),
+            withdrawn_y: BigDecimal::zero(),
+            balance: balance.map(|x_val| (x_val.into(), BigDecimal::zero())),
+        }
+    }
+
+    #[test]
+    fn test_storages_track_deposits_and_withdrawals() {
+        let storages = LsDaoStorages {
+            changes: vec![
+                change(1, (0, 0), Some(0)),
+                change(2, (10, 0), None),
+                change(2, (5, 0), Some(15)),
+                change(3, (0, 12), Some(3)),
+            ],
+        };
+
+        let result = storages.storages(vec![]);
+        assert_eq!(result.len(), 1);
+        let storage = &result[0];
+        assert_eq!(
+            (&storage.deposited_x, &storage.withdrawn_x, &storage.x_val),
+            (&15.into(), &12.into(), &3.into())
+        );
+        assert_eq!(storage.last_version, 3);
+
+        // Reprocessing the batch leaves the storage as it is
+        assert!(storages.storages(result).is_empty());
+    }
+
+    #[test]
+    fn test_batch_after_a_later_one() {
+        let result = replay_overlapping_batch(
+            LsDaoStorages {
+                changes: vec![change(1, (0, 0), Some(0)), change(2, (10, 0), Some(10))],
+            },
+            LsDaoStorages {
+                changes: vec![change(2, (10, 0), Some(10)), change(3, (0, 4), None)],
+            },
+            |storages, existing| storages.storages(existing),
+        );
+        assert_eq!(result.len(), 1);
+        let storage = &result[0];
+        assert_eq!(
+            (&storage.deposited_x, &storage.withdrawn_x, &storage.x_val),
+            (&10.into(), &4.into(), &6.into())
+        );
+        assert_eq!(storage.last_version, 3);
+    }
+}
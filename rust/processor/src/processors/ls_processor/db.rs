@@ -3,29 +3,32 @@ use std::{collections::HashMap, str::FromStr};
 use anyhow::{bail, Result};
 use bigdecimal::BigDecimal;
 use diesel::{
-    deserialize::Queryable, query_dsl::methods::FilterDsl, ExpressionMethods, Insertable,
-    Selectable,
+    deserialize::Queryable, pg::Pg, query_builder::QueryFragment, upsert::excluded,
+    ExpressionMethods, Insertable, Selectable,
 };
-use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
 use tonic::async_trait;
 use tracing::info;
 
 use crate::{
-    schema::{self, ls_events, ls_pools},
-    utils::database::PgPoolConnection,
+    schema::{self, ls_events, ls_pool_snapshots, ls_pools},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
 };
 
-use super::mv::PoolType;
+use super::{events::EventAmounts, mv::PoolType};
 
-// Write 100 values at a time to the table
-const TB_CHUNKS_SIZE: usize = 100;
+// Digits after the decimal point of the stored prices
+pub(crate) const PRICE_SCALE: i64 = 18;
 
 #[async_trait]
 pub(crate) trait InsertToDb {
     async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()>;
 }
 
-#[derive(Selectable, Queryable, AsChangeset, Insertable, Debug, Clone)]
+#[derive(
+    Selectable, Queryable, AsChangeset, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone,
+)]
 #[diesel(table_name = ls_pools)]
 pub struct TableLsPool {
     pub id: String,
@@ -38,6 +41,12 @@ pub struct TableLsPool {
     pub fee: i64,
     pub dao_fee: i64,
     pub last_version: i64,
+    pub x_symbol: Option<String>,
+    pub y_symbol: Option<String>,
+    pub x_decimals: Option<i32>,
+    pub y_decimals: Option<i32>,
+    pub x_val_normalized: Option<BigDecimal>,
+    pub y_val_normalized: Option<BigDecimal>,
 }
 
 impl From<&PoolType> for TableLsPool {
@@ -53,6 +62,12 @@ impl From<&PoolType> for TableLsPool {
             fee: 0,
             dao_fee: 0,
             last_version: 0,
+            x_symbol: None,
+            y_symbol: None,
+            x_decimals: None,
+            y_decimals: None,
+            x_val_normalized: None,
+            y_val_normalized: None,
         }
     }
 }
@@ -70,40 +85,68 @@ impl From<PoolType> for TableLsPool {
             fee: 0,
             dao_fee: 0,
             last_version: 0,
+            x_symbol: None,
+            y_symbol: None,
+            x_decimals: None,
+            y_decimals: None,
+            x_val_normalized: None,
+            y_val_normalized: None,
         }
     }
 }
 
+// Reserves and fees of ls_pools only come from the LiquidityPool resource, the deltas in
+// ls_events are kept for `ls_tools reconcile`.
 #[async_trait]
 impl InsertToDb for Vec<TableLsPool> {
     async fn insert_to_db(mut self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        // A pool may only appear once in an upsert
         self.group();
-
-        let mut updated_count = 0;
         let count = self.len();
 
-        for pool in &self {
-            updated_count += diesel::insert_into(schema::ls_pools::table)
-                .values(pool)
-                .on_conflict(schema::ls_pools::id)
-                .do_update()
-                .set((
-                    schema::ls_pools::x_val.eq(&pool.x_val),
-                    schema::ls_pools::y_val.eq(&pool.y_val),
-                    schema::ls_pools::fee.eq(&pool.fee),
-                    schema::ls_pools::dao_fee.eq(&pool.dao_fee),
-                    schema::ls_pools::last_version.eq(&pool.last_version),
-                ))
-                .filter(schema::ls_pools::last_version.lt(&pool.last_version))
-                .execute(conn)
-                .await?;
-        }
-        info!("{count}:{updated_count} TableLsPool added/updated");
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_pools_query,
+            self,
+            TableLsPool::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsPool added/updated");
 
         Ok(())
     }
 }
 
+pub(crate) fn insert_ls_pools_query(
+    items_to_insert: Vec<TableLsPool>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_pools::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_pools::table)
+            .values(items_to_insert)
+            .on_conflict(id)
+            .do_update()
+            .set((
+                x_val.eq(excluded(x_val)),
+                y_val.eq(excluded(y_val)),
+                fee.eq(excluded(fee)),
+                dao_fee.eq(excluded(dao_fee)),
+                last_version.eq(excluded(last_version)),
+                x_symbol.eq(excluded(x_symbol)),
+                y_symbol.eq(excluded(y_symbol)),
+                x_decimals.eq(excluded(x_decimals)),
+                y_decimals.eq(excluded(y_decimals)),
+                x_val_normalized.eq(excluded(x_val_normalized)),
+                y_val_normalized.eq(excluded(y_val_normalized)),
+            )),
+        Some(" WHERE ls_pools.last_version < EXCLUDED.last_version "),
+    )
+}
+
 trait GroupPool {
     fn group(&mut self);
 }
@@ -131,7 +174,60 @@ impl GroupPool for Vec<TableLsPool> {
     }
 }
 
-#[derive(Selectable, Queryable, Insertable, Debug)]
+/// State of a pool after a transaction that wrote its LiquidityPool resource.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_pool_snapshots)]
+pub struct TableLsPoolSnapshot {
+    pub pool_id: String,
+    pub version: i64,
+    pub timestamp: i64,
+    pub x_val: BigDecimal,
+    pub y_val: BigDecimal,
+    pub fee: i64,
+    pub dao_fee: i64,
+    pub lp_supply: Option<BigDecimal>,
+}
+
+#[async_trait]
+impl InsertToDb for Vec<TableLsPoolSnapshot> {
+    async fn insert_to_db(mut self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        // Every event of a transaction finds the same resource
+        self.sort_by(|a, b| (&a.pool_id, a.version).cmp(&(&b.pool_id, b.version)));
+        self.dedup_by(|a, b| a.pool_id == b.pool_id && a.version == b.version);
+
+        let count = self.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_pool_snapshots_query,
+            self,
+            TableLsPoolSnapshot::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsPoolSnapshot added");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_pool_snapshots_query(
+    items_to_insert: Vec<TableLsPoolSnapshot>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_pool_snapshots::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_pool_snapshots::table)
+            .values(items_to_insert)
+            .on_conflict((pool_id, version))
+            .do_nothing(),
+        None,
+    )
+}
+
+#[derive(Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = ls_events)]
 pub struct TableLsEvent {
     pub id: String,
@@ -147,28 +243,121 @@ pub struct TableLsEvent {
     pub fee: Option<i64>,
     pub dao_fee: Option<i64>,
     pub sq: Option<i64>,
+    pub x_val_normalized: Option<BigDecimal>,
+    pub y_val_normalized: Option<BigDecimal>,
+    // Gross amounts of swaps, flashloans and liquidity changes. Like the reserves they stay in
+    // raw units to be checked against each other and ls_pool_snapshots, whole coins are
+    // amount / 10^ls_coins.decimals.
+    pub x_in: Option<BigDecimal>,
+    pub x_out: Option<BigDecimal>,
+    pub y_in: Option<BigDecimal>,
+    pub y_out: Option<BigDecimal>,
+    // lp_tokens_received | lp_tokens_burned
+    pub lp_tokens: Option<BigDecimal>,
+    // Reserves of the pool after the event, if the transaction wrote its LiquidityPool resource
+    pub x_reserve: Option<BigDecimal>,
+    pub y_reserve: Option<BigDecimal>,
+}
+
+impl TableLsEvent {
+    pub(crate) fn set_amounts(&mut self, amounts: EventAmounts) {
+        self.x_in = amounts.x_in.map(|v| v.into());
+        self.x_out = amounts.x_out.map(|v| v.into());
+        self.y_in = amounts.y_in.map(|v| v.into());
+        self.y_out = amounts.y_out.map(|v| v.into());
+        self.lp_tokens = amounts.lp_tokens.map(|v| v.into());
+    }
+
+    /// Sets the reserves after each of the events of one transaction, given in event order,
+    /// from the reserves of their pools at the end of the transaction. Going backwards, the
+    /// reserves before an event are the ones after it minus its x_val/y_val.
+    pub(crate) fn set_reserves<'a>(
+        events: impl DoubleEndedIterator<Item = &'a mut TableLsEvent>,
+        reserves: &HashMap<String, (BigDecimal, BigDecimal)>,
+    ) {
+        let mut reserves = reserves.clone();
+
+        for event in events.rev() {
+            let (x_reserve, y_reserve) = match reserves.get_mut(&event.pool_id) {
+                Some(reserve) => reserve,
+                None => continue,
+            };
+            event.x_reserve = Some(x_reserve.clone());
+            event.y_reserve = Some(y_reserve.clone());
+            if let Some(x_val) = &event.x_val {
+                *x_reserve -= x_val;
+            }
+            if let Some(y_val) = &event.y_val {
+                *y_reserve -= y_val;
+            }
+        }
+    }
 }
 
 #[async_trait]
 impl InsertToDb for Vec<TableLsEvent> {
     async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
-        for rows in self.chunks(TB_CHUNKS_SIZE) {
-            let count = diesel::insert_into(schema::ls_events::table)
-                .values(rows)
-                .on_conflict(schema::ls_events::id)
-                .do_nothing()
-                .execute(conn)
-                .await?;
-
-            info!("{from}:{count} TableLsEvent added", from = rows.len());
-        }
+        let count = self.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_events_query,
+            self,
+            TableLsEvent::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsEvent added");
 
         Ok(())
     }
 }
 
+pub(crate) fn insert_ls_events_query(
+    items_to_insert: Vec<TableLsEvent>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_events::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_events::table)
+            .values(items_to_insert)
+            .on_conflict(id)
+            .do_nothing(),
+        None,
+    )
+}
+
+/// Only sets the columns derived from the event body and the snapshots, see `ls_tools backfill`
+pub(crate) fn backfill_ls_events_query(
+    items_to_insert: Vec<TableLsEvent>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_events::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_events::table)
+            .values(items_to_insert)
+            .on_conflict(id)
+            .do_update()
+            .set((
+                x_in.eq(excluded(x_in)),
+                x_out.eq(excluded(x_out)),
+                y_in.eq(excluded(y_in)),
+                y_out.eq(excluded(y_out)),
+                lp_tokens.eq(excluded(lp_tokens)),
+                x_reserve.eq(excluded(x_reserve)),
+                y_reserve.eq(excluded(y_reserve)),
+            )),
+        None,
+    )
+}
+
 #[allow(clippy::enum_variant_names)]
-#[derive(Clone, Copy, Debug, diesel_derive_enum::DbEnum, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, diesel_derive_enum::DbEnum, Serialize, Deserialize, PartialEq, Eq)]
 #[ExistingTypePath = "crate::schema::sql_types::EventType"]
 pub enum LsEventType {
     // When new pool created.
@@ -181,14 +370,19 @@ pub enum LsEventType {
     SwapEvent,
     // When flashloan event happened.
     FlashloanEvent,
-    // When oracle updated (i don't think we need to catch it).
+    // When oracle updated, with the cumulative prices for the TWAP.
     OracleUpdatedEvent,
     // When fee of pool updated.
     UpdateFeeEvent,
     // When DAO fee updated for the pool.
     UpdateDAOFeeEvent,
 
+    // When DAO storage of the pool created.
+    StorageCreatedEvent,
+    // When the DAO fee of a swap moved to the DAO storage.
     CoinDepositedEvent,
+    // When coins withdrawn from the DAO storage.
+    CoinWithdrawnEvent,
 }
 
 impl FromStr for LsEventType {
@@ -206,13 +400,18 @@ impl FromStr for LsEventType {
             "SwapEvent" => LsEventType::SwapEvent,
             // When flashloan event happened.
             "FlashloanEvent" => LsEventType::FlashloanEvent,
-            // When oracle updated (i don't think we need to catch it).
+            // When oracle updated, with the cumulative prices for the TWAP.
             "OracleUpdatedEvent" => LsEventType::OracleUpdatedEvent,
             // When fee of pool updated.
             "UpdateFeeEvent" => LsEventType::UpdateFeeEvent,
             // When DAO fee updated for the pool.
             "UpdateDAOFeeEvent" => LsEventType::UpdateDAOFeeEvent,
+            // When DAO storage of the pool created.
+            "StorageCreatedEvent" => LsEventType::StorageCreatedEvent,
+            // When the DAO fee of a swap moved to the DAO storage.
             "CoinDepositedEvent" => LsEventType::CoinDepositedEvent,
+            // When coins withdrawn from the DAO storage.
+            "CoinWithdrawnEvent" => LsEventType::CoinWithdrawnEvent,
             _ => bail!("Unknown event"),
         };
         Ok(result)
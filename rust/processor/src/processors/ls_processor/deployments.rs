@@ -0,0 +1,179 @@
+use anyhow::Result;
+use aptos_protos::transaction::v1::{write_set_change::Change, Transaction};
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, ExpressionMethods, Insertable, QueryDsl, Queryable,
+    Selectable,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_deployments},
+    utils::{
+        database::{execute_in_chunks_in_transaction, PgPoolConnection},
+        util::standardize_address,
+    },
+};
+
+use super::{
+    db::InsertToDb,
+    mv::{unwrap_usr_tx, LS_MODULE},
+};
+
+/// A Liquidswap deployment, found by the publish of its modules by a configured deployer.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_deployments)]
+pub struct TableLsDeployment {
+    pub address: String,
+    pub version_ls: String,
+    pub deployer: String,
+    pub version: i64,
+    pub tx_hash: String,
+    pub timestamp: i64,
+}
+
+/// Deployments published in a batch.
+pub(crate) struct LsDeployments {
+    deployments: Vec<TableLsDeployment>,
+}
+
+impl LsDeployments {
+    /// `deployers`: Vec<(VERSION_LS,DEPLOYER)>, standardized.
+    ///
+    /// The modules of a deployment are published either at the deployer itself or, by the
+    /// deployer as the sender, at a resource account.
+    pub(crate) fn from_txs(deployers: &[(String, String)], transactions: &[Transaction]) -> Self {
+        let mut deployments: Vec<TableLsDeployment> = Vec::new();
+        if deployers.is_empty() {
+            return LsDeployments { deployments };
+        }
+
+        for tx in transactions {
+            let (info, request) = match (tx.info.as_ref(), unwrap_usr_tx(tx)) {
+                (Some(info), Some(user_tx)) if info.success => match user_tx.request.as_ref() {
+                    Some(request) => (info, request),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let sender = standardize_address(&request.sender);
+
+            let published = info
+                .changes
+                .iter()
+                .filter_map(|ch| match ch.change.as_ref()? {
+                    Change::WriteModule(module) => Some(module),
+                    _ => None,
+                })
+                .filter(|module| {
+                    module
+                        .data
+                        .as_ref()
+                        .and_then(|data| data.abi.as_ref())
+                        .map_or(false, |abi| LS_MODULE.contains(&abi.name.as_str()))
+                })
+                .map(|module| standardize_address(&module.address));
+
+            for address in published {
+                let deployer = deployers
+                    .iter()
+                    .find(|(_version_ls, deployer)| deployer == &address || deployer == &sender);
+                let (version_ls, deployer) = match deployer {
+                    Some(deployer) => deployer,
+                    None => continue,
+                };
+                if deployments.iter().any(|d| d.address == address) {
+                    continue;
+                }
+
+                deployments.push(TableLsDeployment {
+                    address,
+                    version_ls: version_ls.clone(),
+                    deployer: deployer.clone(),
+                    version: tx.version as i64,
+                    tx_hash: hex::encode(&info.hash),
+                    timestamp: tx.timestamp.as_ref().map_or(0, |t| t.seconds),
+                });
+            }
+        }
+
+        LsDeployments { deployments }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.deployments.is_empty()
+    }
+
+    /// Vec<(VERSION_LS,ADDRESS)> of the configured addresses, the deployments in the db and the
+    /// ones published in the batch.
+    ///
+    /// The db has the deployments of every earlier batch, batches are processed one at a time.
+    pub(crate) async fn addresses(
+        &self,
+        configured: &[(String, String)],
+        conn: &mut PgPoolConnection<'_>,
+    ) -> Result<Vec<(String, String)>> {
+        let known: Vec<(String, String)> = schema::ls_deployments::table
+            .select((
+                schema::ls_deployments::version_ls,
+                schema::ls_deployments::address,
+            ))
+            .order(schema::ls_deployments::version.asc())
+            .load(conn)
+            .await?;
+
+        let mut result = configured.to_vec();
+        let discovered = known.into_iter().chain(
+            self.deployments
+                .iter()
+                .map(|d| (d.version_ls.clone(), d.address.clone())),
+        );
+        for (version_ls, address) in discovered {
+            if !result.iter().any(|(_, known)| known == &address) {
+                result.push((version_ls, address));
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl InsertToDb for LsDeployments {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        let count = self.deployments.len();
+        if count == 0 {
+            return Ok(());
+        }
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_deployments_query,
+            self.deployments,
+            TableLsDeployment::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsDeployment added");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_deployments_query(
+    items_to_insert: Vec<TableLsDeployment>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    // The first publish is kept, later ones are upgrades of the same deployment
+    (
+        diesel::insert_into(schema::ls_deployments::table)
+            .values(items_to_insert)
+            .on_conflict(schema::ls_deployments::address)
+            .do_nothing(),
+        None,
+    )
+}
@@ -1,6 +1,6 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use aptos_protos::transaction::v1::{Event, Transaction};
 
 use serde::Deserialize;
@@ -8,9 +8,19 @@ use tonic::async_trait;
 
 use crate::{
     processors::ls_processor::{
-        db::{InsertToDb, LsEventType, TableLsEvent, TableLsPool},
+        candles::LsSwap,
+        dao::LsDaoStorageChange,
+        db::{InsertToDb, LsEventType, TableLsEvent, TableLsPool, TableLsPoolSnapshot},
+        flashloans::TableLsFlashloan,
         info::PoolResourceFromTx,
-        mv::{filter_ls_events, EventLs, MoveStructTagLs, TransactionLs, TxInfoForLs},
+        lp::TableLsLpActivity,
+        mv::{
+            event_index, filter_ls_events, unwrap_usr_tx, EventLs, MoveStructTagLs, TransactionLs,
+            TxInfoForLs,
+        },
+        oracle::TableLsOracleObservation,
+        trader_stats::LsTraderSwap,
+        trades::LsTradeHop,
     },
     utils::database::PgPoolConnection,
 };
@@ -18,7 +28,15 @@ use crate::{
 #[derive(Debug)]
 pub(crate) enum LsEvent {
     Pools(TableLsPool),
+    PoolSnapshots(TableLsPoolSnapshot),
     Events(TableLsEvent),
+    Swaps(LsSwap),
+    LpActivities(TableLsLpActivity),
+    OracleObservations(TableLsOracleObservation),
+    TradeHops(LsTradeHop),
+    TraderSwaps(LsTraderSwap),
+    DaoStorageChanges(LsDaoStorageChange),
+    Flashloans(TableLsFlashloan),
 }
 
 impl LsEvent {
@@ -26,13 +44,53 @@ impl LsEvent {
         addresses: &[(String, String)],
         transactions: &[Transaction],
     ) -> Result<Vec<LsEvent>> {
-        Ok(transactions
-            .iter()
-            .map(|tx| LsEvent::try_from_tx(addresses, tx))
-            .collect::<Result<Vec<Vec<_>>>>()?
-            .into_iter()
-            .flatten()
-            .collect())
+        let mut fees = HashMap::new();
+        let mut result = Vec::new();
+        for tx in transactions {
+            let mut tx_events = LsEvent::try_from_tx(addresses, tx)?;
+            LsEvent::set_swap_fees(&mut tx_events, &mut fees);
+            result.extend(tx_events);
+        }
+        Ok(result)
+    }
+
+    /// Gives each swap the (fee, dao_fee) in effect at its event, following the UpdateFeeEvents
+    /// and UpdateDAOFeeEvents of the transaction from the fees at the end of the previous one.
+    /// `fees` carries the end of transaction fees across the batch. Before a pool's first
+    /// transaction in the batch its fees are unknown, a swap there before a fee update keeps the
+    /// fees at the end of its transaction.
+    fn set_swap_fees(tx_events: &mut [LsEvent], fees: &mut HashMap<String, (i64, i64)>) {
+        let previous = |pool_id: &String| match fees.get(pool_id) {
+            Some((fee, dao_fee)) => (Some(*fee), Some(*dao_fee)),
+            None => (None, None),
+        };
+        let mut current = HashMap::new();
+        for ev in tx_events.iter_mut() {
+            match ev {
+                LsEvent::Events(event) if event.fee.is_some() || event.dao_fee.is_some() => {
+                    let (fee, dao_fee) = current
+                        .entry(event.pool_id.clone())
+                        .or_insert_with(|| previous(&event.pool_id));
+                    *fee = event.fee.or(*fee);
+                    *dao_fee = event.dao_fee.or(*dao_fee);
+                },
+                LsEvent::Swaps(swap) => {
+                    let (fee, dao_fee) = current
+                        .get(&swap.pool_id)
+                        .copied()
+                        .unwrap_or_else(|| previous(&swap.pool_id));
+                    swap.fee = fee.unwrap_or(swap.fee);
+                    swap.dao_fee = dao_fee.unwrap_or(swap.dao_fee);
+                },
+                _ => {},
+            }
+        }
+
+        for ev in tx_events.iter() {
+            if let LsEvent::PoolSnapshots(snapshot) = ev {
+                fees.insert(snapshot.pool_id.clone(), (snapshot.fee, snapshot.dao_fee));
+            }
+        }
     }
 
     fn try_from_tx(
@@ -44,12 +102,43 @@ impl LsEvent {
             None => return Ok(Vec::default()),
         };
 
-        let result = it
+        let mut result: Vec<LsEvent> = it
             .map(|ev| LsEvent::try_from_ev_tx(ev, transaction))
             .collect::<Result<Vec<_>>>()?
             .into_iter()
             .flatten()
             .collect();
+
+        // Every event of the transaction finds the reserves at its end
+        let reserves = result
+            .iter()
+            .filter_map(|ev| match ev {
+                LsEvent::PoolSnapshots(snapshot) => Some((
+                    snapshot.pool_id.clone(),
+                    (snapshot.x_val.clone(), snapshot.y_val.clone()),
+                )),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        TableLsEvent::set_reserves(
+            result.iter_mut().filter_map(|ev| match ev {
+                LsEvent::Events(event) => Some(event),
+                _ => None,
+            }),
+            &reserves,
+        );
+        // A swap row follows the row of its event
+        let mut event_reserves = None;
+        for ev in result.iter_mut() {
+            match ev {
+                LsEvent::Events(event) => {
+                    event_reserves = event.x_reserve.clone().zip(event.y_reserve.clone())
+                },
+                LsEvent::Swaps(swap) => swap.reserves = event_reserves.clone(),
+                _ => {},
+            }
+        }
+
         Ok(result)
     }
 
@@ -61,25 +150,40 @@ impl LsEvent {
         let pool_type = mv_st.pool_type()?;
 
         // Searching for new values for the pool
-        let pool_row = tx
-            .pool_row_from_resources(
-                mv_st,
-                version_ls,
-                tx.version.try_into().context("tx version")?,
-            )?
-            .map(LsEvent::Pools)
-            .map(|v| vec![v]);
+        let pool = tx.pool_row_from_resources(
+            mv_st,
+            version_ls,
+            tx.version.try_into().context("tx version")?,
+        )?;
+        let snapshot_row = match &pool {
+            Some(pool) => Some(LsEvent::PoolSnapshots(TableLsPoolSnapshot {
+                pool_id: pool.id.clone(),
+                version: pool.last_version,
+                timestamp: tx.timestamp.as_ref().context("tx timestamp")?.seconds,
+                x_val: pool.x_val.clone(),
+                y_val: pool.y_val.clone(),
+                fee: pool.fee,
+                dao_fee: pool.dao_fee,
+                lp_supply: tx.lp_supply_from_resources(mv_st)?.map(|v| v.into()),
+            })),
+            None => None,
+        };
+        let pool_row = pool.clone().map(LsEvent::Pools).map(|v| vec![v]);
 
         let event_type = LsEventType::from_str(&mv_st.name)?;
 
         match event_type {
             // When new pool created.
-            LsEventType::PoolCreatedEvent => Ok(pool_row.unwrap_or_else(|| {
-                vec![LsEvent::Pools(TableLsPool {
-                    version_ls: version_ls.clone(),
-                    ..(&pool_type).into()
-                })]
-            })),
+            LsEventType::PoolCreatedEvent => {
+                let mut result = pool_row.unwrap_or_else(|| {
+                    vec![LsEvent::Pools(TableLsPool {
+                        version_ls: version_ls.clone(),
+                        ..(&pool_type).into()
+                    })]
+                });
+                result.extend(snapshot_row);
+                Ok(result)
+            },
 
             LsEventType::LiquidityAddedEvent
             | LsEventType::OracleUpdatedEvent
@@ -88,33 +192,21 @@ impl LsEvent {
             | LsEventType::FlashloanEvent
             | LsEventType::UpdateFeeEvent
             | LsEventType::UpdateDAOFeeEvent
-            | LsEventType::CoinDepositedEvent => {
+            | LsEventType::StorageCreatedEvent
+            | LsEventType::CoinDepositedEvent
+            | LsEventType::CoinWithdrawnEvent => {
                 let TxInfoForLs {
                     version,
                     tx_hash,
                     timestamp,
                     sender,
+                    entry_function,
                 } = tx.info().ok_or(anyhow!(
                     "Not all data could be extracted from the transaction"
                 ))?;
 
                 let even_type = ev_ls.data_value()?;
-                let mut data: ObjEventType = serde_json::from_value(even_type.clone())
-                    .map_err(|err| anyhow!("{err:?}\n{even_type:?}"))?;
-
-                data = match event_type {
-                    LsEventType::UpdateFeeEvent => ObjEventType::UpdateFee {
-                        new_fee: data.fee().with_context(|| {
-                            format!("fee not found. event_type: {event_type:?}. data: {data:?}")
-                        })?,
-                    },
-                    LsEventType::UpdateDAOFeeEvent => ObjEventType::UpdateDaoFee {
-                        new_fee: data.fee().with_context(|| {
-                            format!("dao fee not found. event_type: {event_type:?}. data: {data:?}")
-                        })?,
-                    },
-                    _ => data,
-                };
+                let data = ObjEventType::from_event(event_type, &even_type)?;
 
                 let EventVal {
                     x_val,
@@ -123,8 +215,128 @@ impl LsEvent {
                     dao_fee,
                 } = data.get_val()?;
 
-                let event_row = LsEvent::Events(TableLsEvent {
-                    id: ev_ls.key()? + "_" + &ev_ls.sequence_number.to_string(),
+                // Volumes for the candles and daily stats, priced from the reserves after the swap
+                let swap_row = match (&pool, event_type, data.swap_amounts()?) {
+                    (Some(pool), LsEventType::SwapEvent, Some(amounts)) => {
+                        let event_index = unwrap_usr_tx(tx)
+                            .and_then(|usr_tx| event_index(&usr_tx.events, ev_ls))
+                            .context("event index")?;
+                        Some(LsEvent::Swaps(LsSwap::new(
+                            pool,
+                            version,
+                            event_index.try_into().context("event index")?,
+                            timestamp,
+                            amounts,
+                        )))
+                    },
+                    _ => None,
+                };
+
+                let trade_hop_row = match (event_type, data.swap_amounts()?) {
+                    (LsEventType::SwapEvent, Some(amounts)) => {
+                        Some(LsEvent::TradeHops(LsTradeHop::new(
+                            (version, &tx_hash, &sender, timestamp),
+                            pool_type.hash(),
+                            (&pool_type.x_name, &pool_type.y_name),
+                            amounts,
+                        )))
+                    },
+                    _ => None,
+                };
+
+                let trader_swap_row = match (event_type, data.swap_amounts()?) {
+                    (LsEventType::SwapEvent, Some((x_in, x_out, y_in, y_out))) => {
+                        Some(LsEvent::TraderSwaps(LsTraderSwap {
+                            account: sender.clone(),
+                            pool_id: pool_type.hash(),
+                            version,
+                            x_in: x_in.into(),
+                            x_out: x_out.into(),
+                            y_in: y_in.into(),
+                            y_out: y_out.into(),
+                        }))
+                    },
+                    _ => None,
+                };
+
+                let id = ev_ls.key()? + "_" + &ev_ls.sequence_number.to_string();
+
+                let lp_activity_row = match data.lp_change()? {
+                    Some((x_val, y_val, lp_tokens)) => {
+                        Some(LsEvent::LpActivities(TableLsLpActivity {
+                            id: id.clone(),
+                            lp_balance: tx
+                                .lp_balance_from_resources(&sender, mv_st)?
+                                .map(|v| v.into()),
+                            account: sender.clone(),
+                            pool_id: pool_type.hash(),
+                            tp: event_type,
+                            version,
+                            tx_hash: tx_hash.clone(),
+                            timestamp,
+                            x_val: x_val.into(),
+                            y_val: y_val.into(),
+                            lp_tokens: lp_tokens.into(),
+                        }))
+                    },
+                    None => None,
+                };
+
+                let oracle_row =
+                    data.cumulative_prices()?
+                        .map(|(price_x_cumulative, price_y_cumulative)| {
+                            LsEvent::OracleObservations(TableLsOracleObservation {
+                                id: id.clone(),
+                                pool_id: pool_type.hash(),
+                                version,
+                                timestamp,
+                                price_x_cumulative: price_x_cumulative.into(),
+                                price_y_cumulative: price_y_cumulative.into(),
+                            })
+                        });
+
+                let dao_storage_row = match data.dao_storage_change()? {
+                    Some((deposited_x, deposited_y, withdrawn_x, withdrawn_y)) => {
+                        Some(LsEvent::DaoStorageChanges(LsDaoStorageChange {
+                            pool_id: pool_type.hash(),
+                            version,
+                            deposited_x: deposited_x.into(),
+                            deposited_y: deposited_y.into(),
+                            withdrawn_x: withdrawn_x.into(),
+                            withdrawn_y: withdrawn_y.into(),
+                            balance: tx
+                                .dao_storage_from_resources(mv_st)?
+                                .map(|(x_val, y_val)| (x_val.into(), y_val.into())),
+                        }))
+                    },
+                    None => None,
+                };
+
+                let flashloan_row = match data.flashloan_amounts()? {
+                    Some((x_in, x_out, y_in, y_out)) => {
+                        Some(LsEvent::Flashloans(TableLsFlashloan {
+                            id: id.clone(),
+                            pool_id: pool_type.hash(),
+                            version,
+                            tx_hash: tx_hash.clone(),
+                            timestamp,
+                            borrower: sender.clone(),
+                            entry_function,
+                            x_borrowed: x_out.into(),
+                            y_borrowed: y_out.into(),
+                            x_repaid: x_in.into(),
+                            y_repaid: y_in.into(),
+                            x_fee: (x_in - x_out).into(),
+                            y_fee: (y_in - y_out).into(),
+                            fee: pool.as_ref().map(|pool| pool.fee),
+                            dao_fee: pool.as_ref().map(|pool| pool.dao_fee),
+                        }))
+                    },
+                    None => None,
+                };
+
+                let mut event_row = TableLsEvent {
+                    id,
                     pool_id: pool_type.hash(),
                     tp: event_type,
                     event: even_type,
@@ -137,15 +349,34 @@ impl LsEvent {
                     fee,
                     dao_fee,
                     sq: None,
-                });
+                    x_val_normalized: None,
+                    y_val_normalized: None,
+                    x_in: None,
+                    x_out: None,
+                    y_in: None,
+                    y_out: None,
+                    lp_tokens: None,
+                    x_reserve: None,
+                    y_reserve: None,
+                };
+                event_row.set_amounts(data.amounts()?);
+                let event_row = LsEvent::Events(event_row);
 
-                let result = match pool_row {
+                let mut result = match pool_row {
                     Some(mut result) => {
                         result.push(event_row);
                         result
                     },
                     None => vec![event_row],
                 };
+                result.extend(snapshot_row);
+                result.extend(swap_row);
+                result.extend(trade_hop_row);
+                result.extend(trader_swap_row);
+                result.extend(lp_activity_row);
+                result.extend(oracle_row);
+                result.extend(dao_storage_row);
+                result.extend(flashloan_row);
                 Ok(result)
             },
         }
@@ -155,27 +386,26 @@ impl LsEvent {
 #[async_trait]
 impl InsertToDb for Vec<LsEvent> {
     async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
-        let (pools, events): (Vec<_>, Vec<_>) = self
-            .into_iter()
-            .partition(|row| matches!(row, LsEvent::Pools(..)));
+        let mut pools = Vec::new();
+        let mut snapshots = Vec::new();
+        let mut events = Vec::new();
+        let mut flashloans = Vec::new();
 
-        let pools = pools
-            .into_iter()
-            .filter_map(|ls_db| match ls_db {
-                LsEvent::Pools(pools) => Some(pools),
-                _ => None,
-            })
-            .collect::<Vec<_>>();
-        pools.insert_to_db(conn).await?;
+        // The other rows are derived by the builders of their tables
+        for ls_db in self {
+            match ls_db {
+                LsEvent::Pools(pool) => pools.push(pool),
+                LsEvent::PoolSnapshots(snapshot) => snapshots.push(snapshot),
+                LsEvent::Events(event) => events.push(event),
+                LsEvent::Flashloans(flashloan) => flashloans.push(flashloan),
+                _ => {},
+            }
+        }
 
-        let events = events
-            .into_iter()
-            .filter_map(|ls_db| match ls_db {
-                LsEvent::Events(events) => Some(events),
-                _ => None,
-            })
-            .collect::<Vec<_>>();
+        pools.insert_to_db(conn).await?;
+        snapshots.insert_to_db(conn).await?;
         events.insert_to_db(conn).await?;
+        flashloans.insert_to_db(conn).await?;
 
         Ok(())
     }
@@ -214,9 +444,70 @@ pub enum ObjEventType {
         x_val: String,
         y_val: String,
     },
+    #[serde(skip_deserializing)]
+    Flashloan {
+        x_in: String,
+        y_in: String,
+        x_out: String,
+        y_out: String,
+    },
+    #[serde(skip_deserializing)]
+    CoinWithdrawnEvent {
+        x_val: String,
+        y_val: String,
+    },
+    #[serde(skip_deserializing)]
+    StorageCreated {},
 }
 
 impl ObjEventType {
+    /// Data of an event of `event_type` from its body, as stored in ls_events.event
+    pub(crate) fn from_event(event_type: LsEventType, value: &serde_json::Value) -> Result<Self> {
+        let data: ObjEventType = match event_type {
+            LsEventType::StorageCreatedEvent => ObjEventType::StorageCreated {},
+            _ => serde_json::from_value(value.clone())
+                .map_err(|err| anyhow!("{err:?}\n{value:?}"))?,
+        };
+
+        let data = match event_type {
+            LsEventType::UpdateFeeEvent => ObjEventType::UpdateFee {
+                new_fee: data.fee().with_context(|| {
+                    format!("fee not found. event_type: {event_type:?}. data: {data:?}")
+                })?,
+            },
+            LsEventType::UpdateDAOFeeEvent => ObjEventType::UpdateDaoFee {
+                new_fee: data.fee().with_context(|| {
+                    format!("dao fee not found. event_type: {event_type:?}. data: {data:?}")
+                })?,
+            },
+            // Same fields as SwapEvent
+            LsEventType::FlashloanEvent => match data {
+                ObjEventType::Swap {
+                    x_in,
+                    y_in,
+                    x_out,
+                    y_out,
+                } => ObjEventType::Flashloan {
+                    x_in,
+                    y_in,
+                    x_out,
+                    y_out,
+                },
+                _ => bail!("amounts not found. event_type: {event_type:?}. data: {data:?}"),
+            },
+            // Same fields as CoinDepositedEvent
+            LsEventType::CoinWithdrawnEvent => match data {
+                ObjEventType::CoinDepositedEvent { x_val, y_val } => {
+                    ObjEventType::CoinWithdrawnEvent { x_val, y_val }
+                },
+                _ => bail!("x_val, y_val not found. event_type: {event_type:?}. data: {data:?}"),
+            },
+            _ => data,
+        };
+
+        Ok(data)
+    }
+
     pub(crate) fn get_val(&self) -> Result<EventVal> {
         let mut result = EventVal::default();
 
@@ -229,11 +520,18 @@ impl ObjEventType {
                 result.x_val = Some(added_x_val.parse::<i128>()?);
                 result.y_val = Some(added_y_val.parse::<i128>()?);
             },
+            // The borrowed coins are repaid with the fee in the same transaction
             ObjEventType::Swap {
                 x_in,
                 y_in,
                 x_out,
                 y_out,
+            }
+            | ObjEventType::Flashloan {
+                x_in,
+                y_in,
+                x_out,
+                y_out,
             } => {
                 result.x_val = Some(x_in.parse::<i128>()? - x_out.parse::<i128>()?);
                 result.y_val = Some(y_in.parse::<i128>()? - y_out.parse::<i128>()?);
@@ -250,7 +548,10 @@ impl ObjEventType {
                 result.x_val = Some(-x_val.parse::<i128>()?);
                 result.y_val = Some(-y_val.parse::<i128>()?);
             },
-            ObjEventType::Last { .. } => {},
+            // Coins leave the DAO storage, not the pool
+            ObjEventType::CoinWithdrawnEvent { .. }
+            | ObjEventType::StorageCreated {}
+            | ObjEventType::Last { .. } => {},
             ObjEventType::UpdateFee { new_fee } => {
                 result.fee = Some(new_fee.parse()?);
             },
@@ -262,6 +563,123 @@ impl ObjEventType {
         Ok(result)
     }
 
+    /// @return: (x_in, x_out, y_in, y_out) of a swap
+    pub(crate) fn swap_amounts(&self) -> Result<Option<(i128, i128, i128, i128)>> {
+        match self {
+            ObjEventType::Swap {
+                x_in,
+                y_in,
+                x_out,
+                y_out,
+            } => Ok(Some((
+                x_in.parse()?,
+                x_out.parse()?,
+                y_in.parse()?,
+                y_out.parse()?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// @return: (x_in, x_out, y_in, y_out) of a flashloan
+    pub(crate) fn flashloan_amounts(&self) -> Result<Option<(i128, i128, i128, i128)>> {
+        match self {
+            ObjEventType::Flashloan {
+                x_in,
+                y_in,
+                x_out,
+                y_out,
+            } => Ok(Some((
+                x_in.parse()?,
+                x_out.parse()?,
+                y_in.parse()?,
+                y_out.parse()?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// @return: (x, y, lp tokens) added to or removed from a pool
+    pub(crate) fn lp_change(&self) -> Result<Option<(i128, i128, i128)>> {
+        match self {
+            ObjEventType::Added {
+                added_x_val,
+                added_y_val,
+                lp_tokens_received,
+            } => Ok(Some((
+                added_x_val.parse()?,
+                added_y_val.parse()?,
+                lp_tokens_received.parse()?,
+            ))),
+            ObjEventType::Return {
+                returned_x_val,
+                returned_y_val,
+                lp_tokens_burned,
+            } => Ok(Some((
+                returned_x_val.parse()?,
+                returned_y_val.parse()?,
+                lp_tokens_burned.parse()?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Gross amounts of the event for the typed columns of ls_events
+    pub(crate) fn amounts(&self) -> Result<EventAmounts> {
+        let mut result = EventAmounts::default();
+
+        if let Some((x_in, x_out, y_in, y_out)) = self.swap_amounts()?.or(self.flashloan_amounts()?)
+        {
+            result.x_in = Some(x_in);
+            result.x_out = Some(x_out);
+            result.y_in = Some(y_in);
+            result.y_out = Some(y_out);
+        }
+        if let Some((x_val, y_val, lp_tokens)) = self.lp_change()? {
+            match self {
+                ObjEventType::Added { .. } => {
+                    result.x_in = Some(x_val);
+                    result.y_in = Some(y_val);
+                },
+                _ => {
+                    result.x_out = Some(x_val);
+                    result.y_out = Some(y_val);
+                },
+            }
+            result.lp_tokens = Some(lp_tokens);
+        }
+
+        Ok(result)
+    }
+
+    /// @return: (last_price_x_cumulative, last_price_y_cumulative)
+    pub(crate) fn cumulative_prices(&self) -> Result<Option<(u128, u128)>> {
+        match self {
+            ObjEventType::Last {
+                last_price_x_cumulative,
+                last_price_y_cumulative,
+            } => Ok(Some((
+                last_price_x_cumulative.parse()?,
+                last_price_y_cumulative.parse()?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// @return: (deposited_x, deposited_y, withdrawn_x, withdrawn_y) of the DAO storage
+    pub(crate) fn dao_storage_change(&self) -> Result<Option<(i128, i128, i128, i128)>> {
+        match self {
+            ObjEventType::CoinDepositedEvent { x_val, y_val } => {
+                Ok(Some((x_val.parse()?, y_val.parse()?, 0, 0)))
+            },
+            ObjEventType::CoinWithdrawnEvent { x_val, y_val } => {
+                Ok(Some((0, 0, x_val.parse()?, y_val.parse()?)))
+            },
+            ObjEventType::StorageCreated {} => Ok(Some((0, 0, 0, 0))),
+            _ => Ok(None),
+        }
+    }
+
     /// @return: fee | dao_fee
     pub(crate) fn fee(&self) -> Option<String> {
         match self {
@@ -273,6 +691,16 @@ impl ObjEventType {
     }
 }
 
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct EventAmounts {
+    pub(crate) x_in: Option<i128>,
+    pub(crate) x_out: Option<i128>,
+    pub(crate) y_in: Option<i128>,
+    pub(crate) y_out: Option<i128>,
+    // lp_tokens_received | lp_tokens_burned
+    pub(crate) lp_tokens: Option<i128>,
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct EventVal {
     x_val: Option<i128>,
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use bigdecimal::BigDecimal;
+use diesel::{pg::Pg, query_builder::QueryFragment, Insertable, Queryable, Selectable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_flashloans},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
+};
+
+use super::db::InsertToDb;
+
+/// A flashloan of a pool, from FlashloanEvent.
+///
+/// The pool lends x_out and y_out and gets x_in and y_in back in the same transaction, its
+/// reserves change by the fee only. The DAO part of the fee then moves to the DAO storage with a
+/// CoinDepositedEvent.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_flashloans)]
+pub struct TableLsFlashloan {
+    pub id: String,
+    pub pool_id: String,
+    pub version: i64,
+    pub tx_hash: String,
+    pub timestamp: i64,
+    pub borrower: String,
+    pub entry_function: Option<String>,
+    // x_out
+    pub x_borrowed: BigDecimal,
+    // y_out
+    pub y_borrowed: BigDecimal,
+    // x_in
+    pub x_repaid: BigDecimal,
+    // y_in
+    pub y_repaid: BigDecimal,
+    // x_in - x_out
+    pub x_fee: BigDecimal,
+    // y_in - y_out
+    pub y_fee: BigDecimal,
+    // Fee settings of the pool at the flashloan, if its resource was written
+    pub fee: Option<i64>,
+    pub dao_fee: Option<i64>,
+}
+
+#[async_trait]
+impl InsertToDb for Vec<TableLsFlashloan> {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        let count = self.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_flashloans_query,
+            self,
+            TableLsFlashloan::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsFlashloan added");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_flashloans_query(
+    items_to_insert: Vec<TableLsFlashloan>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    (
+        diesel::insert_into(schema::ls_flashloans::table)
+            .values(items_to_insert)
+            .on_conflict(schema::ls_flashloans::id)
+            .do_nothing(),
+        None,
+    )
+}
@@ -1,15 +1,23 @@
-use crate::processors::ls_processor::mv::MoveStructTagLs;
+use crate::{processors::ls_processor::mv::MoveStructTagLs, utils::util::standardize_address};
 
 use super::db::TableLsPool;
 use anyhow::{Context, Result};
 use aptos_protos::transaction::v1::{
-    write_set_change::Change, MoveStructTag, MoveType, Transaction,
+    move_type::Content, write_set_change::Change, MoveStructTag, MoveType, Transaction,
+    WriteResource,
 };
 
 use serde::Deserialize;
 
 const MODULE_NAME: &str = "liquidity_pool";
 const RESOURCE_NAME: &str = "LiquidityPool";
+const DAO_STORAGE_MODULE_NAME: &str = "dao_storage";
+const DAO_STORAGE_NAME: &str = "Storage";
+const COIN_MODULE_NAME: &str = "coin";
+const COIN_STORE_NAME: &str = "CoinStore";
+const COIN_INFO_NAME: &str = "CoinInfo";
+const LP_COIN_MODULE_NAME: &str = "lp_coin";
+const LP_COIN_NAME: &str = "LP";
 
 pub(crate) trait PoolResourceFromTx {
     fn pool_resource_data_as_str(&self, address: &str, gen_tp: &[MoveType]) -> Option<&String>;
@@ -20,28 +28,101 @@ pub(crate) trait PoolResourceFromTx {
         ls_version: &str,
         tx_version: i64,
     ) -> Result<Option<TableLsPool>>;
+
+    fn lp_balance_from_resources(
+        &self,
+        account: &str,
+        mv_st: &MoveStructTag,
+    ) -> Result<Option<i128>>;
+
+    fn lp_supply_from_resources(&self, mv_st: &MoveStructTag) -> Result<Option<i128>>;
+
+    fn dao_storage_from_resources(&self, mv_st: &MoveStructTag) -> Result<Option<(i128, i128)>>;
 }
 
-impl PoolResourceFromTx for &Transaction {
-    fn pool_resource_data_as_str(&self, address: &str, gen_tp: &[MoveType]) -> Option<&String> {
-        self.info
-            .as_ref()?
-            .changes
-            .iter()
-            .filter_map(|ch| ch.change.as_ref())
-            .filter_map(|ch| match ch {
-                Change::WriteResource(r) => Some(r),
-                _ => None,
-            })
-            .filter_map(|ch| {
-                let tp = ch.r#type.as_ref()?;
-                (tp.address == address
+#[inline]
+fn write_resources<'a>(tx: &'a Transaction) -> impl Iterator<Item = &'a WriteResource> {
+    tx.info
+        .iter()
+        .flat_map(|info| info.changes.iter())
+        .filter_map(|ch| ch.change.as_ref())
+        .filter_map(|ch| match ch {
+            Change::WriteResource(r) => Some(r),
+            _ => None,
+        })
+}
+
+/// Account of the pool of the event, which holds its LiquidityPool resource and publishes its
+/// LP coin. The event is from a known deployment, so the account is its resource account.
+fn pool_account(tx: &Transaction, mv_st: &MoveStructTag) -> Option<String> {
+    write_resources(tx)
+        .filter(|ch| {
+            ch.r#type.as_ref().map_or(false, |tp| {
+                standardize_address(&tp.address) == standardize_address(&mv_st.address)
                     && tp.module == MODULE_NAME
                     && tp.name == RESOURCE_NAME
-                    && tp.generic_type_params == gen_tp)
-                    .then_some(&ch.data)
+                    && tp.generic_type_params == mv_st.generic_type_params
             })
-            .last()
+        })
+        .map(|ch| standardize_address(&ch.address))
+        .last()
+}
+
+/// Data of 0x1::coin::{name}<{resource_address}::lp_coin::LP<X, Y, Curve>> of the pool of the
+/// event, at the address of the account if set. Other LP coins of the same X, Y and Curve are
+/// not from the deployment and are skipped.
+fn lp_coin_resource_data<'a>(
+    tx: &'a Transaction,
+    name: &str,
+    account: Option<&str>,
+    mv_st: &MoveStructTag,
+) -> Option<&'a String> {
+    let resource_address = pool_account(tx, mv_st)?;
+    let gen_tp = mv_st.generic_type_params.as_slice();
+    write_resources(tx)
+        .filter_map(|ch| {
+            let tp = ch.r#type.as_ref()?;
+            let lp_tp = match tp.generic_type_params.as_slice() {
+                [MoveType {
+                    content: Some(Content::Struct(lp_tp)),
+                    ..
+                }] => lp_tp,
+                _ => return None,
+            };
+            (account.map_or(true, |account| standardize_address(&ch.address) == account)
+                && tp.module == COIN_MODULE_NAME
+                && tp.name == name
+                && standardize_address(&lp_tp.address) == resource_address
+                && lp_tp.module == LP_COIN_MODULE_NAME
+                && lp_tp.name == LP_COIN_NAME
+                && lp_tp.generic_type_params == gen_tp)
+                .then_some(&ch.data)
+        })
+        .last()
+}
+
+/// Data of {address}::{module}::{name}<X, Y, Curve>
+fn ls_resource_data<'a>(
+    tx: &'a Transaction,
+    address: &str,
+    (module, name): (&str, &str),
+    gen_tp: &[MoveType],
+) -> Option<&'a String> {
+    write_resources(tx)
+        .filter_map(|ch| {
+            let tp = ch.r#type.as_ref()?;
+            (standardize_address(&tp.address) == standardize_address(address)
+                && tp.module == module
+                && tp.name == name
+                && tp.generic_type_params == gen_tp)
+                .then_some(&ch.data)
+        })
+        .last()
+}
+
+impl PoolResourceFromTx for &Transaction {
+    fn pool_resource_data_as_str(&self, address: &str, gen_tp: &[MoveType]) -> Option<&String> {
+        ls_resource_data(self, address, (MODULE_NAME, RESOURCE_NAME), gen_tp)
     }
 
     /// Searching for new values for the pool
@@ -74,6 +155,88 @@ impl PoolResourceFromTx for &Transaction {
 
         Ok(Some(pool_row))
     }
+
+    /// LP coin balance of the account after the transaction,
+    /// from 0x1::coin::CoinStore<{resource_address}::lp_coin::LP<X, Y, Curve>>
+    fn lp_balance_from_resources(
+        &self,
+        account: &str,
+        mv_st: &MoveStructTag,
+    ) -> Result<Option<i128>> {
+        let coin_store_str =
+            match lp_coin_resource_data(self, COIN_STORE_NAME, Some(account), mv_st) {
+                Some(resource) => resource,
+                None => return Ok(None),
+            };
+
+        let coin_store: CoinStoreResource = serde_json::from_str(coin_store_str)
+            .with_context(|| format!("Data: {coin_store_str}"))?;
+        let balance = coin_store
+            .coin
+            .value
+            .parse()
+            .with_context(|| format!("coin value: {}", &coin_store.coin.value))?;
+
+        Ok(Some(balance))
+    }
+
+    /// LP coin supply after the transaction,
+    /// from 0x1::coin::CoinInfo<{resource_address}::lp_coin::LP<X, Y, Curve>>.
+    /// `None` if the supply is not tracked or is a parallelizable aggregator.
+    fn lp_supply_from_resources(&self, mv_st: &MoveStructTag) -> Result<Option<i128>> {
+        let coin_info_str = match lp_coin_resource_data(self, COIN_INFO_NAME, None, mv_st) {
+            Some(resource) => resource,
+            None => return Ok(None),
+        };
+
+        let coin_info: CoinInfoResource = serde_json::from_str(coin_info_str)
+            .with_context(|| format!("Data: {coin_info_str}"))?;
+        let supply = match coin_info
+            .supply
+            .vec
+            .first()
+            .and_then(|supply| supply.integer.vec.first())
+        {
+            Some(supply) => supply,
+            None => return Ok(None),
+        };
+
+        let supply = supply
+            .value
+            .parse()
+            .with_context(|| format!("supply value: {}", &supply.value))?;
+
+        Ok(Some(supply))
+    }
+
+    /// Coins in the DAO storage of the pool after the transaction,
+    /// from {resource_address}::dao_storage::Storage<X, Y, Curve>
+    fn dao_storage_from_resources(&self, mv_st: &MoveStructTag) -> Result<Option<(i128, i128)>> {
+        let storage_str = match ls_resource_data(
+            self,
+            &mv_st.address,
+            (DAO_STORAGE_MODULE_NAME, DAO_STORAGE_NAME),
+            &mv_st.generic_type_params,
+        ) {
+            Some(resource) => resource,
+            None => return Ok(None),
+        };
+
+        let storage: DaoStorageResource =
+            serde_json::from_str(storage_str).with_context(|| format!("Data: {storage_str}"))?;
+        let x_val = storage
+            .coin_x
+            .value
+            .parse()
+            .with_context(|| format!("coin_x value: {}", &storage.coin_x.value))?;
+        let y_val = storage
+            .coin_y
+            .value
+            .parse()
+            .with_context(|| format!("coin_y value: {}", &storage.coin_y.value))?;
+
+        Ok(Some((x_val, y_val)))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -115,3 +278,29 @@ impl PoolResource {
 struct PoolResourceValue {
     value: String,
 }
+
+#[derive(Debug, Deserialize)]
+struct CoinStoreResource {
+    coin: PoolResourceValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct DaoStorageResource {
+    coin_x: PoolResourceValue,
+    coin_y: PoolResourceValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinInfoResource {
+    supply: MoveOption<OptionalAggregator>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoveOption<T> {
+    vec: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OptionalAggregator {
+    integer: MoveOption<PoolResourceValue>,
+}
@@ -0,0 +1,386 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, Zero};
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, upsert::excluded, ExpressionMethods, Insertable,
+    QueryDsl, Queryable, Selectable, SelectableHelper,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_lp_activities, ls_lp_positions},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
+};
+
+use super::{
+    db::{InsertToDb, LsEventType},
+    events::LsEvent,
+};
+
+/// Liquidity added to or removed from a pool by an account.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_lp_activities)]
+pub struct TableLsLpActivity {
+    pub id: String,
+    pub account: String,
+    pub pool_id: String,
+    pub tp: LsEventType,
+    pub version: i64,
+    pub tx_hash: String,
+    pub timestamp: i64,
+    // added_x_val | returned_x_val
+    pub x_val: BigDecimal,
+    // added_y_val | returned_y_val
+    pub y_val: BigDecimal,
+    // lp_tokens_received | lp_tokens_burned
+    pub lp_tokens: BigDecimal,
+    // LP coin balance of the account after the transaction, if its CoinStore was written
+    pub lp_balance: Option<BigDecimal>,
+}
+
+impl TableLsLpActivity {
+    fn is_added(&self) -> bool {
+        self.tp == LsEventType::LiquidityAddedEvent
+    }
+}
+
+/// Current LP balance of an account in a pool, with the X and Y it paid for it.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_lp_positions)]
+pub struct TableLsLpPosition {
+    pub account: String,
+    pub pool_id: String,
+    pub lp_balance: BigDecimal,
+    pub cost_x: BigDecimal,
+    pub cost_y: BigDecimal,
+    pub last_version: i64,
+}
+
+type PositionKey = (String, String);
+
+impl TableLsLpPosition {
+    fn new(account: &str, pool_id: &str) -> Self {
+        TableLsLpPosition {
+            account: account.to_string(),
+            pool_id: pool_id.to_string(),
+            lp_balance: BigDecimal::zero(),
+            cost_x: BigDecimal::zero(),
+            cost_y: BigDecimal::zero(),
+            last_version: 0,
+        }
+    }
+
+    fn key(&self) -> PositionKey {
+        (self.account.clone(), self.pool_id.clone())
+    }
+
+    /// `last_in_tx`: the last activity of the account in the pool in its transaction, the only one
+    /// the balance after the transaction is right for.
+    fn apply(&mut self, activity: &TableLsLpActivity, last_in_tx: bool) {
+        let lp_balance = match &activity.lp_balance {
+            Some(lp_balance) if last_in_tx => lp_balance.clone(),
+            _ if activity.is_added() => &self.lp_balance + &activity.lp_tokens,
+            _ => &self.lp_balance - &activity.lp_tokens,
+        };
+
+        if activity.is_added() {
+            self.cost_x += &activity.x_val;
+            self.cost_y += &activity.y_val;
+        } else {
+            // The burned share of the position takes its share of the cost basis with it
+            let lp_balance_before = &lp_balance + &activity.lp_tokens;
+            if lp_balance_before.is_zero() || lp_balance.is_zero() {
+                self.cost_x = BigDecimal::zero();
+                self.cost_y = BigDecimal::zero();
+            } else {
+                self.cost_x = (&self.cost_x * &lp_balance / &lp_balance_before).with_scale(0);
+                self.cost_y = (&self.cost_y * &lp_balance / &lp_balance_before).with_scale(0);
+            }
+        }
+
+        self.lp_balance = lp_balance;
+        self.last_version = activity.version;
+    }
+}
+
+/// LP activities of a batch and the positions they update.
+///
+/// Activities at or before the `last_version` a position was loaded with are already part of it,
+/// so reprocessing a batch does not apply them twice.
+pub(crate) struct LsLpActivities {
+    activities: Vec<TableLsLpActivity>,
+}
+
+impl LsLpActivities {
+    pub(crate) fn new(events: &[LsEvent]) -> Self {
+        let mut activities = events
+            .iter()
+            .filter_map(|ev| match ev {
+                LsEvent::LpActivities(activity) => Some(activity.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        activities.sort_by_key(|activity| activity.version);
+
+        LsLpActivities { activities }
+    }
+
+    fn positions(&self, existing: Vec<TableLsLpPosition>) -> Vec<TableLsLpPosition> {
+        let mut positions: HashMap<PositionKey, (i64, TableLsLpPosition)> = existing
+            .into_iter()
+            .map(|position| (position.key(), (position.last_version, position)))
+            .collect();
+        let mut updated = HashSet::new();
+
+        // Several activities of an account in a pool in one transaction all see the balance at
+        // its end, the ones before the last follow the LP tokens instead
+        let mut seen = HashSet::new();
+        let mut last_in_tx = vec![false; self.activities.len()];
+        for (index, activity) in self.activities.iter().enumerate().rev() {
+            last_in_tx[index] =
+                seen.insert((&activity.account, &activity.pool_id, activity.version));
+        }
+
+        for (activity, last_in_tx) in self.activities.iter().zip(last_in_tx) {
+            let key = (activity.account.clone(), activity.pool_id.clone());
+            let (loaded_version, position) = positions.entry(key.clone()).or_insert_with(|| {
+                (
+                    0,
+                    TableLsLpPosition::new(&activity.account, &activity.pool_id),
+                )
+            });
+            if activity.version <= *loaded_version {
+                continue;
+            }
+            position.apply(activity, last_in_tx);
+            updated.insert(key);
+        }
+
+        positions
+            .into_iter()
+            .filter_map(|(key, (_, position))| updated.contains(&key).then_some(position))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InsertToDb for LsLpActivities {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        if self.activities.is_empty() {
+            return Ok(());
+        }
+
+        let accounts = self
+            .activities
+            .iter()
+            .map(|activity| activity.account.clone())
+            .collect::<HashSet<_>>();
+        let pool_ids = self
+            .activities
+            .iter()
+            .map(|activity| activity.pool_id.clone())
+            .collect::<HashSet<_>>();
+
+        // Positions of the accounts, whose last_version skips activities they already hold.
+        // Positions in pools other accounts used may come along, they are not written back.
+        let existing = schema::ls_lp_positions::table
+            .filter(schema::ls_lp_positions::account.eq_any(accounts))
+            .filter(schema::ls_lp_positions::pool_id.eq_any(pool_ids))
+            .select(TableLsLpPosition::as_select())
+            .load::<TableLsLpPosition>(conn)
+            .await?;
+
+        let positions = self.positions(existing);
+
+        let activities_count = self.activities.len();
+        let positions_count = positions.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_lp_activities_query,
+            self.activities,
+            TableLsLpActivity::field_count(),
+        )
+        .await?;
+        info!("{activities_count} TableLsLpActivity added");
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_lp_positions_query,
+            positions,
+            TableLsLpPosition::field_count(),
+        )
+        .await?;
+        info!("{positions_count} TableLsLpPosition added/updated");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_lp_activities_query(
+    items_to_insert: Vec<TableLsLpActivity>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_lp_activities::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_lp_activities::table)
+            .values(items_to_insert)
+            .on_conflict(id)
+            .do_nothing(),
+        None,
+    )
+}
+
+fn insert_ls_lp_positions_query(
+    items_to_insert: Vec<TableLsLpPosition>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_lp_positions::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_lp_positions::table)
+            .values(items_to_insert)
+            .on_conflict((account, pool_id))
+            .do_update()
+            .set((
+                lp_balance.eq(excluded(lp_balance)),
+                cost_x.eq(excluded(cost_x)),
+                cost_y.eq(excluded(cost_y)),
+                last_version.eq(excluded(last_version)),
+            )),
+        Some(" WHERE ls_lp_positions.last_version < EXCLUDED.last_version "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::ls_processor::tests::replay_overlapping_batch;
+
+    fn activity(
+        version: i64,
+        tp: LsEventType,
+        (x_val, y_val, lp_tokens): (i64, i64, i64),
+        lp_balance: Option<i64>,
+    ) -> TableLsLpActivity {
+        TableLsLpActivity {
+            id: format!("ev_{version}"),
+            account: "0x1".to_string(),
+            pool_id: "pool".to_string(),
+            tp,
+            version,
+            tx_hash: String::new(),
+            timestamp: 0,
+            x_val: x_val.into(),
+            y_val: y_val.into(),
+            lp_tokens: lp_tokens.into(),
+            lp_balance: lp_balance.map(BigDecimal::from),
+        }
+    }
+
+    #[test]
+    fn test_positions_track_cost_basis() {
+        let activities = LsLpActivities {
+            activities: vec![
+                activity(
+                    1,
+                    LsEventType::LiquidityAddedEvent,
+                    (100, 200, 10),
+                    Some(10),
+                ),
+                activity(2, LsEventType::LiquidityAddedEvent, (50, 100, 5), None),
+                activity(3, LsEventType::LiquidityRemovedEvent, (60, 120, 6), Some(9)),
+            ],
+        };
+
+        let positions = activities.positions(vec![]);
+        assert_eq!(positions.len(), 1);
+        let position = &positions[0];
+        assert_eq!(position.lp_balance, 9.into());
+        assert_eq!(position.cost_x, 90.into());
+        assert_eq!(position.cost_y, 180.into());
+        assert_eq!(position.last_version, 3);
+
+        // Reprocessing the batch leaves the position as it is
+        assert!(activities.positions(positions).is_empty());
+    }
+
+    #[test]
+    fn test_balance_after_the_tx_is_for_its_last_activity() {
+        let activities = LsLpActivities {
+            activities: vec![
+                activity(
+                    1,
+                    LsEventType::LiquidityAddedEvent,
+                    (100, 200, 10),
+                    Some(10),
+                ),
+                // Half of the LP tokens burned, then more added in the same transaction
+                activity(
+                    2,
+                    LsEventType::LiquidityRemovedEvent,
+                    (50, 100, 5),
+                    Some(15),
+                ),
+                activity(
+                    2,
+                    LsEventType::LiquidityAddedEvent,
+                    (100, 200, 10),
+                    Some(15),
+                ),
+            ],
+        };
+
+        let positions = activities.positions(vec![]);
+        assert_eq!(positions.len(), 1);
+        let position = &positions[0];
+        assert_eq!(position.lp_balance, 15.into());
+        assert_eq!(position.cost_x, 150.into());
+        assert_eq!(position.cost_y, 300.into());
+    }
+
+    #[test]
+    fn test_batch_after_a_later_one() {
+        let positions = replay_overlapping_batch(
+            LsLpActivities {
+                activities: vec![
+                    activity(
+                        1,
+                        LsEventType::LiquidityAddedEvent,
+                        (100, 200, 10),
+                        Some(10),
+                    ),
+                    activity(2, LsEventType::LiquidityAddedEvent, (50, 100, 5), Some(15)),
+                ],
+            },
+            LsLpActivities {
+                activities: vec![
+                    activity(2, LsEventType::LiquidityAddedEvent, (50, 100, 5), Some(15)),
+                    activity(
+                        3,
+                        LsEventType::LiquidityRemovedEvent,
+                        (50, 100, 5),
+                        Some(10),
+                    ),
+                ],
+            },
+            |activities, existing| activities.positions(existing),
+        );
+        assert_eq!(positions.len(), 1);
+        let position = &positions[0];
+        assert_eq!(position.lp_balance, 10.into());
+        assert_eq!(position.cost_x, 100.into());
+        assert_eq!(position.cost_y, 200.into());
+        assert_eq!(position.last_version, 3);
+    }
+}
@@ -1,25 +1,46 @@
-use std::fmt::Debug;
+use std::{collections::HashSet, fmt::Debug};
 
+use anyhow::Context;
 use async_trait::async_trait;
+use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
 use tracing::{error, instrument};
 
 use aptos_protos::transaction::v1::Transaction;
 
+pub mod backfill;
+pub mod bench;
+mod candles;
+mod coins;
+mod daily;
+mod dao;
 pub mod db;
-mod events;
+mod deployments;
+pub(crate) mod events;
+mod flashloans;
 pub(crate) mod info;
-mod mv;
+mod lp;
+pub(crate) mod mv;
+mod oracle;
+mod prices;
+pub mod reconcile;
+mod trader_stats;
+mod trades;
 
 use crate::processors::ls_processor::db::InsertToDb;
 use crate::{
-    processors::{
-        ls_processor::mv::clr_hex_address, ProcessingResult, ProcessorName, ProcessorTrait,
+    processors::{ProcessingResult, ProcessorName, ProcessorTrait},
+    utils::{
+        database::{BatchWriteMode, BatchWriter, PgDbPool, PgPoolConnection},
+        util::standardize_address,
     },
-    utils::database::{PgDbPool, PgPoolConnection},
 };
 
-use self::events::LsEvent;
+use self::{
+    candles::LsCandles, coins::LsCoins, daily::LsPoolDailyStats, dao::LsDaoStorages,
+    deployments::LsDeployments, events::LsEvent, lp::LsLpActivities, oracle::LsOracle,
+    prices::LsPrices, trader_stats::LsTraderStats, trades::LsTrades,
+};
 
 pub struct LsProcessor {
     connection_pool: PgDbPool,
@@ -31,8 +52,8 @@ impl LsProcessor {
         ls_config
             .address
             .iter_mut()
-            .for_each(|(_version_ls, address)| *address = clr_hex_address(address));
-
+            .chain(ls_config.deployers.iter_mut())
+            .for_each(|(_version_ls, address)| *address = standardize_address(address));
         Self {
             connection_pool,
             ls_config,
@@ -60,7 +81,7 @@ impl ProcessorTrait for LsProcessor {
     #[instrument(level = "debug", skip(self, transactions))]
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -68,9 +89,21 @@ impl ProcessorTrait for LsProcessor {
         let processing_start = std::time::Instant::now();
         let last_transaction_timstamp = transactions.last().and_then(|t| t.timestamp.clone());
 
-        let events: Vec<LsEvent> = LsEvent::try_from_txs(&self.ls_config.address, &transactions)?;
+        let deployments = LsDeployments::from_txs(&self.ls_config.deployers, transactions);
+        let addresses = if self.ls_config.deployers.is_empty() {
+            self.ls_config.address.clone()
+        } else {
+            let mut conn: PgPoolConnection = self.connection_pool.get().await?;
+            deployments
+                .addresses(&self.ls_config.address, &mut conn)
+                .await?
+        };
 
-        if events.is_empty() {
+        let mut events: Vec<LsEvent> = LsEvent::try_from_txs(&addresses, transactions)?;
+        let mut coins = LsCoins::try_from_txs(transactions)?;
+
+        // CoinInfo writes are recorded even without LS events, a pool may trade the coin later
+        if events.is_empty() && deployments.is_empty() && coins.is_empty() {
             let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
 
             return Ok(ProcessingResult {
@@ -84,9 +117,54 @@ impl ProcessorTrait for LsProcessor {
 
         let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
         let db_insertion_start = std::time::Instant::now();
-        let mut conn: PgPoolConnection = self.connection_pool.get().await?;
 
-        events.insert_to_db(&mut conn).await.map_err(|err| {
+        // The tables are built from the rows of earlier batches, so the whole batch is written
+        // in one transaction, along with the processor status. Batches are committed in order,
+        // see `ProcessorConfig::requires_ordered_batches`.
+        let insert_result = async {
+            let mut writer =
+                BatchWriter::new(self.get_pool(), BatchWriteMode::AtomicWithStatus).await?;
+            let conn = writer
+                .transaction_conn()
+                .context("Atomic batch writes run in a transaction")?;
+
+            // Coin metadata comes partly from the db
+            coins.enrich(&mut events, conn).await?;
+            // Prices start from ls_pools as it was before the batch
+            let prices = LsPrices::try_new(
+                &events,
+                &self.ls_config.stablecoins,
+                &BigDecimal::from(self.ls_config.full_confidence_liquidity_usd),
+                conn,
+            )
+            .await?;
+
+            let candles = LsCandles::new(&events, &self.ls_config.candle_intervals_in_secs);
+            let daily_stats = LsPoolDailyStats::new(&events);
+            let dao_storages = LsDaoStorages::new(&events);
+            let lp_activities = LsLpActivities::new(&events);
+            let oracle = LsOracle::new(&events, &self.ls_config.twap_windows_in_secs);
+            let trader_stats = LsTraderStats::new(&events);
+            let trades = LsTrades::try_new(&events, transactions, &coins)?;
+
+            deployments.insert_to_db(conn).await?;
+            coins.insert_to_db(conn).await?;
+            events.insert_to_db(conn).await?;
+            candles.insert_to_db(conn).await?;
+            daily_stats.insert_to_db(conn).await?;
+            dao_storages.insert_to_db(conn).await?;
+            lp_activities.insert_to_db(conn).await?;
+            oracle.insert_to_db(conn).await?;
+            prices.insert_to_db(conn).await?;
+            trader_stats.insert_to_db(conn).await?;
+            trades.insert_to_db(conn).await?;
+
+            self.commit_batch(writer, end_version, last_transaction_timstamp.clone())
+                .await?;
+            anyhow::Ok(())
+        }
+        .await;
+        insert_result.map_err(|err| {
             error!(
                 start_version = start_version,
                 end_version = end_version,
@@ -104,7 +182,7 @@ impl ProcessorTrait for LsProcessor {
             end_version,
             processing_duration_in_secs,
             db_insertion_duration_in_secs,
-            last_transaction_timstamp: transactions.last().unwrap().timestamp.clone(),
+            last_transaction_timstamp,
         })
     }
 
@@ -122,5 +200,73 @@ pub struct LsConfigs {
     // 0x61d2c22a6cb7831bee0f48363b0eec92369357aece0d1142062f7d5d85c7bef8
     //
     // Vec<(VERSION_LS,ADDRESS)>
+    #[serde(default)]
     address: Vec<(String, String)>,
+
+    // Accounts whose publishes of liquidity_pool/dao_storage add a deployment to ls_deployments,
+    // indexed along with `address`. The publish may be at the account itself or, with it as the
+    // sender, at a resource account. ls_deployments is read every batch if set.
+    //
+    // Vec<(VERSION_LS,DEPLOYER)>
+    #[serde(default)]
+    deployers: Vec<(String, String)>,
+
+    // Intervals of ls_candles. Default: 1m, 5m, 1h, 1d
+    #[serde(default = "LsConfigs::default_candle_intervals_in_secs")]
+    candle_intervals_in_secs: Vec<i64>,
+
+    // Windows of ls_twaps. Default: 5m, 30m, 1h
+    #[serde(default = "LsConfigs::default_twap_windows_in_secs")]
+    twap_windows_in_secs: Vec<i64>,
+
+    // Coin types worth 1 USD in coin_prices, as in ls_pools.x_name. No prices if empty.
+    #[serde(default)]
+    stablecoins: HashSet<String>,
+
+    // Liquidity in USD behind a price in coin_prices for a confidence of 1. Default: 100000
+    #[serde(default = "LsConfigs::default_full_confidence_liquidity_usd")]
+    full_confidence_liquidity_usd: u64,
+}
+
+impl LsConfigs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.candle_intervals_in_secs
+                .iter()
+                .all(|interval| *interval > 0),
+            "candle_intervals_in_secs must be positive"
+        );
+        anyhow::ensure!(
+            self.twap_windows_in_secs.iter().all(|window| *window > 0),
+            "twap_windows_in_secs must be positive"
+        );
+        Ok(())
+    }
+
+    fn default_candle_intervals_in_secs() -> Vec<i64> {
+        vec![60, 5 * 60, 60 * 60, 24 * 60 * 60]
+    }
+
+    fn default_twap_windows_in_secs() -> Vec<i64> {
+        vec![5 * 60, 30 * 60, 60 * 60]
+    }
+
+    fn default_full_confidence_liquidity_usd() -> u64 {
+        100_000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Commits `committed`, then processes `replayed`, a batch that starts at or before the last
+    /// version of `committed` and runs past it, e.g. after a restart at an earlier version.
+    /// @return: the rows that `replayed` writes
+    pub(super) fn replay_overlapping_batch<B, R>(
+        committed: B,
+        replayed: B,
+        build: impl Fn(B, Vec<R>) -> Vec<R>,
+    ) -> Vec<R> {
+        let existing = build(committed, vec![]);
+        build(replayed, existing)
+    }
 }
@@ -7,10 +7,13 @@ use aptos_protos::transaction::v1::{
     Event, MoveStructTag, MoveType, Transaction, UserTransaction,
 };
 
+use crate::utils::util::{get_entry_function_from_user_request, standardize_address};
+
 use super::db::LsEventType;
 
-const LS_MODULE: [&str; 2] = ["liquidity_pool", "dao_storage"];
+pub(crate) const LS_MODULE: [&str; 2] = ["liquidity_pool", "dao_storage"];
 
+/// `addresses` are standardized, see `standardize_address`
 /// @return (version_ls, event ls)
 pub(crate) fn filter_ls_events<'a>(
     addresses: &'a [(String, String)],
@@ -28,9 +31,10 @@ pub(crate) fn filter_ls_events<'a>(
 
         (LS_MODULE.contains(&ms.module.as_str()) && LsEventType::from_str(&ms.name).is_ok())
             .then(|| {
+                let ms_address = standardize_address(&ms.address);
                 addresses
                     .iter()
-                    .find(|(_version_ls, address)| address == &ms.address)
+                    .find(|(_version_ls, address)| address == &ms_address)
                     .map(|(version_ls, _)| (version_ls, ev))
             })
             .and_then(|v| v)
@@ -38,15 +42,23 @@ pub(crate) fn filter_ls_events<'a>(
     Some(itr)
 }
 
+/// Index of `event` in `tx_events`, which it has to be borrowed from. Module events have no key
+/// and all share sequence number 0, so this is how the events of a transaction are told apart.
+pub(crate) fn event_index(tx_events: &[Event], event: &Event) -> Option<usize> {
+    tx_events
+        .iter()
+        .position(|tx_event| std::ptr::eq(tx_event, event))
+}
+
 #[inline]
-fn unwrap_usr_tx(tx: &Transaction) -> Option<&UserTransaction> {
+pub(crate) fn unwrap_usr_tx(tx: &Transaction) -> Option<&UserTransaction> {
     match tx.txn_data.as_ref()? {
         TxnData::User(user_tx, ..) => Some(user_tx),
         _ => None,
     }
 }
 
-fn move_type_to_string(mv: &MoveType) -> Option<String> {
+pub(crate) fn move_type_to_string(mv: &MoveType) -> Option<String> {
     let content = mv.content.as_ref()?;
 
     let result = match content {
@@ -92,14 +104,6 @@ fn move_type_to_string(mv: &MoveType) -> Option<String> {
     Some(result)
 }
 
-#[inline]
-pub(crate) fn clr_hex_address(address: &str) -> String {
-    format!(
-        "0x{}",
-        address.trim_start_matches("0x").trim_start_matches('0')
-    )
-}
-
 pub(crate) trait EventLs {
     fn move_struct(&self) -> Option<&MoveStructTag>;
     fn data_value(&self) -> Result<serde_json::Value>;
@@ -189,13 +193,20 @@ impl TransactionLs for Transaction {
         let info = self.info.as_ref()?;
         let tx_hash = hex::encode(&info.hash);
         let timestamp = self.timestamp.as_ref()?.seconds;
-        let sender = clr_hex_address(&unwrap_usr_tx(self)?.request.as_ref()?.sender);
+        let request = unwrap_usr_tx(self)?.request.as_ref()?;
+        let sender = standardize_address(&request.sender);
+        let entry_function = request
+            .payload
+            .is_some()
+            .then(|| get_entry_function_from_user_request(request))
+            .flatten();
 
         Some(TxInfoForLs {
             version,
             tx_hash,
             timestamp,
             sender,
+            entry_function,
         })
     }
 }
@@ -205,4 +216,5 @@ pub(crate) struct TxInfoForLs {
     pub(crate) tx_hash: String,
     pub(crate) timestamp: i64,
     pub(crate) sender: String,
+    pub(crate) entry_function: Option<String>,
 }
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use bigdecimal::BigDecimal;
+use diesel::{
+    pg::Pg,
+    query_builder::QueryFragment,
+    sql_query,
+    sql_types::{Array, Int8, Varchar},
+    upsert::excluded,
+    ExpressionMethods, Insertable, Queryable, QueryableByName, Selectable,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_oracle_observations, ls_twaps},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
+};
+
+use super::{
+    db::{InsertToDb, PRICE_SCALE},
+    events::LsEvent,
+};
+
+// Cumulative prices are sums of UQ64.64 prices, stored as u128 that wrap on overflow
+static UQ64_ONE: Lazy<BigDecimal> = Lazy::new(|| BigDecimal::from(1_u128 << 64));
+static U128_MODULUS: Lazy<BigDecimal> =
+    Lazy::new(|| BigDecimal::from(u128::MAX) + BigDecimal::from(1));
+
+/// Cumulative prices of a pool from OracleUpdatedEvent.
+#[derive(
+    Selectable,
+    Queryable,
+    QueryableByName,
+    Insertable,
+    FieldCount,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+)]
+#[diesel(table_name = ls_oracle_observations)]
+pub struct TableLsOracleObservation {
+    pub id: String,
+    pub pool_id: String,
+    pub version: i64,
+    pub timestamp: i64,
+    pub price_x_cumulative: BigDecimal,
+    pub price_y_cumulative: BigDecimal,
+}
+
+/// The observation a TWAP over the window starts at.
+#[derive(QueryableByName)]
+struct WindowStart {
+    #[diesel(sql_type = Int8)]
+    window_secs: i64,
+    #[diesel(embed)]
+    observation: TableLsOracleObservation,
+}
+
+/// Time-weighted average prices of a pool over a window, ending at its latest observation.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_twaps)]
+pub struct TableLsTwap {
+    pub pool_id: String,
+    pub window_secs: i64,
+    // Price of X in Y
+    pub twap_x: BigDecimal,
+    // Price of Y in X
+    pub twap_y: BigDecimal,
+    pub start_version: i64,
+    pub end_version: i64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+impl TableLsTwap {
+    /// `None` if no time passed between the observations.
+    fn new(
+        start: &TableLsOracleObservation,
+        end: &TableLsOracleObservation,
+        window_secs: i64,
+    ) -> Option<Self> {
+        let elapsed = end.timestamp - start.timestamp;
+        if elapsed <= 0 {
+            return None;
+        }
+
+        Some(TableLsTwap {
+            pool_id: end.pool_id.clone(),
+            window_secs,
+            twap_x: twap(&start.price_x_cumulative, &end.price_x_cumulative, elapsed),
+            twap_y: twap(&start.price_y_cumulative, &end.price_y_cumulative, elapsed),
+            start_version: start.version,
+            end_version: end.version,
+            start_timestamp: start.timestamp,
+            end_timestamp: end.timestamp,
+        })
+    }
+}
+
+fn twap(start: &BigDecimal, end: &BigDecimal, elapsed: i64) -> BigDecimal {
+    let mut diff = end - start;
+    if diff < BigDecimal::from(0) {
+        diff += &*U128_MODULUS;
+    }
+    (diff / BigDecimal::from(elapsed) / &*UQ64_ONE).with_scale(PRICE_SCALE)
+}
+
+/// Oracle observations of a batch and the TWAPs ending at them.
+///
+/// The TWAP over a window starts at the last observation at least `window_secs` before the
+/// latest one. Pools without such an observation yet get no TWAP for the window.
+pub(crate) struct LsOracle {
+    observations: Vec<TableLsOracleObservation>,
+    windows: Vec<i64>,
+}
+
+impl LsOracle {
+    pub(crate) fn new(events: &[LsEvent], windows: &[i64]) -> Self {
+        let observations = events
+            .iter()
+            .filter_map(|ev| match ev {
+                LsEvent::OracleObservations(observation) => Some(observation.clone()),
+                _ => None,
+            })
+            .collect();
+
+        LsOracle {
+            observations,
+            windows: windows.to_vec(),
+        }
+    }
+
+    fn latest(&self) -> Vec<&TableLsOracleObservation> {
+        self.observations
+            .iter()
+            .fold(HashMap::new(), |mut list: HashMap<_, _>, item| {
+                list.entry(&item.pool_id)
+                    .and_modify(|m: &mut &TableLsOracleObservation| {
+                        if m.version < item.version {
+                            *m = item;
+                        }
+                    })
+                    .or_insert(item);
+                list
+            })
+            .into_values()
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InsertToDb for LsOracle {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        let latest = self.latest().into_iter().cloned().collect::<Vec<_>>();
+        let count = self.observations.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_oracle_observations_query,
+            self.observations,
+            TableLsOracleObservation::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsOracleObservation added");
+
+        // (pool_id, window_secs, latest timestamp a TWAP over the window can start at)
+        let (pool_ids, (windows, start_timestamps)): (Vec<_>, (Vec<_>, Vec<_>)) = latest
+            .iter()
+            .flat_map(|end| {
+                self.windows.iter().map(|&window_secs| {
+                    (
+                        end.pool_id.clone(),
+                        (window_secs, end.timestamp - window_secs),
+                    )
+                })
+            })
+            .unzip();
+        let starts = sql_query(
+            "SELECT w.window_secs, o.*
+                FROM unnest($1, $2, $3) AS w(pool_id, window_secs, start_timestamp)
+                JOIN LATERAL (
+                    SELECT * FROM ls_oracle_observations lo
+                        WHERE lo.pool_id = w.pool_id AND lo.timestamp <= w.start_timestamp
+                        ORDER BY lo.timestamp DESC, lo.version DESC
+                        LIMIT 1
+                ) o ON true",
+        )
+        .bind::<Array<Varchar>, _>(pool_ids)
+        .bind::<Array<Int8>, _>(windows)
+        .bind::<Array<Int8>, _>(start_timestamps)
+        .load::<WindowStart>(conn)
+        .await?
+        .into_iter()
+        .map(|start| {
+            (
+                (start.observation.pool_id.clone(), start.window_secs),
+                start.observation,
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+        let mut twaps = Vec::new();
+        for end in &latest {
+            for &window_secs in &self.windows {
+                let start = starts.get(&(end.pool_id.clone(), window_secs));
+                twaps.extend(start.and_then(|start| TableLsTwap::new(start, end, window_secs)));
+            }
+        }
+
+        let count = twaps.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_twaps_query,
+            twaps,
+            TableLsTwap::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsTwap added/updated");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_oracle_observations_query(
+    items_to_insert: Vec<TableLsOracleObservation>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_oracle_observations::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_oracle_observations::table)
+            .values(items_to_insert)
+            .on_conflict(id)
+            .do_nothing(),
+        None,
+    )
+}
+
+fn insert_ls_twaps_query(
+    items_to_insert: Vec<TableLsTwap>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_twaps::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_twaps::table)
+            .values(items_to_insert)
+            .on_conflict((pool_id, window_secs))
+            .do_update()
+            .set((
+                twap_x.eq(excluded(twap_x)),
+                twap_y.eq(excluded(twap_y)),
+                start_version.eq(excluded(start_version)),
+                end_version.eq(excluded(end_version)),
+                start_timestamp.eq(excluded(start_timestamp)),
+                end_timestamp.eq(excluded(end_timestamp)),
+            )),
+        Some(" WHERE ls_twaps.end_version < EXCLUDED.end_version "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(timestamp: i64, x_cumulative: BigDecimal) -> TableLsOracleObservation {
+        TableLsOracleObservation {
+            id: format!("ev_{timestamp}"),
+            pool_id: "pool".to_string(),
+            version: timestamp,
+            timestamp,
+            price_x_cumulative: x_cumulative,
+            price_y_cumulative: BigDecimal::from(0),
+        }
+    }
+
+    #[test]
+    fn test_twap_handles_overflow() {
+        // Price of 2 for 10 seconds, wrapping around u128::MAX
+        let start =
+            BigDecimal::from(u128::MAX) - BigDecimal::from(5_u128 << 64) + BigDecimal::from(1);
+        let end = BigDecimal::from(15_u128 << 64);
+
+        let twap = TableLsTwap::new(&observation(100, start), &observation(110, end), 10)
+            .expect("time passed");
+        assert_eq!(twap.twap_x, BigDecimal::from(2));
+        assert_eq!(twap.twap_y, BigDecimal::from(0));
+    }
+
+    #[test]
+    fn test_twap_needs_elapsed_time() {
+        let one = BigDecimal::from(1);
+        assert!(
+            TableLsTwap::new(&observation(100, one.clone()), &observation(100, one), 10).is_none()
+        );
+    }
+}
@@ -0,0 +1,612 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, One, Zero};
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, BoolExpressionMethods, ExpressionMethods, Insertable,
+    QueryDsl, Queryable, Selectable,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, coin_prices},
+    utils::{
+        database::{execute_in_chunks_in_transaction, PgPoolConnection},
+        util::hash_str,
+    },
+};
+
+use super::{
+    db::{InsertToDb, TableLsPoolSnapshot, PRICE_SCALE},
+    events::LsEvent,
+};
+
+const LIQUIDITY_SCALE: i64 = 6;
+const CONFIDENCE_SCALE: i64 = 4;
+// Curve type of the pools with x^3 * y + x * y^3 = k
+const STABLE_CURVE_SUFFIX: &str = "::Stable";
+
+/// USD price of a coin from the Liquidswap pools, at a version that changed it.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = coin_prices)]
+pub struct TableCoinPrice {
+    // hash_str(coin_type), same as coin_infos.coin_type_hash
+    pub coin_type_hash: String,
+    pub coin_type: String,
+    pub version: i64,
+    pub timestamp: i64,
+    pub price_usd: BigDecimal,
+    // USD value of the reserves of the priced side of the pools the price comes from
+    pub liquidity_usd: BigDecimal,
+    // liquidity_usd relative to full_confidence_liquidity_usd, at most 1
+    pub confidence: BigDecimal,
+}
+
+/// A pool with its reserves in whole coins.
+#[derive(Debug, Clone)]
+struct PricePool {
+    x_name: String,
+    y_name: String,
+    stable: bool,
+    x_decimals: i32,
+    y_decimals: i32,
+    x_val: BigDecimal,
+    y_val: BigDecimal,
+    last_version: i64,
+}
+
+fn normalize(value: &BigDecimal, decimals: i32) -> BigDecimal {
+    let (digits, scale) = value.as_bigint_and_exponent();
+    BigDecimal::new(digits, scale + i64::from(decimals))
+}
+
+impl PricePool {
+    fn new(
+        x_name: &str,
+        y_name: &str,
+        curve: &str,
+        (x_decimals, y_decimals): (i32, i32),
+        (x_val, y_val): (&BigDecimal, &BigDecimal),
+        last_version: i64,
+    ) -> Self {
+        PricePool {
+            x_name: x_name.to_string(),
+            y_name: y_name.to_string(),
+            stable: curve.ends_with(STABLE_CURVE_SUFFIX),
+            x_decimals,
+            y_decimals,
+            x_val: normalize(x_val, x_decimals),
+            y_val: normalize(y_val, y_decimals),
+            last_version,
+        }
+    }
+
+    fn set_reserves(&mut self, snapshot: &TableLsPoolSnapshot) {
+        self.x_val = normalize(&snapshot.x_val, self.x_decimals);
+        self.y_val = normalize(&snapshot.y_val, self.y_decimals);
+        self.last_version = snapshot.version;
+    }
+
+    /// Marginal price of a whole X in Y, `None` if a reserve is empty.
+    fn price_x(&self) -> Option<BigDecimal> {
+        if self.x_val <= BigDecimal::zero() || self.y_val <= BigDecimal::zero() {
+            return None;
+        }
+        let (x, y) = (&self.x_val, &self.y_val);
+
+        let price = if self.stable {
+            // -dy/dx of x^3 * y + x * y^3 = k
+            let three = BigDecimal::from(3);
+            (&three * x * x * y + y * y * y) / (x * x * x + &three * x * y * y)
+        } else {
+            y / x
+        };
+        Some(price.with_scale(PRICE_SCALE))
+    }
+}
+
+/// (price_usd, liquidity_usd) of every coin connected to a stablecoin.
+///
+/// Stablecoins are worth 1 USD. The other coins are priced a hop at a time from the coins of the
+/// previous hop, averaging over their pools weighted by the USD value of the priced reserve.
+fn prices(
+    pools: &[&PricePool],
+    stablecoins: &HashSet<String>,
+) -> HashMap<String, (BigDecimal, BigDecimal)> {
+    let mut priced: HashMap<String, (BigDecimal, BigDecimal)> = HashMap::new();
+    for pool in pools {
+        for (name, val) in [(&pool.x_name, &pool.x_val), (&pool.y_name, &pool.y_val)] {
+            if stablecoins.contains(name) {
+                let (_, liquidity) = priced
+                    .entry(name.clone())
+                    .or_insert_with(|| (BigDecimal::one(), BigDecimal::zero()));
+                *liquidity += val;
+            }
+        }
+    }
+    for name in stablecoins {
+        priced
+            .entry(name.clone())
+            .or_insert_with(|| (BigDecimal::one(), BigDecimal::zero()));
+    }
+
+    loop {
+        // coin -> [(price_usd, weight)] from the coins priced so far
+        let mut quotes: HashMap<&String, Vec<(BigDecimal, BigDecimal)>> = HashMap::new();
+        for pool in pools {
+            let price_x = match pool.price_x() {
+                Some(price_x) => price_x,
+                None => continue,
+            };
+            match (priced.get(&pool.x_name), priced.get(&pool.y_name)) {
+                (Some((x_usd, _)), None) => quotes
+                    .entry(&pool.y_name)
+                    .or_default()
+                    .push((x_usd / &price_x, &pool.x_val * x_usd)),
+                (None, Some((y_usd, _))) => quotes
+                    .entry(&pool.x_name)
+                    .or_default()
+                    .push((&price_x * y_usd, &pool.y_val * y_usd)),
+                _ => {},
+            }
+        }
+        if quotes.is_empty() {
+            break;
+        }
+
+        let hop = quotes
+            .into_iter()
+            .filter_map(|(name, quotes)| {
+                let liquidity = quotes
+                    .iter()
+                    .fold(BigDecimal::zero(), |sum, (_, weight)| sum + weight);
+                if liquidity.is_zero() {
+                    return None;
+                }
+                let weighted = quotes
+                    .iter()
+                    .fold(BigDecimal::zero(), |sum, (price, weight)| {
+                        sum + price * weight
+                    });
+                Some((
+                    name.clone(),
+                    ((weighted / &liquidity).with_scale(PRICE_SCALE), liquidity),
+                ))
+            })
+            .collect::<Vec<_>>();
+        if hop.is_empty() {
+            break;
+        }
+        priced.extend(hop);
+    }
+
+    priced
+}
+
+/// Label of the connected component of every pool, pools are connected by a shared coin.
+///
+/// A price only depends on the pools of its component, so a pool change is only priced through
+/// the pools of the same component.
+fn components(pools: &HashMap<String, PricePool>) -> HashMap<String, usize> {
+    let mut by_coin: HashMap<&String, Vec<&String>> = HashMap::new();
+    for (id, pool) in pools {
+        by_coin.entry(&pool.x_name).or_default().push(id);
+        by_coin.entry(&pool.y_name).or_default().push(id);
+    }
+
+    let mut labels = HashMap::new();
+    for (label, start) in pools.keys().enumerate() {
+        if labels.contains_key(start) {
+            continue;
+        }
+        labels.insert(start.clone(), label);
+        let mut stack = vec![start];
+        while let Some(id) = stack.pop() {
+            let pool = &pools[id];
+            for coin in [&pool.x_name, &pool.y_name] {
+                for next in &by_coin[coin] {
+                    if !labels.contains_key(*next) {
+                        labels.insert((*next).clone(), label);
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+    }
+    labels
+}
+
+// (id, x_name, y_name, curve, x_val, y_val, x_decimals, y_decimals, last_version)
+type PoolRow = (
+    String,
+    String,
+    String,
+    String,
+    BigDecimal,
+    BigDecimal,
+    Option<i32>,
+    Option<i32>,
+    i64,
+);
+
+/// USD prices of the coins whose price the batch changed, at each version that changed them.
+///
+/// The pool graph starts from `ls_pools` as stored before the batch, limited to the connected
+/// components of the pools of the batch. It is re-priced at every version with snapshots, through
+/// the components of the pools they changed. Snapshots at or before the version a pool was loaded
+/// with are already part of it, so reprocessing a batch adds no prices. Only pools with known
+/// decimals of both coins are priced.
+pub(crate) struct LsPrices {
+    prices: Vec<TableCoinPrice>,
+}
+
+impl LsPrices {
+    pub(crate) async fn try_new(
+        events: &[LsEvent],
+        stablecoins: &HashSet<String>,
+        full_confidence_liquidity_usd: &BigDecimal,
+        conn: &mut PgPoolConnection<'_>,
+    ) -> Result<Self> {
+        let mut snapshots = events
+            .iter()
+            .filter_map(|ev| match ev {
+                LsEvent::PoolSnapshots(snapshot) => Some(snapshot),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        snapshots.sort_by_key(|snapshot| snapshot.version);
+        if stablecoins.is_empty() || snapshots.is_empty() {
+            return Ok(LsPrices { prices: vec![] });
+        }
+
+        let batch_pools = events
+            .iter()
+            .filter_map(|ev| match ev {
+                LsEvent::Pools(pool) => Some(pool),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // The stored pools connected to the coins of the batch, a hop at a time
+        let mut pools = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut frontier = batch_pools
+            .iter()
+            .flat_map(|pool| [pool.x_name.clone(), pool.y_name.clone()])
+            .collect::<HashSet<_>>();
+        while !frontier.is_empty() {
+            use schema::ls_pools::dsl;
+
+            seen.extend(frontier.iter().cloned());
+            let stored = dsl::ls_pools
+                .filter(
+                    dsl::x_name
+                        .eq_any(&frontier)
+                        .or(dsl::y_name.eq_any(&frontier)),
+                )
+                .filter(dsl::x_decimals.is_not_null())
+                .filter(dsl::y_decimals.is_not_null())
+                .select((
+                    dsl::id,
+                    dsl::x_name,
+                    dsl::y_name,
+                    dsl::curve,
+                    dsl::x_val,
+                    dsl::y_val,
+                    dsl::x_decimals,
+                    dsl::y_decimals,
+                    dsl::last_version,
+                ))
+                .load::<PoolRow>(conn)
+                .await?;
+
+            frontier = HashSet::new();
+            for (id, x_name, y_name, curve, x_val, y_val, x_decimals, y_decimals, last_version) in
+                stored
+            {
+                if let Some(decimals) = x_decimals.zip(y_decimals) {
+                    for name in [&x_name, &y_name] {
+                        if !seen.contains(name) {
+                            frontier.insert(name.clone());
+                        }
+                    }
+                    let pool = PricePool::new(
+                        &x_name,
+                        &y_name,
+                        &curve,
+                        decimals,
+                        (&x_val, &y_val),
+                        last_version,
+                    );
+                    pools.insert(id, pool);
+                }
+            }
+        }
+        // Pools created in the batch start empty, their snapshots fill them
+        let zero = BigDecimal::zero();
+        for pool in batch_pools {
+            if pools.contains_key(&pool.id) {
+                continue;
+            }
+            if let Some(decimals) = pool.x_decimals.zip(pool.y_decimals) {
+                let price_pool = PricePool::new(
+                    &pool.x_name,
+                    &pool.y_name,
+                    &pool.curve,
+                    decimals,
+                    (&zero, &zero),
+                    -1,
+                );
+                pools.insert(pool.id.clone(), price_pool);
+            }
+        }
+
+        Ok(LsPrices {
+            prices: Self::build(
+                pools,
+                &snapshots,
+                stablecoins,
+                full_confidence_liquidity_usd,
+            ),
+        })
+    }
+
+    /// `snapshots` ordered by version
+    fn build(
+        mut pools: HashMap<String, PricePool>,
+        snapshots: &[&TableLsPoolSnapshot],
+        stablecoins: &HashSet<String>,
+        full_confidence_liquidity_usd: &BigDecimal,
+    ) -> Vec<TableCoinPrice> {
+        let components = components(&pools);
+        let mut current = prices(&pools.values().collect::<Vec<_>>(), stablecoins);
+        let mut result = Vec::new();
+
+        let mut rest = snapshots;
+        while let Some(first) = rest.first() {
+            let (version, timestamp) = (first.version, first.timestamp);
+            let count = rest
+                .iter()
+                .take_while(|snapshot| snapshot.version == version)
+                .count();
+            let (at_version, next) = rest.split_at(count);
+            rest = next;
+
+            let mut changed = HashSet::new();
+            for snapshot in at_version {
+                match pools.get_mut(&snapshot.pool_id) {
+                    Some(pool) if pool.last_version < snapshot.version => {
+                        pool.set_reserves(snapshot);
+                        changed.insert(components[&snapshot.pool_id]);
+                    },
+                    _ => {},
+                }
+            }
+            if changed.is_empty() {
+                continue;
+            }
+
+            let repriced = pools
+                .iter()
+                .filter(|(id, _)| changed.contains(&components[*id]))
+                .map(|(_, pool)| pool)
+                .collect::<Vec<_>>();
+            let coins = repriced
+                .iter()
+                .flat_map(|pool| [&pool.x_name, &pool.y_name])
+                .collect::<HashSet<_>>();
+            let mut after = prices(&repriced, stablecoins);
+
+            // A coin that lost its price gets a row again once it has one
+            current
+                .retain(|coin_type, _| !coins.contains(coin_type) || after.contains_key(coin_type));
+            for coin_type in coins {
+                let (price_usd, liquidity_usd) = match after.remove(coin_type) {
+                    Some(price) => price,
+                    None => continue,
+                };
+                if current.get(coin_type).map(|(price, _)| price) == Some(&price_usd) {
+                    continue;
+                }
+
+                let confidence = if full_confidence_liquidity_usd.is_zero() {
+                    BigDecimal::one()
+                } else {
+                    (&liquidity_usd / full_confidence_liquidity_usd).min(BigDecimal::one())
+                };
+                result.push(TableCoinPrice {
+                    coin_type_hash: hash_str(coin_type),
+                    coin_type: coin_type.clone(),
+                    version,
+                    timestamp,
+                    price_usd: price_usd.clone(),
+                    liquidity_usd: liquidity_usd.with_scale(LIQUIDITY_SCALE),
+                    confidence: confidence.with_scale(CONFIDENCE_SCALE),
+                });
+                current.insert(coin_type.clone(), (price_usd, liquidity_usd));
+            }
+        }
+
+        result
+    }
+}
+
+#[async_trait]
+impl InsertToDb for LsPrices {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        let count = self.prices.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_coin_prices_query,
+            self.prices,
+            TableCoinPrice::field_count(),
+        )
+        .await?;
+        info!("{count} TableCoinPrice added");
+
+        Ok(())
+    }
+}
+
+fn insert_coin_prices_query(
+    items_to_insert: Vec<TableCoinPrice>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::coin_prices::dsl::*;
+
+    (
+        diesel::insert_into(schema::coin_prices::table)
+            .values(items_to_insert)
+            .on_conflict((coin_type_hash, version))
+            .do_nothing(),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn pool(x_name: &str, y_name: &str, curve: &str, (x_val, y_val): (i64, i64)) -> PricePool {
+        PricePool::new(
+            x_name,
+            y_name,
+            curve,
+            (0, 0),
+            (&x_val.into(), &y_val.into()),
+            0,
+        )
+    }
+
+    fn snapshot(pool_id: &str, version: i64, (x_val, y_val): (i64, i64)) -> TableLsPoolSnapshot {
+        TableLsPoolSnapshot {
+            pool_id: pool_id.to_string(),
+            version,
+            timestamp: version,
+            x_val: x_val.into(),
+            y_val: y_val.into(),
+            fee: 30,
+            dao_fee: 33,
+            lp_supply: None,
+        }
+    }
+
+    fn stablecoins() -> HashSet<String> {
+        HashSet::from(["USDC".to_string(), "USDT".to_string()])
+    }
+
+    #[test]
+    fn test_stable_curve_price() {
+        let balanced = pool("USDT", "USDC", "0x1::curves::Stable", (1000, 1000));
+        assert_eq!(balanced.price_x(), Some(1.into()));
+
+        // A stable pool stays close to 1 where an uncorrelated one would not
+        let skewed = pool("USDT", "USDC", "0x1::curves::Stable", (1000, 1100));
+        let price = skewed.price_x().unwrap();
+        assert!(price > 1.into() && price < BigDecimal::from(11) / BigDecimal::from(10));
+        let uncorrelated = pool("USDT", "USDC", "0x1::curves::Uncorrelated", (1000, 1100));
+        assert!(uncorrelated.price_x().unwrap() > price);
+    }
+
+    #[test]
+    fn test_prices_are_liquidity_weighted() {
+        let pools = HashMap::from([
+            // APT at 10 USDC with 1000 USDC, at 12 USDT with 3000 USDT
+            (
+                "a".to_string(),
+                pool("APT", "USDC", "Uncorrelated", (100, 1000)),
+            ),
+            (
+                "b".to_string(),
+                pool("APT", "USDT", "Uncorrelated", (250, 3000)),
+            ),
+            // MOJO at 0.5 APT
+            (
+                "c".to_string(),
+                pool("MOJO", "APT", "Uncorrelated", (200, 100)),
+            ),
+            ("d".to_string(), pool("X", "Y", "Uncorrelated", (1, 1))),
+        ]);
+
+        let result = prices(&pools.values().collect::<Vec<_>>(), &stablecoins());
+        let (apt_price, apt_liquidity) = &result["APT"];
+        assert_eq!(apt_liquidity, &4000.into());
+        // (10 * 1000 + 12 * 3000) / 4000
+        assert_eq!(apt_price, &BigDecimal::from_str("11.5").unwrap());
+        assert_eq!(result["MOJO"].0, BigDecimal::from_str("5.75").unwrap());
+        assert!(!result.contains_key("X"));
+    }
+
+    #[test]
+    fn test_prices_at_each_version() {
+        let pools = HashMap::from([(
+            "a".to_string(),
+            pool("APT", "USDC", "Uncorrelated", (100, 1000)),
+        )]);
+        let snapshots = [
+            snapshot("a", 1, (200, 1000)),
+            snapshot("a", 2, (400, 1000)),
+            snapshot("b", 3, (1, 1)),
+        ];
+        let snapshots = snapshots.iter().collect::<Vec<_>>();
+
+        let result = LsPrices::build(pools, &snapshots, &stablecoins(), &1000.into());
+        // Every move of the batch, unknown pools are skipped
+        let prices = result
+            .iter()
+            .map(|price| (price.coin_type.as_str(), price.version, &price.price_usd))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            prices,
+            [
+                ("APT", 1, &BigDecimal::from(5)),
+                ("APT", 2, &BigDecimal::from_str("2.5").unwrap())
+            ]
+        );
+        assert_eq!(result[1].confidence, 1.into());
+    }
+
+    #[test]
+    fn test_only_the_changed_component_is_repriced() {
+        let pools = HashMap::from([
+            (
+                "a".to_string(),
+                pool("APT", "USDC", "Uncorrelated", (100, 1000)),
+            ),
+            // Its own component, unpriced until its coin has a price
+            ("b".to_string(), pool("MOJO", "X", "Uncorrelated", (1, 1))),
+        ]);
+        let components = components(&pools);
+        assert_ne!(components["a"], components["b"]);
+
+        let snapshots = [snapshot("b", 1, (2, 1)), snapshot("a", 2, (200, 1000))];
+        let snapshots = snapshots.iter().collect::<Vec<_>>();
+        let result = LsPrices::build(pools, &snapshots, &stablecoins(), &1000.into());
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            (result[0].coin_type.as_str(), result[0].version),
+            ("APT", 2)
+        );
+    }
+
+    #[test]
+    fn test_unchanged_prices_are_not_stored() {
+        let pools = HashMap::from([(
+            "a".to_string(),
+            pool("APT", "USDC", "Uncorrelated", (100, 1000)),
+        )]);
+        // More liquidity at the same price
+        let snapshots = [snapshot("a", 1, (200, 2000))];
+        let snapshots = snapshots.iter().collect::<Vec<_>>();
+
+        assert!(LsPrices::build(pools, &snapshots, &stablecoins(), &1000.into()).is_empty());
+    }
+}
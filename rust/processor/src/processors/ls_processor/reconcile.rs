@@ -0,0 +1,72 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::Result;
+use bigdecimal::BigDecimal;
+use diesel::{
+    sql_query,
+    sql_types::{Int8, Numeric, Varchar},
+    QueryableByName,
+};
+use diesel_async::RunQueryDsl;
+
+use crate::utils::database::PgPoolConnection;
+
+/// A pool whose reserves differ from the sum of the deltas of its events.
+#[derive(Debug, QueryableByName)]
+pub struct PoolDivergence {
+    #[diesel(sql_type = Varchar)]
+    pub pool_id: String,
+    #[diesel(sql_type = Int8)]
+    pub last_version: i64,
+    // coin_x_reserve of the LiquidityPool resource at last_version
+    #[diesel(sql_type = Numeric)]
+    pub x_val: BigDecimal,
+    #[diesel(sql_type = Numeric)]
+    pub y_val: BigDecimal,
+    // Sum of ls_events.x_val up to last_version
+    #[diesel(sql_type = Numeric)]
+    pub events_x_val: BigDecimal,
+    #[diesel(sql_type = Numeric)]
+    pub events_y_val: BigDecimal,
+}
+
+impl Display for PoolDivergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pool {} at version {}: x_val {} != {} (diff {}), y_val {} != {} (diff {})",
+            self.pool_id,
+            self.last_version,
+            self.x_val,
+            self.events_x_val,
+            &self.x_val - &self.events_x_val,
+            self.y_val,
+            self.events_y_val,
+            &self.y_val - &self.events_y_val,
+        )
+    }
+}
+
+/// Replays the deltas of `ls_events` of every pool up to the version of its reserves in
+/// `ls_pools` and returns the pools where they do not add up.
+///
+/// A divergence means events are missing from `ls_events` (e.g. the processor started after the
+/// pool was created) or an event type changes the reserves without being accounted for.
+pub async fn reconcile(conn: &mut PgPoolConnection<'_>) -> Result<Vec<PoolDivergence>> {
+    let result = sql_query(
+        "SELECT lp.id AS pool_id, lp.last_version, lp.x_val, lp.y_val,
+                COALESCE(le.x_val, 0) AS events_x_val, COALESCE(le.y_val, 0) AS events_y_val
+            FROM ls_pools lp
+            LEFT JOIN LATERAL (
+                SELECT sum(e.x_val) AS x_val, sum(e.y_val) AS y_val
+                    FROM ls_events e
+                    WHERE e.pool_id = lp.id AND e.version <= lp.last_version
+            ) le ON true
+            WHERE lp.x_val != COALESCE(le.x_val, 0) OR lp.y_val != COALESCE(le.y_val, 0)
+            ORDER BY lp.id",
+    )
+    .load::<PoolDivergence>(conn)
+    .await?;
+
+    Ok(result)
+}
@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, Zero};
+use diesel::{
+    pg::Pg, query_builder::QueryFragment, upsert::excluded, ExpressionMethods, Insertable,
+    QueryDsl, Queryable, Selectable, SelectableHelper,
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_trader_stats},
+    utils::database::{execute_in_chunks_in_transaction, PgPoolConnection},
+};
+
+use super::{
+    db::{InsertToDb, PRICE_SCALE},
+    events::LsEvent,
+};
+
+/// A SwapEvent by the sender of its transaction.
+#[derive(Debug, Clone)]
+pub struct LsTraderSwap {
+    pub account: String,
+    pub pool_id: String,
+    pub version: i64,
+    pub x_in: BigDecimal,
+    pub x_out: BigDecimal,
+    pub y_in: BigDecimal,
+    pub y_out: BigDecimal,
+}
+
+/// Swaps of an account in a pool, with X as the traded coin and Y as the quote.
+///
+/// Bought X opens a position at the Y paid for it. Sold X closes the position at its average
+/// entry price and realizes the difference to the Y received. X sold beyond the position came
+/// from elsewhere, e.g. another pool, and has no entry price, so it realizes nothing.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_trader_stats)]
+pub struct TableLsTraderStats {
+    pub account: String,
+    pub pool_id: String,
+    // Sum of x_out
+    pub x_bought: BigDecimal,
+    // Sum of x_in
+    pub x_sold: BigDecimal,
+    // Sum of y_in
+    pub y_spent: BigDecimal,
+    // Sum of y_out
+    pub y_received: BigDecimal,
+    // X bought and not sold yet
+    pub position_x: BigDecimal,
+    // Y paid for position_x
+    pub cost_y: BigDecimal,
+    // cost_y / position_x, None without a position
+    pub avg_entry_price: Option<BigDecimal>,
+    // In Y
+    pub realized_pnl: BigDecimal,
+    pub trades: i64,
+    pub last_version: i64,
+}
+
+type StatsKey = (String, String);
+
+impl TableLsTraderStats {
+    fn new(account: &str, pool_id: &str) -> Self {
+        TableLsTraderStats {
+            account: account.to_string(),
+            pool_id: pool_id.to_string(),
+            x_bought: BigDecimal::zero(),
+            x_sold: BigDecimal::zero(),
+            y_spent: BigDecimal::zero(),
+            y_received: BigDecimal::zero(),
+            position_x: BigDecimal::zero(),
+            cost_y: BigDecimal::zero(),
+            avg_entry_price: None,
+            realized_pnl: BigDecimal::zero(),
+            trades: 0,
+            last_version: 0,
+        }
+    }
+
+    fn key(&self) -> StatsKey {
+        (self.account.clone(), self.pool_id.clone())
+    }
+
+    fn apply(&mut self, swap: &LsTraderSwap) {
+        self.x_bought += &swap.x_out;
+        self.x_sold += &swap.x_in;
+        self.y_spent += &swap.y_in;
+        self.y_received += &swap.y_out;
+
+        // Buy
+        self.position_x += &swap.x_out;
+        self.cost_y += &swap.y_in;
+
+        // Sell, the part of it covered by the position
+        let closed_x = if swap.x_in < self.position_x {
+            swap.x_in.clone()
+        } else {
+            self.position_x.clone()
+        };
+        if !closed_x.is_zero() {
+            let closed_cost = (&self.cost_y * &closed_x / &self.position_x).with_scale(0);
+            let closed_proceeds = (&swap.y_out * &closed_x / &swap.x_in).with_scale(0);
+            self.realized_pnl += closed_proceeds - &closed_cost;
+            self.cost_y -= closed_cost;
+            self.position_x -= closed_x;
+        }
+
+        self.avg_entry_price = (!self.position_x.is_zero())
+            .then(|| (&self.cost_y / &self.position_x).with_scale(PRICE_SCALE));
+        self.trades += 1;
+        self.last_version = swap.version;
+    }
+}
+
+/// Swaps of a batch and the trader stats they update.
+///
+/// Swaps at or before the `last_version` the stats were loaded with are already part of them, so
+/// reprocessing a batch does not count them twice.
+pub(crate) struct LsTraderStats {
+    swaps: Vec<LsTraderSwap>,
+}
+
+impl LsTraderStats {
+    pub(crate) fn new(events: &[LsEvent]) -> Self {
+        let mut swaps = events
+            .iter()
+            .filter_map(|ev| match ev {
+                LsEvent::TraderSwaps(swap) => Some(swap.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // Stable, swaps of a transaction stay in event order
+        swaps.sort_by_key(|swap| swap.version);
+
+        LsTraderStats { swaps }
+    }
+
+    fn stats(&self, existing: Vec<TableLsTraderStats>) -> Vec<TableLsTraderStats> {
+        let mut stats: HashMap<StatsKey, (i64, TableLsTraderStats)> = existing
+            .into_iter()
+            .map(|stats| (stats.key(), (stats.last_version, stats)))
+            .collect();
+        let mut updated = HashSet::new();
+
+        for swap in &self.swaps {
+            let key = (swap.account.clone(), swap.pool_id.clone());
+            let (loaded_version, trader) = stats
+                .entry(key.clone())
+                .or_insert_with(|| (0, TableLsTraderStats::new(&swap.account, &swap.pool_id)));
+            if swap.version <= *loaded_version {
+                continue;
+            }
+            trader.apply(swap);
+            updated.insert(key);
+        }
+
+        stats
+            .into_iter()
+            .filter_map(|(key, (_, trader))| updated.contains(&key).then_some(trader))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InsertToDb for LsTraderStats {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        if self.swaps.is_empty() {
+            return Ok(());
+        }
+
+        let accounts = self
+            .swaps
+            .iter()
+            .map(|swap| swap.account.clone())
+            .collect::<HashSet<_>>();
+        let pool_ids = self
+            .swaps
+            .iter()
+            .map(|swap| swap.pool_id.clone())
+            .collect::<HashSet<_>>();
+
+        // Stats of the traders, whose last_version skips swaps already counted. Stats in pools
+        // other traders used may come along, they are not written back.
+        let existing = schema::ls_trader_stats::table
+            .filter(schema::ls_trader_stats::account.eq_any(accounts))
+            .filter(schema::ls_trader_stats::pool_id.eq_any(pool_ids))
+            .select(TableLsTraderStats::as_select())
+            .load::<TableLsTraderStats>(conn)
+            .await?;
+
+        let stats = self.stats(existing);
+        let count = stats.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_trader_stats_query,
+            stats,
+            TableLsTraderStats::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsTraderStats added/updated");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_trader_stats_query(
+    items_to_insert: Vec<TableLsTraderStats>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_trader_stats::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_trader_stats::table)
+            .values(items_to_insert)
+            .on_conflict((account, pool_id))
+            .do_update()
+            .set((
+                x_bought.eq(excluded(x_bought)),
+                x_sold.eq(excluded(x_sold)),
+                y_spent.eq(excluded(y_spent)),
+                y_received.eq(excluded(y_received)),
+                position_x.eq(excluded(position_x)),
+                cost_y.eq(excluded(cost_y)),
+                avg_entry_price.eq(excluded(avg_entry_price)),
+                realized_pnl.eq(excluded(realized_pnl)),
+                trades.eq(excluded(trades)),
+                last_version.eq(excluded(last_version)),
+            )),
+        Some(" WHERE ls_trader_stats.last_version < EXCLUDED.last_version "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::ls_processor::tests::replay_overlapping_batch;
+
+    fn swap(version: i64, (x_in, x_out, y_in, y_out): (i64, i64, i64, i64)) -> LsTraderSwap {
+        LsTraderSwap {
+            account: "0x1".to_string(),
+            pool_id: "pool".to_string(),
+            version,
+            x_in: x_in.into(),
+            x_out: x_out.into(),
+            y_in: y_in.into(),
+            y_out: y_out.into(),
+        }
+    }
+
+    #[test]
+    fn test_stats_realize_pnl_at_avg_entry_price() {
+        let trader_stats = LsTraderStats {
+            swaps: vec![
+                // Buy 100 X at 2 and 100 X at 4
+                swap(1, (0, 100, 200, 0)),
+                swap(2, (0, 100, 400, 0)),
+                // Sell 50 X at 5, entry price 3
+                swap(3, (50, 0, 0, 250)),
+            ],
+        };
+
+        let stats = trader_stats.stats(vec![]);
+        assert_eq!(stats.len(), 1);
+        let trader = &stats[0];
+        assert_eq!(trader.x_bought, 200.into());
+        assert_eq!(trader.x_sold, 50.into());
+        assert_eq!(trader.position_x, 150.into());
+        assert_eq!(trader.cost_y, 450.into());
+        assert_eq!(trader.avg_entry_price, Some(BigDecimal::from(3)));
+        assert_eq!(trader.realized_pnl, 100.into());
+        assert_eq!(trader.trades, 3);
+
+        // Reprocessing the batch leaves the stats as they are
+        assert!(trader_stats.stats(stats).is_empty());
+    }
+
+    #[test]
+    fn test_sell_beyond_position() {
+        let trader_stats = LsTraderStats {
+            swaps: vec![swap(1, (0, 10, 20, 0)), swap(2, (30, 0, 0, 90))],
+        };
+
+        let trader = &trader_stats.stats(vec![])[0];
+        // Only the 10 X bought here realize, at 3 each against an entry price of 2
+        assert_eq!(trader.realized_pnl, 10.into());
+        assert_eq!(trader.position_x, 0.into());
+        assert_eq!(trader.avg_entry_price, None);
+    }
+
+    #[test]
+    fn test_batch_after_a_later_one() {
+        let stats = replay_overlapping_batch(
+            LsTraderStats {
+                swaps: vec![swap(1, (0, 100, 200, 0)), swap(2, (0, 100, 400, 0))],
+            },
+            LsTraderStats {
+                swaps: vec![swap(2, (0, 100, 400, 0)), swap(3, (50, 0, 0, 250))],
+            },
+            |trader_stats, existing| trader_stats.stats(existing),
+        );
+        assert_eq!(stats.len(), 1);
+        let trader = &stats[0];
+        assert_eq!(trader.x_bought, 200.into());
+        assert_eq!(trader.position_x, 150.into());
+        assert_eq!(trader.realized_pnl, 100.into());
+        assert_eq!(trader.trades, 3);
+        assert_eq!(trader.last_version, 3);
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use aptos_protos::transaction::v1::{transaction::TxnData, Transaction};
+use bigdecimal::{BigDecimal, Zero};
+use diesel::{pg::Pg, query_builder::QueryFragment, Insertable, Queryable, Selectable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use tonic::async_trait;
+use tracing::info;
+
+use crate::{
+    schema::{self, ls_trades},
+    utils::{
+        database::{execute_in_chunks_in_transaction, PgPoolConnection},
+        util::{get_clean_payload, get_entry_function_from_user_request},
+    },
+};
+
+use super::{
+    coins::LsCoins,
+    db::{InsertToDb, PRICE_SCALE},
+    events::LsEvent,
+};
+
+/// One SwapEvent of a trade.
+#[derive(Debug, Clone, Serialize)]
+pub struct LsTradeHop {
+    #[serde(skip)]
+    pub version: i64,
+    #[serde(skip)]
+    pub tx_hash: String,
+    #[serde(skip)]
+    pub sender: String,
+    #[serde(skip)]
+    pub timestamp: i64,
+    pub pool_id: String,
+    pub coin_in: String,
+    pub amount_in: BigDecimal,
+    pub coin_out: String,
+    pub amount_out: BigDecimal,
+}
+
+impl LsTradeHop {
+    /// The direction of the swap follows from its amounts, coin X is sold if x_in is set.
+    pub(crate) fn new(
+        (version, tx_hash, sender, timestamp): (i64, &str, &str, i64),
+        pool_id: String,
+        (x_name, y_name): (&str, &str),
+        (x_in, x_out, y_in, y_out): (i128, i128, i128, i128),
+    ) -> Self {
+        let (coin_in, amount_in, coin_out, amount_out) = if x_in > 0 {
+            (x_name, x_in, y_name, y_out)
+        } else {
+            (y_name, y_in, x_name, x_out)
+        };
+
+        LsTradeHop {
+            version,
+            tx_hash: tx_hash.to_string(),
+            sender: sender.to_string(),
+            timestamp,
+            pool_id,
+            coin_in: coin_in.to_string(),
+            amount_in: amount_in.into(),
+            coin_out: coin_out.to_string(),
+            amount_out: amount_out.into(),
+        }
+    }
+}
+
+/// Swaps of a user transaction that chain into a single trade.
+///
+/// The route lists the swaps in event order, each one selling what the one before bought. The
+/// trade sells the input of the first swap and buys the output of the last one, e.g.
+/// X -> Y -> Z for a router swap over two pools.
+#[derive(Selectable, Queryable, Insertable, FieldCount, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ls_trades)]
+pub struct TableLsTrade {
+    pub version: i64,
+    // Index of the trade in the transaction
+    pub trade_index: i32,
+    pub tx_hash: String,
+    pub sender: String,
+    pub timestamp: i64,
+    pub hops: i32,
+    pub route: serde_json::Value,
+    pub coin_in: String,
+    pub amount_in: BigDecimal,
+    pub coin_out: String,
+    pub amount_out: BigDecimal,
+    // amount_out / amount_in
+    pub price: Option<BigDecimal>,
+    pub amount_in_normalized: Option<BigDecimal>,
+    pub amount_out_normalized: Option<BigDecimal>,
+    pub price_normalized: Option<BigDecimal>,
+    pub entry_function: Option<String>,
+    // function, type_arguments and arguments (e.g. the min out amount) of the entry function
+    pub payload: Option<serde_json::Value>,
+}
+
+fn price(amount_in: &BigDecimal, amount_out: &BigDecimal) -> Option<BigDecimal> {
+    (!amount_in.is_zero()).then(|| (amount_out / amount_in).with_scale(PRICE_SCALE))
+}
+
+impl TableLsTrade {
+    fn new(
+        trade_index: i32,
+        hops: Vec<LsTradeHop>,
+        tx: Option<&Transaction>,
+        coins: &LsCoins,
+    ) -> Result<Self> {
+        let first = hops.first().context("trade without swaps")?;
+        let last = hops.last().context("trade without swaps")?;
+
+        let user_request = tx
+            .and_then(|tx| match tx.txn_data.as_ref()? {
+                TxnData::User(user_tx) => Some(user_tx),
+                _ => None,
+            })
+            .and_then(|user_tx| user_tx.request.as_ref());
+        let entry_function = user_request.and_then(get_entry_function_from_user_request);
+        let payload = user_request
+            .and_then(|request| request.payload.as_ref())
+            .and_then(|payload| get_clean_payload(payload, first.version));
+
+        let amount_in_normalized = coins
+            .get(&first.coin_in)
+            .map(|coin| coin.normalize(&first.amount_in));
+        let amount_out_normalized = coins
+            .get(&last.coin_out)
+            .map(|coin| coin.normalize(&last.amount_out));
+        let price_normalized = match (&amount_in_normalized, &amount_out_normalized) {
+            (Some(amount_in), Some(amount_out)) => price(amount_in, amount_out),
+            _ => None,
+        };
+
+        Ok(TableLsTrade {
+            version: first.version,
+            trade_index,
+            tx_hash: first.tx_hash.clone(),
+            sender: first.sender.clone(),
+            timestamp: first.timestamp,
+            hops: hops.len().try_into().context("number of hops")?,
+            route: serde_json::to_value(&hops)?,
+            coin_in: first.coin_in.clone(),
+            amount_in: first.amount_in.clone(),
+            coin_out: last.coin_out.clone(),
+            amount_out: last.amount_out.clone(),
+            price: price(&first.amount_in, &last.amount_out),
+            amount_in_normalized,
+            amount_out_normalized,
+            price_normalized,
+            entry_function,
+            payload,
+        })
+    }
+}
+
+pub(crate) struct LsTrades {
+    trades: Vec<TableLsTrade>,
+}
+
+impl LsTrades {
+    pub(crate) fn try_new(
+        events: &[LsEvent],
+        transactions: &[Transaction],
+        coins: &LsCoins,
+    ) -> Result<Self> {
+        let mut hops_by_version: BTreeMap<i64, Vec<LsTradeHop>> = BTreeMap::new();
+        for ev in events {
+            if let LsEvent::TradeHops(hop) = ev {
+                hops_by_version
+                    .entry(hop.version)
+                    .or_default()
+                    .push(hop.clone());
+            }
+        }
+
+        let mut trades = vec![];
+        for (version, hops) in hops_by_version {
+            let tx = transactions
+                .binary_search_by_key(&version, |tx| tx.version as i64)
+                .ok()
+                .map(|index| &transactions[index]);
+            for (trade_index, hops) in split_routes(hops).into_iter().enumerate() {
+                let trade_index = trade_index.try_into().context("trade index")?;
+                trades.push(TableLsTrade::new(trade_index, hops, tx, coins)?);
+            }
+        }
+
+        Ok(LsTrades { trades })
+    }
+}
+
+/// Splits the swaps of a transaction where a swap does not sell the coin the one before bought,
+/// e.g. for two unrelated swaps of a script.
+fn split_routes(hops: Vec<LsTradeHop>) -> Vec<Vec<LsTradeHop>> {
+    let mut routes: Vec<Vec<LsTradeHop>> = vec![];
+    for hop in hops {
+        let route = routes.last_mut().filter(|route| {
+            route
+                .last()
+                .map_or(false, |last| last.coin_out == hop.coin_in)
+        });
+        match route {
+            Some(route) => route.push(hop),
+            None => routes.push(vec![hop]),
+        }
+    }
+    routes
+}
+
+#[async_trait]
+impl InsertToDb for LsTrades {
+    async fn insert_to_db(self, conn: &mut PgPoolConnection<'_>) -> Result<()> {
+        let count = self.trades.len();
+
+        execute_in_chunks_in_transaction(
+            conn,
+            insert_ls_trades_query,
+            self.trades,
+            TableLsTrade::field_count(),
+        )
+        .await?;
+        info!("{count} TableLsTrade added");
+
+        Ok(())
+    }
+}
+
+fn insert_ls_trades_query(
+    items_to_insert: Vec<TableLsTrade>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::ls_trades::dsl::*;
+
+    (
+        diesel::insert_into(schema::ls_trades::table)
+            .values(items_to_insert)
+            .on_conflict((version, trade_index))
+            .do_nothing(),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trade_over_two_pools() {
+        let info = (10, "hash", "0x1", 100);
+        let hops = vec![
+            // Sell 100 X for 50 Y
+            LsTradeHop::new(info, "xy".to_string(), ("X", "Y"), (100, 0, 0, 50)),
+            // Sell 50 Y for 200 Z, Z is X of the second pool
+            LsTradeHop::new(info, "zy".to_string(), ("Z", "Y"), (0, 200, 50, 0)),
+        ];
+        let coins = LsCoins::try_from_txs(&[]).unwrap();
+
+        let routes = split_routes(hops);
+        assert_eq!(routes.len(), 1);
+        let trade = TableLsTrade::new(0, routes[0].clone(), None, &coins).unwrap();
+        assert_eq!(trade.hops, 2);
+        assert_eq!(
+            (trade.coin_in.as_str(), trade.coin_out.as_str()),
+            ("X", "Z")
+        );
+        assert_eq!(trade.amount_in, 100.into());
+        assert_eq!(trade.amount_out, 200.into());
+        assert_eq!(trade.price, Some(2.into()));
+        assert_eq!(trade.route[1]["pool_id"], "zy");
+        assert_eq!(trade.route[1]["coin_in"], "Y");
+        assert!(trade.entry_function.is_none());
+    }
+
+    #[test]
+    fn test_swaps_that_do_not_chain() {
+        let info = (10, "hash", "0x1", 100);
+        let hops = vec![
+            // Sell 100 X for 50 Y
+            LsTradeHop::new(info, "xy".to_string(), ("X", "Y"), (100, 0, 0, 50)),
+            // Sell 30 Z for 60 Y, not the Y bought before
+            LsTradeHop::new(info, "zy".to_string(), ("Z", "Y"), (30, 0, 0, 60)),
+        ];
+
+        let routes = split_routes(hops);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0][0].pool_id, "xy");
+        assert_eq!(routes[1][0].pool_id, "zy");
+    }
+}
@@ -6,7 +6,7 @@
 
 use aptos_protos::transaction::v1::Transaction as ProtoTransaction;
 use async_trait::async_trait;
-use diesel::{upsert::excluded, ExpressionMethods};
+use diesel::{pg::Pg, query_builder::QueryFragment, upsert::excluded, ExpressionMethods};
 use enum_dispatch::enum_dispatch;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
@@ -15,6 +15,7 @@ pub mod account_transactions_processor;
 pub mod ans_processor;
 pub mod coin_processor;
 pub mod default_processor;
+pub mod dex_processor;
 pub mod events_processor;
 pub mod fungible_asset_processor;
 pub mod ls_processor;
@@ -31,6 +32,7 @@ use self::{
     ans_processor::{AnsProcessor, AnsProcessorConfig},
     coin_processor::CoinProcessor,
     default_processor::DefaultProcessor,
+    dex_processor::{DexProcessor, DexProcessorConfig},
     events_processor::EventsProcessor,
     fungible_asset_processor::FungibleAssetProcessor,
     ls_processor::LsProcessor,
@@ -48,7 +50,7 @@ use crate::{
     schema::processor_status,
     utils::{
         counters::{GOT_CONNECTION_COUNT, UNABLE_TO_GET_CONNECTION_COUNT},
-        database::{execute_with_better_error, PgDbPool, PgPoolConnection},
+        database::{execute_with_better_error, BatchWriter, PgDbPool, PgPoolConnection},
         util::parse_timestamp,
     },
 };
@@ -73,7 +75,7 @@ pub trait ProcessorTrait: Send + Sync + Debug {
     /// Process all transactions including writing to the database
     async fn process_transactions(
         &self,
-        transactions: Vec<ProtoTransaction>,
+        transactions: &[ProtoTransaction],
         start_version: u64,
         end_version: u64,
         db_chain_id: Option<u64>,
@@ -122,30 +124,57 @@ pub trait ProcessorTrait: Send + Sync + Debug {
         version: u64,
         last_transaction_timestamp: Option<aptos_protos::util::timestamp::Timestamp>,
     ) -> anyhow::Result<()> {
-        let timestamp = last_transaction_timestamp.map(|t| parse_timestamp(&t, version as i64));
-        let status = ProcessorStatus {
-            processor: self.name().to_string(),
-            last_success_version: version as i64,
-            last_transaction_timestamp: timestamp,
-        };
-        execute_with_better_error(
-            self.get_pool(),
-            diesel::insert_into(processor_status::table)
-                .values(&status)
-                .on_conflict(processor_status::processor)
-                .do_update()
-                .set((
-                    processor_status::last_success_version
-                        .eq(excluded(processor_status::last_success_version)),
-                    processor_status::last_updated.eq(excluded(processor_status::last_updated)),
-                    processor_status::last_transaction_timestamp
-                        .eq(excluded(processor_status::last_transaction_timestamp)),
-                )),
-            Some(" WHERE processor_status.last_success_version <= EXCLUDED.last_success_version "),
-        )
-        .await?;
+        let (query, additional_where_clause) =
+            upsert_processor_status_query(self.name(), version, last_transaction_timestamp);
+        execute_with_better_error(self.get_pool(), query, additional_where_clause).await?;
         Ok(())
     }
+
+    /// Commits a batch that was written with `writer`. If the writer is set up for it, the
+    /// processor status is moved to `version` in the same transaction.
+    async fn commit_batch(
+        &self,
+        mut writer: BatchWriter,
+        version: u64,
+        last_transaction_timestamp: Option<aptos_protos::util::timestamp::Timestamp>,
+    ) -> diesel::QueryResult<()> {
+        if writer.writes_processor_status() {
+            let (query, additional_where_clause) =
+                upsert_processor_status_query(self.name(), version, last_transaction_timestamp);
+            writer.execute(query, additional_where_clause).await?;
+        }
+        writer.commit().await
+    }
+}
+
+fn upsert_processor_status_query(
+    processor_name: &'static str,
+    version: u64,
+    last_transaction_timestamp: Option<aptos_protos::util::timestamp::Timestamp>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    let timestamp = last_transaction_timestamp.map(|t| parse_timestamp(&t, version as i64));
+    let status = ProcessorStatus {
+        processor: processor_name.to_string(),
+        last_success_version: version as i64,
+        last_transaction_timestamp: timestamp,
+    };
+    (
+        diesel::insert_into(processor_status::table)
+            .values(status)
+            .on_conflict(processor_status::processor)
+            .do_update()
+            .set((
+                processor_status::last_success_version
+                    .eq(excluded(processor_status::last_success_version)),
+                processor_status::last_updated.eq(excluded(processor_status::last_updated)),
+                processor_status::last_transaction_timestamp
+                    .eq(excluded(processor_status::last_transaction_timestamp)),
+            )),
+        Some(" WHERE processor_status.last_success_version <= EXCLUDED.last_success_version "),
+    )
 }
 
 /// This enum captures the configs for all the different processors that are defined.
@@ -184,6 +213,7 @@ pub enum ProcessorConfig {
     AnsProcessor(AnsProcessorConfig),
     CoinProcessor,
     DefaultProcessor,
+    DexProcessor(DexProcessorConfig),
     LsProcessor(LsConfigs),
     EventsProcessor,
     FungibleAssetProcessor,
@@ -202,6 +232,36 @@ impl ProcessorConfig {
     pub fn name(&self) -> &'static str {
         self.into()
     }
+
+    /// Checks the processor specific settings, before anything is started.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            ProcessorConfig::LsProcessor(config) => config.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the processor builds on the rows that earlier batches wrote, so that batches have
+    /// to be committed in order, i.e. with a single processing task.
+    pub fn requires_ordered_batches(&self) -> bool {
+        matches!(self, ProcessorConfig::LsProcessor(_))
+    }
+
+    /// Whether the processor writes its tables through `BatchWriter`, so that
+    /// `enable_atomic_batch_writes` applies to it. `ls_processor` always writes atomically and
+    /// `monitoring_processor` writes no tables.
+    pub fn supports_atomic_batch_writes(&self) -> bool {
+        matches!(
+            self,
+            ProcessorConfig::CoinProcessor
+                | ProcessorConfig::DefaultProcessor
+                | ProcessorConfig::DexProcessor(_)
+                | ProcessorConfig::LsProcessor(_)
+                | ProcessorConfig::MonitoringProcessor
+                | ProcessorConfig::StakeProcessor
+                | ProcessorConfig::TokenV2Processor
+        )
+    }
 }
 
 /// This enum contains all the processors defined in this crate. We use enum_dispatch
@@ -226,6 +286,7 @@ pub enum Processor {
     AnsProcessor,
     CoinProcessor,
     DefaultProcessor,
+    DexProcessor,
     LsProcessor,
     EventsProcessor,
     FungibleAssetProcessor,
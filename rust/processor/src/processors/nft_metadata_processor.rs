@@ -87,7 +87,7 @@ impl ProcessorTrait for NftMetadataProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         db_chain_id: Option<u64>,
@@ -98,7 +98,7 @@ impl ProcessorTrait for NftMetadataProcessor {
         // First get all token related table metadata from the batch of transactions. This is in case
         // an earlier transaction has metadata (in resources) that's missing from a later transaction.
         let table_handle_to_owner =
-            TableMetadataForToken::get_table_handle_to_owner_from_transactions(&transactions);
+            TableMetadataForToken::get_table_handle_to_owner_from_transactions(transactions);
 
         // Initialize pubsub client
         let config = ClientConfig::default().with_auth().await?;
@@ -109,7 +109,7 @@ impl ProcessorTrait for NftMetadataProcessor {
 
         // Publish CurrentTokenDataV2 and CurrentCollectionV2 from transactions
         let (token_datas, collections) =
-            parse_v2_token(&transactions, &table_handle_to_owner, &mut conn).await;
+            parse_v2_token(transactions, &table_handle_to_owner, &mut conn).await;
         let mut pubsub_messages: Vec<PubsubMessage> =
             Vec::with_capacity(token_datas.len() + collections.len());
 
@@ -143,7 +143,7 @@ impl ProcessorTrait for ObjectsProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -157,7 +157,7 @@ impl ProcessorTrait for ObjectsProcessor {
         let mut all_current_objects = AHashMap::new();
         let mut object_metadata_helper: ObjectAggregatedDataMapping = AHashMap::new();
 
-        for txn in &transactions {
+        for txn in transactions {
             let txn_version = txn.version as i64;
             let changes = &txn
                 .info
@@ -18,7 +18,7 @@ use crate::{
     },
     schema,
     utils::{
-        database::{execute_in_chunks, PgDbPool},
+        database::{BatchWriteMode, BatchWriter, PgDbPool},
         util::{parse_timestamp, standardize_address},
     },
 };
@@ -37,11 +37,15 @@ use tracing::error;
 
 pub struct StakeProcessor {
     connection_pool: PgDbPool,
+    write_mode: BatchWriteMode,
 }
 
 impl StakeProcessor {
-    pub fn new(connection_pool: PgDbPool) -> Self {
-        Self { connection_pool }
+    pub fn new(connection_pool: PgDbPool, write_mode: BatchWriteMode) -> Self {
+        Self {
+            connection_pool,
+            write_mode,
+        }
     }
 }
 
@@ -57,7 +61,7 @@ impl Debug for StakeProcessor {
 }
 
 async fn insert_to_db(
-    conn: PgDbPool,
+    writer: &mut BatchWriter,
     name: &'static str,
     start_version: u64,
     end_version: u64,
@@ -78,69 +82,69 @@ async fn insert_to_db(
         "Inserting to db",
     );
 
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_stake_pool_voter_query,
-        current_stake_pool_voters,
-        CurrentStakingPoolVoter::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_proposal_votes_query,
-        proposal_votes,
-        ProposalVote::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_delegator_activities_query,
-        delegator_actvities,
-        DelegatedStakingActivity::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_delegator_balances_query,
-        delegator_balances,
-        DelegatorBalance::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_delegator_balances_query,
-        current_delegator_balances,
-        CurrentDelegatorBalance::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_delegator_pools_query,
-        delegator_pools,
-        DelegatorPool::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_delegator_pool_balances_query,
-        delegator_pool_balances,
-        DelegatorPoolBalance::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_delegator_pool_balances_query,
-        current_delegator_pool_balances,
-        CurrentDelegatorPoolBalance::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_delegated_voter_query,
-        current_delegated_voter,
-        CurrentDelegatedVoter::field_count(),
-    )
-    .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_stake_pool_voter_query,
+            current_stake_pool_voters,
+            CurrentStakingPoolVoter::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_proposal_votes_query,
+            proposal_votes,
+            ProposalVote::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_delegator_activities_query,
+            delegator_actvities,
+            DelegatedStakingActivity::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_delegator_balances_query,
+            delegator_balances,
+            DelegatorBalance::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_delegator_balances_query,
+            current_delegator_balances,
+            CurrentDelegatorBalance::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_delegator_pools_query,
+            delegator_pools,
+            DelegatorPool::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_delegator_pool_balances_query,
+            delegator_pool_balances,
+            DelegatorPoolBalance::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_delegator_pool_balances_query,
+            current_delegator_pool_balances,
+            CurrentDelegatorPoolBalance::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_delegated_voter_query,
+            current_delegated_voter,
+            CurrentDelegatedVoter::field_count(),
+        )
+        .await?;
 
     Ok(())
 }
@@ -345,7 +349,7 @@ impl ProcessorTrait for StakeProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -367,7 +371,7 @@ impl ProcessorTrait for StakeProcessor {
         let mut all_current_delegated_voter = AHashMap::new();
         let mut all_vote_delegation_handle_to_pool_address = AHashMap::new();
 
-        for txn in &transactions {
+        for txn in transactions {
             // Add votes data
             let current_stake_pool_voter = CurrentStakingPoolVoter::from_transaction(txn).unwrap();
             all_current_stake_pool_voters.extend(current_stake_pool_voter);
@@ -505,21 +509,31 @@ impl ProcessorTrait for StakeProcessor {
         let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
         let db_insertion_start = std::time::Instant::now();
 
-        let tx_result = insert_to_db(
-            self.get_pool(),
-            self.name(),
-            start_version,
-            end_version,
-            all_current_stake_pool_voters,
-            all_proposal_votes,
-            all_delegator_activities,
-            all_delegator_balances,
-            all_current_delegator_balances,
-            all_delegator_pools,
-            all_delegator_pool_balances,
-            all_current_delegator_pool_balances,
-            all_current_delegated_voter,
-        )
+        let tx_result = async {
+            let mut writer = BatchWriter::new(self.get_pool(), self.write_mode).await?;
+            insert_to_db(
+                &mut writer,
+                self.name(),
+                start_version,
+                end_version,
+                all_current_stake_pool_voters,
+                all_proposal_votes,
+                all_delegator_activities,
+                all_delegator_balances,
+                all_current_delegator_balances,
+                all_delegator_pools,
+                all_delegator_pool_balances,
+                all_current_delegator_pool_balances,
+                all_current_delegated_voter,
+            )
+            .await?;
+            self.commit_batch(
+                writer,
+                end_version,
+                transactions.last().unwrap().timestamp.clone(),
+            )
+            .await
+        }
         .await;
         let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
         match tx_result {
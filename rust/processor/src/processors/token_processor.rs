@@ -406,7 +406,7 @@ impl ProcessorTrait for TokenProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -417,7 +417,7 @@ impl ProcessorTrait for TokenProcessor {
         // First get all token related table metadata from the batch of transactions. This is in case
         // an earlier transaction has metadata (in resources) that's missing from a later transaction.
         let table_handle_to_owner =
-            TableMetadataForToken::get_table_handle_to_owner_from_transactions(&transactions);
+            TableMetadataForToken::get_table_handle_to_owner_from_transactions(transactions);
 
         // Token V1 only, this section will be deprecated soon
         let mut all_tokens = vec![];
@@ -443,7 +443,7 @@ impl ProcessorTrait for TokenProcessor {
         // This is likely temporary
         let mut all_nft_points = vec![];
 
-        for txn in &transactions {
+        for txn in transactions {
             let (
                 mut tokens,
                 mut token_ownerships,
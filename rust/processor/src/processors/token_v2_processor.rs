@@ -30,7 +30,7 @@ use crate::{
     schema,
     utils::{
         counters::PROCESSOR_UNKNOWN_TYPE_COUNT,
-        database::{execute_in_chunks, PgDbPool, PgPoolConnection},
+        database::{BatchWriteMode, BatchWriter, PgDbPool, PgPoolConnection},
         util::{get_entry_function_from_user_request, parse_timestamp, standardize_address},
     },
 };
@@ -49,11 +49,15 @@ use tracing::error;
 
 pub struct TokenV2Processor {
     connection_pool: PgDbPool,
+    write_mode: BatchWriteMode,
 }
 
 impl TokenV2Processor {
-    pub fn new(connection_pool: PgDbPool) -> Self {
-        Self { connection_pool }
+    pub fn new(connection_pool: PgDbPool, write_mode: BatchWriteMode) -> Self {
+        Self {
+            connection_pool,
+            write_mode,
+        }
     }
 }
 
@@ -69,7 +73,7 @@ impl Debug for TokenV2Processor {
 }
 
 async fn insert_to_db(
-    conn: PgDbPool,
+    writer: &mut BatchWriter,
     name: &'static str,
     start_version: u64,
     end_version: u64,
@@ -89,62 +93,62 @@ async fn insert_to_db(
         "Inserting to db",
     );
 
-    execute_in_chunks(
-        conn.clone(),
-        insert_collections_v2_query,
-        collections_v2,
-        CollectionV2::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_token_datas_v2_query,
-        token_datas_v2,
-        TokenDataV2::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_token_ownerships_v2_query,
-        token_ownerships_v2,
-        TokenOwnershipV2::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_collections_v2_query,
-        current_collections_v2,
-        CurrentCollectionV2::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_token_datas_v2_query,
-        current_token_datas_v2,
-        CurrentTokenDataV2::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_token_ownerships_v2_query,
-        current_token_ownerships_v2,
-        CurrentTokenOwnershipV2::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_token_activities_v2_query,
-        token_activities_v2,
-        TokenActivityV2::field_count(),
-    )
-    .await?;
-    execute_in_chunks(
-        conn.clone(),
-        insert_current_token_v2_metadatas_query,
-        current_token_v2_metadata,
-        CurrentTokenV2Metadata::field_count(),
-    )
-    .await?;
+    writer
+        .execute_in_chunks(
+            insert_collections_v2_query,
+            collections_v2,
+            CollectionV2::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_token_datas_v2_query,
+            token_datas_v2,
+            TokenDataV2::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_token_ownerships_v2_query,
+            token_ownerships_v2,
+            TokenOwnershipV2::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_collections_v2_query,
+            current_collections_v2,
+            CurrentCollectionV2::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_token_datas_v2_query,
+            current_token_datas_v2,
+            CurrentTokenDataV2::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_token_ownerships_v2_query,
+            current_token_ownerships_v2,
+            CurrentTokenOwnershipV2::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_token_activities_v2_query,
+            token_activities_v2,
+            TokenActivityV2::field_count(),
+        )
+        .await?;
+    writer
+        .execute_in_chunks(
+            insert_current_token_v2_metadatas_query,
+            current_token_v2_metadata,
+            CurrentTokenV2Metadata::field_count(),
+        )
+        .await?;
     Ok(())
 }
 
@@ -345,7 +349,7 @@ impl ProcessorTrait for TokenV2Processor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -356,7 +360,7 @@ impl ProcessorTrait for TokenV2Processor {
         // First get all token related table metadata from the batch of transactions. This is in case
         // an earlier transaction has metadata (in resources) that's missing from a later transaction.
         let table_handle_to_owner =
-            TableMetadataForToken::get_table_handle_to_owner_from_transactions(&transactions);
+            TableMetadataForToken::get_table_handle_to_owner_from_transactions(transactions);
 
         // Token V2 processing which includes token v1
         let (
@@ -368,25 +372,35 @@ impl ProcessorTrait for TokenV2Processor {
             current_token_datas_v2,
             token_activities_v2,
             current_token_v2_metadata,
-        ) = parse_v2_token(&transactions, &table_handle_to_owner, &mut conn).await;
+        ) = parse_v2_token(transactions, &table_handle_to_owner, &mut conn).await;
 
         let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
         let db_insertion_start = std::time::Instant::now();
 
-        let tx_result = insert_to_db(
-            self.get_pool(),
-            self.name(),
-            start_version,
-            end_version,
-            collections_v2,
-            token_datas_v2,
-            token_ownerships_v2,
-            current_collections_v2,
-            current_token_ownerships_v2,
-            current_token_datas_v2,
-            token_activities_v2,
-            current_token_v2_metadata,
-        )
+        let tx_result = async {
+            let mut writer = BatchWriter::new(self.get_pool(), self.write_mode).await?;
+            insert_to_db(
+                &mut writer,
+                self.name(),
+                start_version,
+                end_version,
+                collections_v2,
+                token_datas_v2,
+                token_ownerships_v2,
+                current_collections_v2,
+                current_token_ownerships_v2,
+                current_token_datas_v2,
+                token_activities_v2,
+                current_token_v2_metadata,
+            )
+            .await?;
+            self.commit_batch(
+                writer,
+                end_version,
+                transactions.last().unwrap().timestamp.clone(),
+            )
+            .await
+        }
         .await;
 
         let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
@@ -127,7 +127,7 @@ impl ProcessorTrait for UserTransactionProcessor {
 
     async fn process_transactions(
         &self,
-        transactions: Vec<Transaction>,
+        transactions: &[Transaction],
         start_version: u64,
         end_version: u64,
         _: Option<u64>,
@@ -135,7 +135,7 @@ impl ProcessorTrait for UserTransactionProcessor {
         let processing_start = std::time::Instant::now();
         let mut signatures = vec![];
         let mut user_transactions = vec![];
-        for txn in &transactions {
+        for txn in transactions {
             let txn_version = txn.version as i64;
             let block_height = txn.block_height as i64;
             let txn_data = match txn.txn_data.as_ref() {
@@ -171,6 +171,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    coin_prices (coin_type_hash, version) {
+        #[max_length = 64]
+        coin_type_hash -> Varchar,
+        #[max_length = 5000]
+        coin_type -> Varchar,
+        version -> Int8,
+        timestamp -> Int8,
+        price_usd -> Numeric,
+        liquidity_usd -> Numeric,
+        confidence -> Numeric,
+    }
+}
+
 diesel::table! {
     coin_supply (transaction_version, coin_type_hash) {
         transaction_version -> Int8,
@@ -697,6 +711,66 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dex_liquidity_changes (protocol, id) {
+        #[max_length = 32]
+        protocol -> Varchar,
+        id -> Varchar,
+        #[max_length = 128]
+        pool_id -> Varchar,
+        #[max_length = 66]
+        account -> Varchar,
+        version -> Int8,
+        #[max_length = 64]
+        tx_hash -> Varchar,
+        timestamp -> Int8,
+        x_val -> Numeric,
+        y_val -> Numeric,
+        lp_tokens -> Numeric,
+    }
+}
+
+diesel::table! {
+    dex_pools (protocol, pool_id) {
+        #[max_length = 32]
+        protocol -> Varchar,
+        #[max_length = 128]
+        pool_id -> Varchar,
+        #[max_length = 5000]
+        coin_x -> Varchar,
+        #[max_length = 5000]
+        coin_y -> Varchar,
+        #[max_length = 5000]
+        pool_type -> Nullable<Varchar>,
+        x_val -> Numeric,
+        y_val -> Numeric,
+        fee_bps -> Nullable<Int8>,
+        last_version -> Int8,
+    }
+}
+
+diesel::table! {
+    dex_swaps (protocol, id) {
+        #[max_length = 32]
+        protocol -> Varchar,
+        id -> Varchar,
+        #[max_length = 128]
+        pool_id -> Varchar,
+        version -> Int8,
+        #[max_length = 64]
+        tx_hash -> Varchar,
+        #[max_length = 66]
+        sender -> Varchar,
+        timestamp -> Int8,
+        #[max_length = 5000]
+        coin_in -> Varchar,
+        amount_in -> Numeric,
+        #[max_length = 5000]
+        coin_out -> Varchar,
+        amount_out -> Numeric,
+    }
+}
+
 diesel::table! {
     events (transaction_version, event_index) {
         sequence_number -> Int8,
@@ -805,6 +879,65 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    ls_candles (pool_id, interval_secs, start_time) {
+        #[max_length = 64]
+        pool_id -> Varchar,
+        interval_secs -> Int8,
+        start_time -> Int8,
+        open -> Numeric,
+        high -> Numeric,
+        low -> Numeric,
+        close -> Numeric,
+        base_volume -> Numeric,
+        quote_volume -> Numeric,
+        trades -> Int8,
+        first_version -> Int8,
+        last_version -> Int8,
+        last_event_index -> Int8,
+    }
+}
+
+diesel::table! {
+    ls_coins (coin_type_hash) {
+        #[max_length = 64]
+        coin_type_hash -> Varchar,
+        coin_type -> Varchar,
+        name -> Varchar,
+        symbol -> Varchar,
+        decimals -> Int4,
+        version -> Int8,
+    }
+}
+
+diesel::table! {
+    ls_dao_storages (pool_id) {
+        #[max_length = 64]
+        pool_id -> Varchar,
+        x_val -> Numeric,
+        y_val -> Numeric,
+        deposited_x -> Numeric,
+        deposited_y -> Numeric,
+        withdrawn_x -> Numeric,
+        withdrawn_y -> Numeric,
+        last_version -> Int8,
+    }
+}
+
+diesel::table! {
+    ls_deployments (address) {
+        #[max_length = 66]
+        address -> Varchar,
+        version_ls -> Varchar,
+        #[max_length = 66]
+        deployer -> Varchar,
+        version -> Int8,
+        #[max_length = 64]
+        tx_hash -> Varchar,
+        timestamp -> Int8,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::EventType;
@@ -826,6 +959,122 @@ diesel::table! {
         y_val -> Nullable<Numeric>,
         fee -> Nullable<Int8>,
         dao_fee -> Nullable<Int8>,
+        x_val_normalized -> Nullable<Numeric>,
+        y_val_normalized -> Nullable<Numeric>,
+        x_in -> Nullable<Numeric>,
+        x_out -> Nullable<Numeric>,
+        y_in -> Nullable<Numeric>,
+        y_out -> Nullable<Numeric>,
+        lp_tokens -> Nullable<Numeric>,
+        x_reserve -> Nullable<Numeric>,
+        y_reserve -> Nullable<Numeric>,
+    }
+}
+
+diesel::table! {
+    ls_flashloans (id) {
+        id -> Varchar,
+        #[max_length = 64]
+        pool_id -> Varchar,
+        version -> Int8,
+        #[max_length = 64]
+        tx_hash -> Varchar,
+        timestamp -> Int8,
+        #[max_length = 66]
+        borrower -> Varchar,
+        #[max_length = 1000]
+        entry_function -> Nullable<Varchar>,
+        x_borrowed -> Numeric,
+        y_borrowed -> Numeric,
+        x_repaid -> Numeric,
+        y_repaid -> Numeric,
+        x_fee -> Numeric,
+        y_fee -> Numeric,
+        fee -> Nullable<Int8>,
+        dao_fee -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::EventType;
+
+    ls_lp_activities (id) {
+        id -> Varchar,
+        #[max_length = 66]
+        account -> Varchar,
+        #[max_length = 64]
+        pool_id -> Varchar,
+        tp -> EventType,
+        version -> Int8,
+        #[max_length = 64]
+        tx_hash -> Varchar,
+        timestamp -> Int8,
+        x_val -> Numeric,
+        y_val -> Numeric,
+        lp_tokens -> Numeric,
+        lp_balance -> Nullable<Numeric>,
+    }
+}
+
+diesel::table! {
+    ls_lp_positions (account, pool_id) {
+        #[max_length = 66]
+        account -> Varchar,
+        #[max_length = 64]
+        pool_id -> Varchar,
+        lp_balance -> Numeric,
+        cost_x -> Numeric,
+        cost_y -> Numeric,
+        last_version -> Int8,
+    }
+}
+
+diesel::table! {
+    ls_oracle_observations (id) {
+        id -> Varchar,
+        #[max_length = 64]
+        pool_id -> Varchar,
+        version -> Int8,
+        timestamp -> Int8,
+        price_x_cumulative -> Numeric,
+        price_y_cumulative -> Numeric,
+    }
+}
+
+diesel::table! {
+    ls_pool_daily_stats (pool_id, day) {
+        #[max_length = 64]
+        pool_id -> Varchar,
+        day -> Int8,
+        x_volume -> Numeric,
+        y_volume -> Numeric,
+        x_lp_fee -> Numeric,
+        y_lp_fee -> Numeric,
+        x_dao_fee -> Numeric,
+        y_dao_fee -> Numeric,
+        swaps -> Int8,
+        x_val -> Numeric,
+        y_val -> Numeric,
+        fee -> Int8,
+        dao_fee -> Int8,
+        last_swap_version -> Int8,
+        last_swap_event_index -> Int8,
+        reserves_version -> Int8,
+    }
+}
+
+diesel::table! {
+    ls_pool_snapshots (pool_id, version) {
+        #[max_length = 64]
+        pool_id -> Varchar,
+        version -> Int8,
+        timestamp -> Int8,
+        x_val -> Numeric,
+        y_val -> Numeric,
+        fee -> Int8,
+        dao_fee -> Int8,
+        lp_supply -> Nullable<Numeric>,
     }
 }
 
@@ -843,6 +1092,70 @@ diesel::table! {
         #[max_length = 8]
         version_ls -> Nullable<Varchar>,
         last_version -> Int8,
+        x_symbol -> Nullable<Varchar>,
+        y_symbol -> Nullable<Varchar>,
+        x_decimals -> Nullable<Int4>,
+        y_decimals -> Nullable<Int4>,
+        x_val_normalized -> Nullable<Numeric>,
+        y_val_normalized -> Nullable<Numeric>,
+    }
+}
+
+diesel::table! {
+    ls_trader_stats (account, pool_id) {
+        #[max_length = 66]
+        account -> Varchar,
+        #[max_length = 64]
+        pool_id -> Varchar,
+        x_bought -> Numeric,
+        x_sold -> Numeric,
+        y_spent -> Numeric,
+        y_received -> Numeric,
+        position_x -> Numeric,
+        cost_y -> Numeric,
+        avg_entry_price -> Nullable<Numeric>,
+        realized_pnl -> Numeric,
+        trades -> Int8,
+        last_version -> Int8,
+    }
+}
+
+diesel::table! {
+    ls_trades (version, trade_index) {
+        version -> Int8,
+        trade_index -> Int4,
+        #[max_length = 64]
+        tx_hash -> Varchar,
+        #[max_length = 66]
+        sender -> Varchar,
+        timestamp -> Int8,
+        hops -> Int4,
+        route -> Jsonb,
+        coin_in -> Varchar,
+        amount_in -> Numeric,
+        coin_out -> Varchar,
+        amount_out -> Numeric,
+        price -> Nullable<Numeric>,
+        amount_in_normalized -> Nullable<Numeric>,
+        amount_out_normalized -> Nullable<Numeric>,
+        price_normalized -> Nullable<Numeric>,
+        #[max_length = 1000]
+        entry_function -> Nullable<Varchar>,
+        payload -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    ls_twaps (pool_id, window_secs) {
+        #[max_length = 64]
+        pool_id -> Varchar,
+        window_secs -> Int8,
+        twap_x -> Numeric,
+        twap_y -> Numeric,
+        start_version -> Int8,
+        end_version -> Int8,
+        start_timestamp -> Int8,
+        end_timestamp -> Int8,
     }
 }
 
@@ -1259,6 +1572,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     coin_activities,
     coin_balances,
     coin_infos,
+    coin_prices,
     coin_supply,
     collection_datas,
     collections_v2,
@@ -1286,14 +1600,30 @@ diesel::allow_tables_to_appear_in_same_query!(
     delegated_staking_pool_balances,
     delegated_staking_pools,
     delegator_balances,
+    dex_liquidity_changes,
+    dex_pools,
+    dex_swaps,
     events,
     fungible_asset_activities,
     fungible_asset_balances,
     fungible_asset_metadata,
     indexer_status,
     ledger_infos,
+    ls_candles,
+    ls_coins,
+    ls_dao_storages,
+    ls_deployments,
     ls_events,
+    ls_flashloans,
+    ls_lp_activities,
+    ls_lp_positions,
+    ls_oracle_observations,
+    ls_pool_daily_stats,
+    ls_pool_snapshots,
     ls_pools,
+    ls_trader_stats,
+    ls_trades,
+    ls_twaps,
     move_modules,
     move_resources,
     nft_points,
@@ -89,6 +89,26 @@ pub static PROCESSOR_SUCCESSES_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Number of times a batch has been retried after a transient error
+pub static PROCESSOR_RETRIES_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_retry_count",
+        "Number of times a batch has been retried after a transient error",
+        &["processor_name"]
+    )
+    .unwrap()
+});
+
+/// Number of times a batch has failed after all retries were used up
+pub static PROCESSOR_RETRIES_EXHAUSTED_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_retries_exhausted_count",
+        "Number of times a batch has failed after all retries were used up",
+        &["processor_name"]
+    )
+    .unwrap()
+});
+
 /// Number of times the connection pool has timed out when trying to get a connection
 pub static UNABLE_TO_GET_CONNECTION_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     register_int_counter!(
@@ -237,6 +257,16 @@ pub static GRPC_LATENCY_BY_PROCESSOR_IN_SECS: Lazy<GaugeVec> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Number of times a data service endpoint failed to connect or dropped the stream.
+pub static GRPC_ENDPOINT_FAILURE_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_grpc_endpoint_failure_count",
+        "Number of times a data service endpoint failed to connect or dropped the stream",
+        &["processor_name", "stream_address"]
+    )
+    .unwrap()
+});
+
 /// Processor unknown type count.
 pub static PROCESSOR_UNKNOWN_TYPE_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
@@ -15,7 +15,7 @@ use diesel_async::{
         bb8::{Pool, PooledConnection},
         AsyncDieselConnectionManager, ManagerConfig, PoolError,
     },
-    RunQueryDsl,
+    AnsiTransactionManager, RunQueryDsl, TransactionManager,
 };
 use diesel_async_migrations::{embed_migrations, EmbeddedMigrations};
 use futures_util::{future::BoxFuture, FutureExt};
@@ -177,9 +177,88 @@ where
     Ok(())
 }
 
+/// Same as `execute_in_chunks` but every chunk runs on `conn`, which is expected to already be
+/// inside a transaction. Each chunk gets its own savepoint so that the retry with cleaned data
+/// does not run inside an aborted transaction.
+pub async fn execute_in_chunks_in_transaction<U, T>(
+    conn: &mut MyDbConnection,
+    build_query: fn(Vec<T>) -> (U, Option<&'static str>),
+    items_to_insert: Vec<T>,
+    chunk_size: usize,
+) -> Result<(), diesel::result::Error>
+where
+    U: QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    T: serde::Serialize + for<'de> serde::Deserialize<'de> + Clone,
+{
+    let chunks = get_chunks(items_to_insert.len(), chunk_size);
+
+    for (start_ind, end_ind) in chunks {
+        let items = &items_to_insert[start_ind..end_ind];
+
+        let (query, additional_where_clause) = build_query(items.to_vec());
+        if execute_in_savepoint(conn, query, additional_where_clause)
+            .await
+            .is_err()
+        {
+            let cleaned_items = clean_data_for_db(items.to_vec(), true);
+            let (cleaned_query, additional_where_clause) = build_query(cleaned_items);
+            execute_in_savepoint(conn, cleaned_query, additional_where_clause).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn execute_in_savepoint<U>(
+    conn: &mut MyDbConnection,
+    query: U,
+    additional_where_clause: Option<&'static str>,
+) -> QueryResult<usize>
+where
+    U: QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+{
+    // Nested transactions are savepoints
+    <AnsiTransactionManager as TransactionManager<MyDbConnection>>::begin_transaction(conn).await?;
+    match execute_with_better_error_conn(conn, query, additional_where_clause).await {
+        Ok(res) => {
+            <AnsiTransactionManager as TransactionManager<MyDbConnection>>::commit_transaction(
+                conn,
+            )
+            .await?;
+            Ok(res)
+        },
+        Err(e) => {
+            <AnsiTransactionManager as TransactionManager<MyDbConnection>>::rollback_transaction(
+                conn,
+            )
+            .await?;
+            Err(e)
+        },
+    }
+}
+
 pub async fn execute_with_better_error<U>(
     pool: PgDbPool,
     query: U,
+    additional_where_clause: Option<&'static str>,
+) -> QueryResult<usize>
+where
+    U: QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+{
+    let conn = &mut pool.get().await.map_err(|e| {
+        tracing::warn!("Error getting connection from pool: {:?}", e);
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+    execute_with_better_error_conn(conn, query, additional_where_clause).await
+}
+
+/// Same as `execute_with_better_error` but on a connection that the caller already holds, e.g.
+/// one that is inside a transaction.
+pub async fn execute_with_better_error_conn<U>(
+    conn: &mut MyDbConnection,
+    query: U,
     mut additional_where_clause: Option<&'static str>,
 ) -> QueryResult<usize>
 where
@@ -198,14 +277,6 @@ where
     let debug_string = diesel::debug_query::<diesel::pg::Pg, _>(&final_query).to_string();
     tracing::trace!("Executing query: {:?}", debug_string);
 
-    let conn = &mut pool.get().await.map_err(|e| {
-        tracing::warn!("Error getting connection from pool: {:?}", e);
-        diesel::result::Error::DatabaseError(
-            diesel::result::DatabaseErrorKind::UnableToSendCommand,
-            Box::new(e.to_string()),
-        )
-    })?;
-
     let res = final_query.execute(conn).await;
     if let Err(ref e) = res {
         tracing::warn!("Error running query: {:?}\n{:?}", e, debug_string);
@@ -213,6 +284,126 @@ where
     res
 }
 
+/// How a processor writes the tables of one batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BatchWriteMode {
+    /// Every query runs on its own pooled connection.
+    #[default]
+    PerQuery,
+    /// All queries of a batch run in a single transaction.
+    Atomic,
+    /// Like `Atomic`, and `processor_status` is updated in the same transaction. Only correct
+    /// when batches are committed in order, i.e. with a single processing task.
+    AtomicWithStatus,
+}
+
+/// Destination for the queries of one batch, see `BatchWriteMode`.
+pub enum BatchWriter {
+    Pool(PgDbPool),
+    Transaction {
+        conn: PgPoolConnection<'static>,
+        writes_processor_status: bool,
+    },
+}
+
+impl BatchWriter {
+    /// Creates a writer for one batch. In the atomic modes this takes a connection out of the
+    /// pool and opens a transaction on it, which is committed by `commit`. If the writer is
+    /// dropped without committing, the connection is discarded by the pool.
+    pub async fn new(pool: PgDbPool, mode: BatchWriteMode) -> QueryResult<Self> {
+        if mode == BatchWriteMode::PerQuery {
+            return Ok(BatchWriter::Pool(pool));
+        }
+        let mut conn = pool.get_owned().await.map_err(|e| {
+            tracing::warn!("Error getting connection from pool: {:?}", e);
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UnableToSendCommand,
+                Box::new(e.to_string()),
+            )
+        })?;
+        <AnsiTransactionManager as TransactionManager<MyDbConnection>>::begin_transaction(
+            &mut *conn,
+        )
+        .await?;
+        Ok(BatchWriter::Transaction {
+            conn,
+            writes_processor_status: mode == BatchWriteMode::AtomicWithStatus,
+        })
+    }
+
+    /// Whether `processor_status` should be updated through this writer.
+    pub fn writes_processor_status(&self) -> bool {
+        matches!(
+            self,
+            BatchWriter::Transaction {
+                writes_processor_status: true,
+                ..
+            }
+        )
+    }
+
+    /// The connection of the transaction, for processors that also read the rows they build on
+    /// within it.
+    pub fn transaction_conn(&mut self) -> Option<&mut PgPoolConnection<'static>> {
+        match self {
+            BatchWriter::Pool(_) => None,
+            BatchWriter::Transaction { conn, .. } => Some(conn),
+        }
+    }
+
+    pub async fn execute_in_chunks<U, T>(
+        &mut self,
+        build_query: fn(Vec<T>) -> (U, Option<&'static str>),
+        items_to_insert: Vec<T>,
+        chunk_size: usize,
+    ) -> Result<(), diesel::result::Error>
+    where
+        U: QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+        T: serde::Serialize + for<'de> serde::Deserialize<'de> + Clone,
+    {
+        match self {
+            BatchWriter::Pool(pool) => {
+                execute_in_chunks(pool.clone(), build_query, items_to_insert, chunk_size).await
+            },
+            BatchWriter::Transaction { conn, .. } => {
+                execute_in_chunks_in_transaction(conn, build_query, items_to_insert, chunk_size)
+                    .await
+            },
+        }
+    }
+
+    pub async fn execute<U>(
+        &mut self,
+        query: U,
+        additional_where_clause: Option<&'static str>,
+    ) -> QueryResult<usize>
+    where
+        U: QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    {
+        match self {
+            BatchWriter::Pool(pool) => {
+                execute_with_better_error(pool.clone(), query, additional_where_clause).await
+            },
+            BatchWriter::Transaction { conn, .. } => {
+                execute_with_better_error_conn(conn, query, additional_where_clause).await
+            },
+        }
+    }
+
+    /// Commits the transaction if there is one.
+    pub async fn commit(self) -> QueryResult<()> {
+        match self {
+            BatchWriter::Pool(_) => Ok(()),
+            BatchWriter::Transaction { mut conn, .. } => {
+                <AnsiTransactionManager as TransactionManager<MyDbConnection>>::commit_transaction(
+                    &mut *conn,
+                )
+                .await
+            },
+        }
+    }
+}
+
 pub async fn run_pending_migrations(conn: &mut MyDbConnection) {
     MIGRATIONS
         .run_pending_migrations(conn)
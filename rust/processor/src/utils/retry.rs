@@ -0,0 +1,172 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Retry policy for processing a batch of transactions.
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Kinds of `diesel::result::Error` that are considered transient and worth retrying.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryableErrorKind {
+    /// Postgres aborted the transaction to break a deadlock (SQLSTATE 40P01).
+    Deadlock,
+    /// Postgres could not serialize concurrent transactions (SQLSTATE 40001).
+    SerializationFailure,
+    /// The connection was closed while the query was running.
+    ClosedConnection,
+    /// The query could not be sent, e.g. no connection could be taken from the pool.
+    UnableToSendCommand,
+    /// The query ran against a read-only replica, e.g. during a failover.
+    ReadOnlyTransaction,
+}
+
+impl RetryableErrorKind {
+    pub fn matches(&self, error: &DieselError) -> bool {
+        let (kind, info) = match error {
+            DieselError::DatabaseError(kind, info) => (kind, info),
+            _ => return false,
+        };
+        match self {
+            // Diesel does not have a dedicated kind for deadlocks so we have to look at the message.
+            RetryableErrorKind::Deadlock => {
+                matches!(kind, DatabaseErrorKind::Unknown)
+                    && info.message().contains("deadlock detected")
+            },
+            RetryableErrorKind::SerializationFailure => {
+                matches!(kind, DatabaseErrorKind::SerializationFailure)
+            },
+            RetryableErrorKind::ClosedConnection => {
+                matches!(kind, DatabaseErrorKind::ClosedConnection)
+            },
+            RetryableErrorKind::UnableToSendCommand => {
+                matches!(kind, DatabaseErrorKind::UnableToSendCommand)
+            },
+            RetryableErrorKind::ReadOnlyTransaction => {
+                matches!(kind, DatabaseErrorKind::ReadOnlyTransaction)
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct BatchRetryConfig {
+    /// Number of times a batch is processed before the worker gives up, including the first
+    /// attempt. Defaults to 5. Set to 1 to disable retries.
+    pub max_attempts: u32,
+
+    /// Delay before the first retry in milliseconds. Defaults to 500.
+    pub initial_backoff_in_ms: u64,
+
+    /// Upper bound for the delay between two attempts in milliseconds. Defaults to 30000.
+    pub max_backoff_in_ms: u64,
+
+    /// Factor applied to the delay after every attempt. Defaults to 2.
+    pub backoff_multiplier: f64,
+
+    /// Fraction of the delay that is randomized, between 0 and 1. Defaults to 0.2.
+    pub jitter_ratio: f64,
+
+    /// Errors that are retried. Anything else fails the batch right away.
+    pub retryable_errors: Vec<RetryableErrorKind>,
+}
+
+impl BatchRetryConfig {
+    /// Returns true if any error in the chain is a diesel error of a retryable kind.
+    pub fn is_retryable(&self, error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .filter_map(|e| e.downcast_ref::<DieselError>())
+            .any(|e| self.retryable_errors.iter().any(|kind| kind.matches(e)))
+    }
+
+    /// Delay to wait after the given failed attempt (1-based) before trying again.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        exponential_backoff(
+            self.initial_backoff_in_ms,
+            self.max_backoff_in_ms,
+            self.backoff_multiplier,
+            self.jitter_ratio,
+            attempt,
+        )
+    }
+}
+
+/// Delay after the given failed attempt (1-based): `initial_in_ms * multiplier^(attempt - 1)`,
+/// capped at `max_in_ms` and randomized by up to `jitter_ratio` in either direction.
+pub fn exponential_backoff(
+    initial_in_ms: u64,
+    max_in_ms: u64,
+    multiplier: f64,
+    jitter_ratio: f64,
+    attempt: u32,
+) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+    let delay_in_ms =
+        (initial_in_ms as f64 * multiplier.max(1.0).powi(exponent)).min(max_in_ms as f64);
+    let jitter_ratio = jitter_ratio.clamp(0.0, 1.0);
+    let jitter = if jitter_ratio > 0.0 {
+        rand::thread_rng().gen_range(-jitter_ratio..=jitter_ratio)
+    } else {
+        0.0
+    };
+    Duration::from_millis((delay_in_ms * (1.0 + jitter)).max(0.0) as u64)
+}
+
+impl Default for BatchRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff_in_ms: 500,
+            max_backoff_in_ms: 30_000,
+            backoff_multiplier: 2.0,
+            jitter_ratio: 0.2,
+            retryable_errors: vec![
+                RetryableErrorKind::Deadlock,
+                RetryableErrorKind::SerializationFailure,
+                RetryableErrorKind::ClosedConnection,
+                RetryableErrorKind::UnableToSendCommand,
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db_error(kind: DatabaseErrorKind, message: &str) -> DieselError {
+        DieselError::DatabaseError(kind, Box::new(message.to_string()))
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let config = BatchRetryConfig::default();
+        let deadlock =
+            anyhow::Error::new(db_error(DatabaseErrorKind::Unknown, "deadlock detected"))
+                .context("Error inserting transactions to db");
+        assert!(config.is_retryable(&deadlock));
+
+        let unique = anyhow::Error::new(db_error(
+            DatabaseErrorKind::UniqueViolation,
+            "duplicate key",
+        ));
+        assert!(!config.is_retryable(&unique));
+        assert!(!config.is_retryable(&anyhow::anyhow!("deadlock detected")));
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let config = BatchRetryConfig {
+            jitter_ratio: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(config.backoff(1), Duration::from_millis(500));
+        assert_eq!(config.backoff(3), Duration::from_millis(2_000));
+        assert_eq!(config.backoff(100), Duration::from_millis(30_000));
+    }
+}
@@ -2,46 +2,55 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    archive::{create_recorder_loop, ArchiveWriter, RecordConfig},
     config::IndexerGrpcHttp2Config,
+    grpc_stream::TransactionSource,
     models::{ledger_info::LedgerInfo, processor_status::ProcessorStatusQuery},
     processors::{
         account_transactions_processor::AccountTransactionsProcessor, ans_processor::AnsProcessor,
         coin_processor::CoinProcessor, default_processor::DefaultProcessor,
-        events_processor::EventsProcessor, fungible_asset_processor::FungibleAssetProcessor,
-        ls_processor::LsProcessor, monitoring_processor::MonitoringProcessor,
-        nft_metadata_processor::NftMetadataProcessor, objects_processor::ObjectsProcessor,
-        stake_processor::StakeProcessor, token_processor::TokenProcessor,
-        token_v2_processor::TokenV2Processor, user_transaction_processor::UserTransactionProcessor,
-        ProcessingResult, Processor, ProcessorConfig, ProcessorTrait,
+        dex_processor::DexProcessor, events_processor::EventsProcessor,
+        fungible_asset_processor::FungibleAssetProcessor, ls_processor::LsProcessor,
+        monitoring_processor::MonitoringProcessor, nft_metadata_processor::NftMetadataProcessor,
+        objects_processor::ObjectsProcessor, stake_processor::StakeProcessor,
+        token_processor::TokenProcessor, token_v2_processor::TokenV2Processor,
+        user_transaction_processor::UserTransactionProcessor, ProcessingResult, Processor,
+        ProcessorConfig, ProcessorTrait,
     },
     schema::ledger_infos,
     utils::{
         counters::{
-            ProcessorStep, GRPC_LATENCY_BY_PROCESSOR_IN_SECS, LATEST_PROCESSED_VERSION,
-            MULTI_BATCH_PROCESSING_TIME_IN_SECS, NUM_TRANSACTIONS_PROCESSED_COUNT,
-            PROCESSED_BYTES_COUNT, PROCESSOR_DATA_PROCESSED_LATENCY_IN_SECS,
-            PROCESSOR_DATA_RECEIVED_LATENCY_IN_SECS, PROCESSOR_ERRORS_COUNT,
-            PROCESSOR_INVOCATIONS_COUNT, PROCESSOR_SUCCESSES_COUNT,
+            ProcessorStep, FETCHER_THREAD_CHANNEL_SIZE, GRPC_LATENCY_BY_PROCESSOR_IN_SECS,
+            LATEST_PROCESSED_VERSION, MULTI_BATCH_PROCESSING_TIME_IN_SECS,
+            NUM_TRANSACTIONS_PROCESSED_COUNT, PROCESSED_BYTES_COUNT,
+            PROCESSOR_DATA_PROCESSED_LATENCY_IN_SECS, PROCESSOR_DATA_RECEIVED_LATENCY_IN_SECS,
+            PROCESSOR_ERRORS_COUNT, PROCESSOR_INVOCATIONS_COUNT, PROCESSOR_RETRIES_COUNT,
+            PROCESSOR_RETRIES_EXHAUSTED_COUNT, PROCESSOR_SUCCESSES_COUNT,
             SINGLE_BATCH_DB_INSERTION_TIME_IN_SECS, SINGLE_BATCH_PARSING_TIME_IN_SECS,
             SINGLE_BATCH_PROCESSING_TIME_IN_SECS, TRANSACTION_UNIX_TIMESTAMP,
         },
-        database::{execute_with_better_error, new_db_pool, run_pending_migrations, PgDbPool},
+        database::{
+            execute_with_better_error, new_db_pool, run_pending_migrations, BatchWriteMode,
+            PgDbPool,
+        },
+        retry::BatchRetryConfig,
         util::{time_diff_since_pb_timestamp_in_secs, timestamp_to_iso, timestamp_to_unixtime},
     },
 };
 use anyhow::{Context, Result};
 use aptos_moving_average::MovingAverage;
 use aptos_protos::transaction::v1::Transaction;
+use prost::Message;
 use std::{sync::Arc, time::Duration};
 use tokio::time::timeout;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use url::Url;
 
 // this is how large the fetch queue should be. Each bucket should have a max of 80MB or so, so a batch
 // of 50 means that we could potentially have at least 4.8GB of data in memory at any given time and that we should provision
 // machines accordingly.
 const BUFFER_SIZE: usize = 50;
-// Consumer thread will wait X seconds before panicking if it doesn't receive any data
+// Consumer thread will wait X seconds before giving up if it doesn't receive any data
 const CONSUMER_THREAD_TIMEOUT_IN_SECS: u64 = 60 * 5;
 pub(crate) const PROCESSOR_SERVICE_TYPE: &str = "processor";
 
@@ -52,26 +61,132 @@ pub struct TransactionsPBResponse {
     pub size_in_bytes: u64,
 }
 
+/// Fatal conditions that stop the worker. They are returned from `Worker::run` instead of
+/// panicking so that the server can log them and exit with a deterministic status code.
+#[derive(Debug)]
+pub enum WorkerError {
+    /// The transaction channel closed while the fetcher was still expected to send data.
+    ChannelClosed,
+    /// No batch arrived from the fetcher within the consumer timeout.
+    ConsumerTimeout { timeout_in_secs: u64 },
+    /// The stream returned a different chain id than the one stored in the database.
+    ChainIdChanged {
+        existing_chain_id: u64,
+        chain_id: u64,
+    },
+    /// Two consecutive batches from the stream were not contiguous.
+    BatchGap {
+        last_fetched_version: i64,
+        current_fetched_version: u64,
+    },
+    /// The gap detector saw too many batches pile up behind a missing version.
+    GapDetectionLimitReached {
+        gap_start_version: u64,
+        num_gaps: u64,
+    },
+    /// A batch could not be processed.
+    ProcessingFailed {
+        start_version: u64,
+        end_version: u64,
+        error: anyhow::Error,
+    },
+    /// The fetcher could not keep the stream alive.
+    Fetcher(anyhow::Error),
+    /// A database call outside of batch processing failed.
+    Database(anyhow::Error),
+    /// A background task panicked or was cancelled.
+    TaskFailed(anyhow::Error),
+}
+
+impl std::fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkerError::ChannelClosed => write!(f, "[Parser] Channel closed unexpectedly"),
+            WorkerError::ConsumerTimeout { timeout_in_secs } => write!(
+                f,
+                "[Parser] Consumer thread timed out after {timeout_in_secs}s waiting for transactions"
+            ),
+            WorkerError::ChainIdChanged {
+                existing_chain_id,
+                chain_id,
+            } => write!(
+                f,
+                "[Parser] Stream somehow changed chain id from {existing_chain_id} to {chain_id}"
+            ),
+            WorkerError::BatchGap {
+                last_fetched_version,
+                current_fetched_version,
+            } => write!(
+                f,
+                "[Parser] Received batch with gap from GRPC stream: last fetched version {last_fetched_version}, current fetched version {current_fetched_version}"
+            ),
+            WorkerError::GapDetectionLimitReached {
+                gap_start_version,
+                num_gaps,
+            } => write!(
+                f,
+                "[Parser] Processed {num_gaps} batches with a gap starting at version {gap_start_version}"
+            ),
+            WorkerError::ProcessingFailed {
+                start_version,
+                end_version,
+                error,
+            } => write!(
+                f,
+                "[Parser] Error processing transactions {start_version}-{end_version}: {error:?}"
+            ),
+            WorkerError::Fetcher(error) => write!(f, "[Parser] Fetcher failed: {error:?}"),
+            WorkerError::Database(error) => write!(f, "[Parser] Database error: {error:?}"),
+            WorkerError::TaskFailed(error) => write!(f, "[Parser] Task failed: {error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for WorkerError {}
+
+/// Resolves once the process receives SIGTERM or SIGINT.
+async fn wait_for_shutdown_signal() {
+    let ctrl_c = tokio::signal::ctrl_c();
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("[Parser] Failed to install SIGTERM handler");
+        tokio::select! {
+            _ = ctrl_c => {},
+            _ = sigterm.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = ctrl_c.await;
+    }
+}
+
 pub struct Worker {
     pub db_pool: PgDbPool,
-    pub processor_config: ProcessorConfig,
+    pub processor_configs: Vec<ProcessorConfig>,
     pub postgres_connection_string: String,
     pub indexer_grpc_data_service_address: Url,
+    pub transaction_source: TransactionSource,
     pub grpc_http2_config: IndexerGrpcHttp2Config,
+    pub record_config: Option<RecordConfig>,
     pub auth_token: String,
     pub starting_version: Option<u64>,
     pub ending_version: Option<u64>,
     pub number_concurrent_processing_tasks: usize,
     pub gap_detection_batch_size: u64,
     pub enable_verbose_logging: Option<bool>,
+    pub batch_retry_config: BatchRetryConfig,
+    pub enable_atomic_batch_writes: Option<bool>,
 }
 
 impl Worker {
     pub async fn new(
-        processor_config: ProcessorConfig,
+        processor_configs: Vec<ProcessorConfig>,
         postgres_connection_string: String,
-        indexer_grpc_data_service_address: Url,
+        transaction_source: TransactionSource,
         grpc_http2_config: IndexerGrpcHttp2Config,
+        record_config: Option<RecordConfig>,
         auth_token: String,
         starting_version: Option<u64>,
         ending_version: Option<u64>,
@@ -79,8 +194,41 @@ impl Worker {
         db_pool_size: Option<u32>,
         gap_detection_batch_size: u64,
         enable_verbose_logging: Option<bool>,
+        batch_retry_config: BatchRetryConfig,
+        enable_atomic_batch_writes: Option<bool>,
     ) -> Result<Self> {
-        let processor_name = processor_config.name();
+        anyhow::ensure!(
+            !processor_configs.is_empty(),
+            "[Parser] At least one processor must be configured"
+        );
+        let indexer_grpc_data_service_address = transaction_source.address()?;
+        let mut seen_processor_names = std::collections::HashSet::new();
+        for processor_config in &processor_configs {
+            // Each processor tracks its progress in the `processor_status` row named after it.
+            anyhow::ensure!(
+                seen_processor_names.insert(processor_config.name()),
+                "[Parser] Processor {} is configured more than once",
+                processor_config.name()
+            );
+            processor_config.validate().with_context(|| {
+                format!(
+                    "[Parser] Invalid config of processor {}",
+                    processor_config.name()
+                )
+            })?;
+            anyhow::ensure!(
+                !enable_atomic_batch_writes.unwrap_or(false)
+                    || processor_config.supports_atomic_batch_writes(),
+                "[Parser] Processor {} does not support enable_atomic_batch_writes",
+                processor_config.name()
+            );
+        }
+        let number_concurrent_processing_tasks = number_concurrent_processing_tasks.unwrap_or(10);
+        let processor_name = processor_configs
+            .iter()
+            .map(|processor_config| processor_config.name())
+            .collect::<Vec<_>>()
+            .join(",");
         info!(processor_name = processor_name, "[Parser] Kicking off");
 
         info!(
@@ -96,85 +244,110 @@ impl Worker {
             service_type = PROCESSOR_SERVICE_TYPE,
             "[Parser] Finish creating the connection pool"
         );
-        let number_concurrent_processing_tasks = number_concurrent_processing_tasks.unwrap_or(10);
         Ok(Self {
             db_pool: conn_pool,
-            processor_config,
+            processor_configs,
             postgres_connection_string,
             indexer_grpc_data_service_address,
+            transaction_source,
             grpc_http2_config,
+            record_config,
             starting_version,
             ending_version,
             auth_token,
             number_concurrent_processing_tasks,
             gap_detection_batch_size,
             enable_verbose_logging,
+            batch_retry_config,
+            enable_atomic_batch_writes,
         })
     }
 
     /// This is the main logic of the processor. We will do a few large parts:
     /// 1. Connect to GRPC and handling all the stuff before starting the stream such as diesel migration
     /// 2. Start a thread specifically to fetch data from GRPC. We will keep a buffer of X batches of transactions
-    /// 3. Start a loop to consume from the buffer. We will have Y threads to process the transactions in parallel. (Y should be less than X for obvious reasons)
+    /// 3. Start a loop per processor to consume from the buffer. We will have Y threads to process the transactions in parallel. (Y should be less than X for obvious reasons)
     ///   * Note that the batches will be sequential so we won't have problems with gaps
     /// 4. We will keep track of the last processed version and monitoring things like TPS
-    pub async fn run(&mut self) {
-        let processor_name = self.processor_config.name();
-        let enable_verbose_logging = self.enable_verbose_logging.unwrap_or(false);
+    ///
+    /// When several processors are configured they share the stream: every batch is fanned out to
+    /// a channel per processor, and each processor keeps its own gap detector, `processor_status`
+    /// row and metrics.
+    ///
+    /// On SIGTERM/SIGINT the fetcher stops, batches that are already being processed are allowed
+    /// to finish, the gap detectors flush the last contiguous version to `processor_status` and
+    /// `run` returns `Ok(())`. Fatal conditions are returned as a `WorkerError`.
+    pub async fn run(&mut self) -> Result<(), WorkerError> {
+        let processor_names = self.processor_names();
         info!(
-            processor_name = processor_name,
+            processor_name = processor_names,
             service_type = PROCESSOR_SERVICE_TYPE,
             "[Parser] Running migrations"
         );
         let migration_time = std::time::Instant::now();
         self.run_migrations().await;
         info!(
-            processor_name = processor_name,
+            processor_name = processor_names,
             service_type = PROCESSOR_SERVICE_TYPE,
             duration_in_secs = migration_time.elapsed().as_secs_f64(),
             "[Parser] Finished migrations"
         );
 
-        let starting_version_from_db = self
-            .get_start_version()
-            .await
-            .expect("[Parser] Database error when getting starting version")
-            .unwrap_or_else(|| {
-                info!(
-                    processor_name = processor_name,
-                    service_type = PROCESSOR_SERVICE_TYPE,
-                    "[Parser] No starting version from db so starting from version 0"
-                );
-                0
-            });
-
-        let starting_version = match self.starting_version {
-            None => starting_version_from_db,
-            Some(version) => version,
-        };
+        // Build the processors based on the config. Each one resumes from its own status row.
+        let mut processors = vec![];
+        for processor_config in &self.processor_configs {
+            let processor_name = processor_config.name();
+            let starting_version_from_db = self
+                .get_start_version(processor_name)
+                .await
+                .context("[Parser] Database error when getting starting version")
+                .map_err(WorkerError::Database)?
+                .unwrap_or_else(|| {
+                    info!(
+                        processor_name = processor_name,
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        "[Parser] No starting version from db so starting from version 0"
+                    );
+                    0
+                });
 
-        info!(
-            processor_name = processor_name,
-            service_type = PROCESSOR_SERVICE_TYPE,
-            stream_address = self.indexer_grpc_data_service_address.to_string(),
-            final_start_version = starting_version,
-            start_version_from_config = self.starting_version,
-            start_version_from_db = starting_version_from_db,
-            "[Parser] Building processor",
-        );
+            let starting_version = match self.starting_version {
+                None => starting_version_from_db,
+                Some(version) => version,
+            };
 
-        let concurrent_tasks = self.number_concurrent_processing_tasks;
+            info!(
+                processor_name = processor_name,
+                service_type = PROCESSOR_SERVICE_TYPE,
+                stream_address = self.indexer_grpc_data_service_address.to_string(),
+                final_start_version = starting_version,
+                start_version_from_config = self.starting_version,
+                start_version_from_db = starting_version_from_db,
+                "[Parser] Building processor",
+            );
 
-        // Build the processor based on the config.
-        let processor = build_processor(&self.processor_config, self.db_pool.clone());
-        let processor = Arc::new(processor);
+            let processor = build_processor(
+                processor_config,
+                self.db_pool.clone(),
+                self.batch_write_mode(processor_config),
+            );
+            processors.push((
+                Arc::new(processor),
+                starting_version,
+                self.concurrent_tasks(processor_config),
+            ));
+        }
 
-        // This is the moving average that we use to calculate TPS
-        let mut ma = MovingAverage::new(10);
-        let mut batch_start_version = starting_version;
+        // The stream starts at the earliest version that any of the processors still needs.
+        let starting_version = processors
+            .iter()
+            .map(|(_, starting_version, _)| *starting_version)
+            .min()
+            .unwrap_or_default();
+        let batch_start_version = starting_version;
 
         let ending_version = self.ending_version;
-        let indexer_grpc_data_service_address = self.indexer_grpc_data_service_address.clone();
+        let transaction_source = self.transaction_source.clone();
         let indexer_grpc_http2_ping_interval =
             self.grpc_http2_config.grpc_http2_ping_interval_in_secs();
         let indexer_grpc_http2_ping_timeout =
@@ -182,12 +355,48 @@ impl Worker {
         // Create a transaction fetcher thread that will continuously fetch transactions from the GRPC stream
         // and write into a channel
         // The each item will be (chain_id, batch of transactions)
-        let (tx, receiver) = kanal::bounded_async::<TransactionsPBResponse>(BUFFER_SIZE);
+        let (tx, receiver) = kanal::bounded_async::<Arc<TransactionsPBResponse>>(BUFFER_SIZE);
         let request_ending_version = self.ending_version;
         let auth_token = self.auth_token.clone();
+
+        // Flipped to `true` on SIGTERM/SIGINT, or when one of the processors hits a fatal error.
+        // The fetcher stops pulling from the stream and the consumer loops stop picking up new
+        // batches once the in-flight ones are done.
+        let (shutdown_sender, shutdown_receiver) = tokio::sync::watch::channel(false);
+        let shutdown_sender = Arc::new(shutdown_sender);
+        let signal_shutdown_sender = shutdown_sender.clone();
+        let signal_processor_names = processor_names.clone();
         tokio::spawn(async move {
+            wait_for_shutdown_signal().await;
             info!(
-                processor_name = processor_name,
+                processor_name = signal_processor_names,
+                service_type = PROCESSOR_SERVICE_TYPE,
+                "[Parser] Received shutdown signal, draining in-flight batches"
+            );
+            let _ = signal_shutdown_sender.send(true);
+        });
+
+        // In record mode the fetcher hands a copy of every batch to the recorder, which flushes
+        // the last archive file once the fetcher is done.
+        let (archive_sender, recorder_task) = match &self.record_config {
+            Some(record_config) => {
+                let writer = ArchiveWriter::new(record_config)
+                    .context("[Parser] Failed to open archive for recording")
+                    .map_err(WorkerError::Fetcher)?;
+                let (archive_sender, archive_receiver) = kanal::bounded_async(BUFFER_SIZE);
+                (
+                    Some(archive_sender),
+                    Some(tokio::spawn(create_recorder_loop(archive_receiver, writer))),
+                )
+            },
+            None => (None, None),
+        };
+
+        let fetcher_shutdown_receiver = shutdown_receiver.clone();
+        let fetcher_processor_names = processor_names.clone();
+        let fetcher_task = tokio::spawn(async move {
+            info!(
+                processor_name = fetcher_processor_names,
                 service_type = PROCESSOR_SERVICE_TYPE,
                 end_version = ending_version,
                 start_version = batch_start_version,
@@ -195,42 +404,160 @@ impl Worker {
             );
             crate::grpc_stream::create_fetcher_loop(
                 tx,
-                indexer_grpc_data_service_address,
+                transaction_source,
                 indexer_grpc_http2_ping_interval,
                 indexer_grpc_http2_ping_timeout,
+                archive_sender,
                 starting_version,
                 request_ending_version,
                 auth_token,
-                processor_name.to_string(),
+                fetcher_processor_names,
                 batch_start_version,
                 BUFFER_SIZE,
+                fetcher_shutdown_receiver,
             )
             .await
         });
 
-        // Create a gap detector task that will panic if there is a gap in the processing
+        // With several processors every one of them gets its own queue and channel, so a slow
+        // processor does not delay the batches of the others and only holds back the stream once
+        // its own buffers are full.
+        let mut consumers = vec![];
+        let fan_out_task = if processors.len() == 1 {
+            let (processor, starting_version, concurrent_tasks) = processors.pop().unwrap();
+            consumers.push((processor, receiver, starting_version, concurrent_tasks));
+            None
+        } else {
+            let mut processor_senders = vec![];
+            for (processor, starting_version, concurrent_tasks) in processors {
+                let (sender, processor_receiver) =
+                    kanal::bounded_async::<Arc<TransactionsPBResponse>>(BUFFER_SIZE);
+                processor_senders.push(ProcessorSender {
+                    processor_name: processor.name(),
+                    starting_version,
+                    sender,
+                });
+                consumers.push((
+                    processor,
+                    processor_receiver,
+                    starting_version,
+                    concurrent_tasks,
+                ));
+            }
+            Some(tokio::spawn(fan_out_batches(receiver, processor_senders)))
+        };
+
+        let this = &*self;
+        let consumer_results = futures::future::join_all(consumers.into_iter().map(
+            |(processor, receiver, starting_version, concurrent_tasks)| {
+                let shutdown_sender = shutdown_sender.clone();
+                let shutdown_receiver = shutdown_receiver.clone();
+                async move {
+                    let res = this
+                        .consume_batches(
+                            processor,
+                            receiver,
+                            starting_version,
+                            concurrent_tasks,
+                            shutdown_receiver,
+                        )
+                        .await;
+                    if res.is_err() {
+                        // Let the other processors drain and stop as well.
+                        let _ = shutdown_sender.send(true);
+                    }
+                    res
+                }
+            },
+        ))
+        .await;
+
+        // The fetcher exits on its own on shutdown or after the ending version; if it closed the
+        // channel for any other reason it returns the error that made it stop.
+        if !fetcher_task.is_finished() {
+            fetcher_task.abort();
+        }
+        let fetcher_result = fetcher_task.await;
+        // The recorder is done once the fetcher dropped its sender. Its error explains why the
+        // fetcher stopped, so it goes first.
+        if let Some(recorder_task) = recorder_task {
+            recorder_task
+                .await
+                .map_err(|e| WorkerError::TaskFailed(e.into()))?
+                .context("[Parser] Failed to record transactions")
+                .map_err(WorkerError::Fetcher)?;
+        }
+        match fetcher_result {
+            // The consumers are already gone, so the fetcher could not hand over its last batch.
+            Ok(Err(WorkerError::ChannelClosed)) if *shutdown_receiver.borrow() => {},
+            Ok(res) => res?,
+            Err(e) if e.is_cancelled() => {},
+            Err(e) => return Err(WorkerError::TaskFailed(e.into())),
+        }
+        if let Some(fan_out_task) = fan_out_task {
+            fan_out_task.abort();
+        }
+        consumer_results
+            .into_iter()
+            .collect::<Result<(), WorkerError>>()?;
+
+        info!(
+            processor_name = processor_names,
+            service_type = PROCESSOR_SERVICE_TYPE,
+            "[Parser] Worker stopped cleanly"
+        );
+        Ok(())
+    }
+
+    /// Consumes the batches of one processor until the stream ends, a shutdown is requested or
+    /// a fatal error happens. Up to `concurrent_tasks` batches are processed at once.
+    async fn consume_batches(
+        &self,
+        processor: Arc<Processor>,
+        receiver: kanal::AsyncReceiver<Arc<TransactionsPBResponse>>,
+        starting_version: u64,
+        concurrent_tasks: usize,
+        mut shutdown_receiver: tokio::sync::watch::Receiver<bool>,
+    ) -> Result<(), WorkerError> {
+        let processor_name = processor.name();
+        let enable_verbose_logging = self.enable_verbose_logging.unwrap_or(false);
+
+        // This is the moving average that we use to calculate TPS
+        let mut ma = MovingAverage::new(10);
+        let mut batch_start_version = starting_version;
+
+        // Create a gap detector task that will stop the worker if there is a gap in the processing
         let (gap_detector_sender, gap_detector_receiver) =
             kanal::bounded_async::<ProcessingResult>(BUFFER_SIZE);
         let processor_clone = processor.clone();
         let gap_detection_batch_size = self.gap_detection_batch_size;
-        tokio::spawn(async move {
+        let gap_detector_task = tokio::spawn(async move {
             crate::gap_detector::create_gap_detector_status_tracker_loop(
                 gap_detector_receiver,
                 processor_clone,
                 batch_start_version,
                 gap_detection_batch_size,
             )
-            .await;
+            .await
         });
 
         // This is the consumer side of the channel. These are the major states:
         // 1. We're backfilling so we should expect many concurrent threads to process transactions
         // 2. We're caught up so we should expect a single thread to process transactions
-        // 3. We have received either an empty batch or a batch with a gap. We should panic.
-        // 4. We have not received anything in X seconds, we should panic.
-        // 5. If it's the wrong chain, panic.
+        // 3. We have received either an empty batch or a batch with a gap. We should stop with an error.
+        // 4. We have not received anything in X seconds, we should stop with an error.
+        // 5. If it's the wrong chain, stop with an error.
+        // 6. The fetcher closed the channel (shutdown, ending version reached or archive replayed), we should drain and stop.
         let mut db_chain_id = None;
-        loop {
+        let mut stream_ended = false;
+        'consumer: loop {
+            if stream_ended || *shutdown_receiver.borrow() {
+                break 'consumer;
+            }
+            // The gap detector only finishes early when it hit a fatal condition.
+            if gap_detector_task.is_finished() {
+                break 'consumer;
+            }
             info!(
                 processor_name = processor_name,
                 service_type = PROCESSOR_SERVICE_TYPE,
@@ -244,11 +571,14 @@ impl Worker {
                 let txn_pb_res = match task_index {
                     0 => {
                         // If we're the first task, we should wait until we get data. If `None`, it means the channel is closed.
-                        let txn_pb_timeout_res = timeout(
-                            Duration::from_secs(CONSUMER_THREAD_TIMEOUT_IN_SECS),
-                            receiver.recv(),
-                        )
-                        .await;
+                        let txn_pb_timeout_res = tokio::select! {
+                            res = timeout(
+                                Duration::from_secs(CONSUMER_THREAD_TIMEOUT_IN_SECS),
+                                receiver.recv(),
+                            ) => res,
+                            // Nothing is in flight yet, so we can stop right away.
+                            Ok(()) = shutdown_receiver.changed() => break 'consumer,
+                        };
                         match txn_pb_timeout_res {
                             Ok(txn_pb_res) => txn_pb_res.map(Some),
                             // Outer `Err` is a timeout
@@ -260,9 +590,9 @@ impl Worker {
                                         self.indexer_grpc_data_service_address.as_str(),
                                     "[Parser] Consumer thread timed out waiting for transactions",
                                 );
-                                panic!(
-                                    "[Parser] Consumer thread timed out waiting for transactions"
-                                );
+                                return Err(WorkerError::ConsumerTimeout {
+                                    timeout_in_secs: CONSUMER_THREAD_TIMEOUT_IN_SECS,
+                                });
                             },
                         }
                     },
@@ -274,16 +604,18 @@ impl Worker {
 
                 let txn_pb = match txn_pb_res {
                     Ok(txn_pb) => txn_pb,
-                    // This happens when the channel is closed. We should panic.
+                    // This happens when the fetcher is done and has closed the channel. Whatever
+                    // was already pulled is still processed below.
                     Err(_e) => {
-                        error!(
+                        info!(
                             processor_name = processor_name,
                             service_type = PROCESSOR_SERVICE_TYPE,
                             stream_address = self.indexer_grpc_data_service_address.as_str(),
                             "[Parser][T#{}] Channel closed; stream ended.",
                             task_index
                         );
-                        panic!("[Parser][T#{}] Channel closed", task_index);
+                        stream_ended = true;
+                        break;
                     },
                 };
 
@@ -302,13 +634,16 @@ impl Worker {
                             existing_id = existing_id,
                             "[Parser] Stream somehow changed chain id!",
                         );
-                        panic!("[Parser] Stream somehow changed chain id!");
+                        return Err(WorkerError::ChainIdChanged {
+                            existing_chain_id: existing_id,
+                            chain_id: txn_pb.chain_id,
+                        });
                     }
                 } else {
                     db_chain_id = Some(
-                        self.check_or_update_chain_id(txn_pb.chain_id as i64)
+                        self.check_or_update_chain_id(processor_name, txn_pb.chain_id as i64)
                             .await
-                            .unwrap(),
+                            .map_err(WorkerError::Database)?,
                     );
                 }
                 let current_fetched_version =
@@ -320,13 +655,20 @@ impl Worker {
                         current_fetched_version = current_fetched_version,
                         "[Parser] Received batch with gap from GRPC stream"
                     );
-                    panic!("[Parser] Received batch with gap from GRPC stream");
+                    return Err(WorkerError::BatchGap {
+                        last_fetched_version,
+                        current_fetched_version,
+                    });
                 }
                 last_fetched_version =
                     txn_pb.transactions.as_slice().last().unwrap().version as i64;
                 transactions_batches.push(txn_pb);
             }
 
+            if transactions_batches.is_empty() {
+                continue;
+            }
+
             let size_in_bytes = transactions_batches
                 .iter()
                 .fold(0.0, |acc, txn_batch| acc + txn_batch.size_in_bytes as f64);
@@ -373,7 +715,10 @@ impl Worker {
                 let processor_clone = processor.clone();
                 let gap_detector_sender = gap_detector_sender.clone();
                 let auth_token = self.auth_token.clone();
+                let batch_retry_config = self.batch_retry_config.clone();
                 let task = tokio::spawn(async move {
+                    // Batches are shared between the processors of the stream, which only
+                    // borrow the transactions.
                     let start_version = transactions_pb
                         .transactions
                         .as_slice()
@@ -429,14 +774,15 @@ impl Worker {
 
                     let processing_duration = std::time::Instant::now();
 
-                    let processed_result = processor_clone
-                        .process_transactions(
-                            transactions_pb.transactions,
-                            start_version,
-                            end_version,
-                            db_chain_id,
-                        ) // TODO: Change how we fetch chain_id, ideally can be accessed by processors when they are initiallized (e.g. so they can have a chain_id field set on new() funciton)
-                        .await;
+                    let processed_result = process_with_retries(
+                        &processor_clone,
+                        &batch_retry_config,
+                        &transactions_pb.transactions,
+                        start_version,
+                        end_version,
+                        db_chain_id,
+                    )
+                    .await;
                     if let Some(ref t) = txn_time {
                         PROCESSOR_DATA_PROCESSED_LATENCY_IN_SECS
                             .with_label_values(&[auth_token.as_str(), processor_name])
@@ -485,10 +831,12 @@ impl Worker {
                         .inc_by(end_version - start_version + 1);
 
                     if let Ok(ref res) = processed_result {
-                        gap_detector_sender
-                            .send(res.clone())
-                            .await
-                            .expect("[Parser] Failed to send versions to gap detector");
+                        if let Err(e) = gap_detector_sender.send(res.clone()).await {
+                            return Err(WorkerError::TaskFailed(anyhow::anyhow!(
+                                "[Parser] Failed to send versions to gap detector: {:?}",
+                                e
+                            )));
+                        }
 
                         // Logging and metrics
                         SINGLE_BATCH_PROCESSING_TIME_IN_SECS
@@ -555,26 +903,32 @@ impl Worker {
                         }
                     }
 
-                    processed_result
+                    processed_result.map_err(|error| WorkerError::ProcessingFailed {
+                        start_version,
+                        end_version,
+                        error,
+                    })
                 });
                 tasks.push(task);
             }
             let processing_time = std::time::Instant::now();
             let task_count = tasks.len();
-            let batches = match futures::future::try_join_all(tasks).await {
-                Ok(res) => res,
-                Err(err) => panic!("[Parser] Error processing transaction batches: {:?}", err),
-            };
+            // Wait for every task, even if one of them fails, so no batch is interrupted mid-write.
+            let batches = futures::future::join_all(tasks).await;
 
             // Update states depending on results of the batch processing
             let mut processed_versions = vec![];
+            let mut first_error = None;
             for res in batches {
-                let processed: ProcessingResult = match res {
+                let res = res
+                    .map_err(|e| WorkerError::TaskFailed(e.into()))
+                    .and_then(|res| res);
+                match res {
                     Ok(versions) => {
                         PROCESSOR_SUCCESSES_COUNT
                             .with_label_values(&[processor_name])
                             .inc();
-                        versions
+                        processed_versions.push(versions);
                     },
                     Err(e) => {
                         error!(
@@ -586,10 +940,18 @@ impl Worker {
                         PROCESSOR_ERRORS_COUNT
                             .with_label_values(&[processor_name])
                             .inc();
-                        panic!();
+                        first_error.get_or_insert(e);
                     },
                 };
-                processed_versions.push(processed);
+            }
+            if let Some(e) = first_error {
+                // Let the gap detector persist whatever contiguous progress was made before
+                // surfacing the error.
+                drop(gap_detector_sender);
+                if let Ok(Err(gap_detector_error)) = gap_detector_task.await {
+                    return Err(gap_detector_error);
+                }
+                return Err(e);
             }
 
             // Log the metrics for processed batch
@@ -658,6 +1020,28 @@ impl Worker {
                 .with_label_values(&[processor_name])
                 .set(processing_time.elapsed().as_secs_f64());
         }
+
+        // Nothing is in flight anymore. Closing the gap detector channel makes it flush the last
+        // contiguous version to `processor_status` before it exits.
+        info!(
+            processor_name = processor_name,
+            service_type = PROCESSOR_SERVICE_TYPE,
+            last_processed_version = batch_start_version as i64 - 1,
+            "[Parser] Stopping processor, flushing processor status"
+        );
+        drop(gap_detector_sender);
+        gap_detector_task
+            .await
+            .map_err(|e| WorkerError::TaskFailed(e.into()))??;
+
+        if stream_ended
+            && !*shutdown_receiver.borrow()
+            && self.ending_version.is_none()
+            && self.transaction_source.is_live()
+        {
+            return Err(WorkerError::ChannelClosed);
+        }
+        Ok(())
     }
 
     async fn run_migrations(&self) {
@@ -669,21 +1053,53 @@ impl Worker {
         run_pending_migrations(&mut conn).await;
     }
 
+    /// Processors that build on what earlier batches wrote get a single processing task, so that
+    /// their batches are committed in order. The others share the stream with more tasks.
+    fn concurrent_tasks(&self, processor_config: &ProcessorConfig) -> usize {
+        if processor_config.requires_ordered_batches() {
+            1
+        } else {
+            self.number_concurrent_processing_tasks
+        }
+    }
+
+    /// With a single processing task batches are committed in order, so the processor status can
+    /// be moved forward in the same transaction as the data.
+    fn batch_write_mode(&self, processor_config: &ProcessorConfig) -> BatchWriteMode {
+        if !self.enable_atomic_batch_writes.unwrap_or(false) {
+            BatchWriteMode::PerQuery
+        } else if self.concurrent_tasks(processor_config) == 1 {
+            BatchWriteMode::AtomicWithStatus
+        } else {
+            BatchWriteMode::Atomic
+        }
+    }
+
+    /// Names of all configured processors, used to label what they share such as the stream.
+    fn processor_names(&self) -> String {
+        self.processor_configs
+            .iter()
+            .map(|processor_config| processor_config.name())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
     /// Gets the start version for the processor. If not found, start from 0.
-    pub async fn get_start_version(&self) -> Result<Option<u64>> {
+    pub async fn get_start_version(&self, processor_name: &str) -> Result<Option<u64>> {
         let mut conn = self.db_pool.get().await?;
 
-        match ProcessorStatusQuery::get_by_processor(self.processor_config.name(), &mut conn)
-            .await?
-        {
+        match ProcessorStatusQuery::get_by_processor(processor_name, &mut conn).await? {
             Some(status) => Ok(Some(status.last_success_version as u64 + 1)),
             None => Ok(None),
         }
     }
 
     /// Verify the chain id from GRPC against the database.
-    pub async fn check_or_update_chain_id(&self, grpc_chain_id: i64) -> Result<u64> {
-        let processor_name = self.processor_config.name();
+    pub async fn check_or_update_chain_id(
+        &self,
+        processor_name: &'static str,
+        grpc_chain_id: i64,
+    ) -> Result<u64> {
         info!(
             processor_name = processor_name,
             "[Parser] Checking if chain id is correct"
@@ -725,11 +1141,180 @@ impl Worker {
     }
 }
 
+/// Processes one batch, retrying transient database errors according to `retry_config`.
+/// Errors that are not retryable, or that are still failing once all attempts are used up,
+/// are returned to the caller.
+async fn process_with_retries(
+    processor: &Processor,
+    retry_config: &BatchRetryConfig,
+    transactions: &[Transaction],
+    start_version: u64,
+    end_version: u64,
+    db_chain_id: Option<u64>,
+) -> Result<ProcessingResult> {
+    let processor_name = processor.name();
+    let max_attempts = retry_config.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        // TODO: Change how we fetch chain_id, ideally can be accessed by processors when they are initiallized (e.g. so they can have a chain_id field set on new() funciton)
+        let error = match processor
+            .process_transactions(transactions, start_version, end_version, db_chain_id)
+            .await
+        {
+            Ok(processed) => return Ok(processed),
+            Err(error) => error,
+        };
+
+        if !retry_config.is_retryable(&error) {
+            return Err(error);
+        }
+        if attempt >= max_attempts {
+            PROCESSOR_RETRIES_EXHAUSTED_COUNT
+                .with_label_values(&[processor_name])
+                .inc();
+            error!(
+                processor_name = processor_name,
+                service_type = PROCESSOR_SERVICE_TYPE,
+                start_version,
+                end_version,
+                attempts = attempt,
+                error = ?error,
+                "[Parser] Giving up on batch after exhausting retries"
+            );
+            return Err(error.context(format!("Gave up after {} attempts", attempt)));
+        }
+
+        let backoff = retry_config.backoff(attempt);
+        warn!(
+            processor_name = processor_name,
+            service_type = PROCESSOR_SERVICE_TYPE,
+            start_version,
+            end_version,
+            attempt,
+            backoff_in_ms = backoff.as_millis() as u64,
+            error = ?error,
+            "[Parser] Transient error processing batch, retrying"
+        );
+        PROCESSOR_RETRIES_COUNT
+            .with_label_values(&[processor_name])
+            .inc();
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+struct ProcessorSender {
+    processor_name: &'static str,
+    starting_version: u64,
+    sender: kanal::AsyncSender<Arc<TransactionsPBResponse>>,
+}
+
+/// Forwards every batch from the fetcher to each processor, through a queue and a forwarding
+/// task per processor. The batch is shared, only the versions below a processor's starting
+/// version are trimmed off for it, since the stream starts at the earliest starting version of
+/// all processors. A processor whose consumer has stopped is dropped.
+///
+/// A batch is handed to all queues at once, so a processor that falls behind does not keep the
+/// others from getting it. It holds back the stream only once its queue is full as well.
+async fn fan_out_batches(
+    receiver: kanal::AsyncReceiver<Arc<TransactionsPBResponse>>,
+    processor_senders: Vec<ProcessorSender>,
+) {
+    let mut queues = Vec::with_capacity(processor_senders.len());
+    let mut forwarders = Vec::with_capacity(processor_senders.len());
+    for processor_sender in processor_senders {
+        let (queue_sender, queue_receiver) = kanal::bounded_async(BUFFER_SIZE);
+        queues.push(queue_sender);
+        forwarders.push(tokio::spawn(forward_batches(
+            queue_receiver,
+            processor_sender,
+        )));
+    }
+
+    while let Ok(txn_pb) = receiver.recv().await {
+        let sent =
+            futures::future::join_all(queues.iter().map(|queue| queue.send(txn_pb.clone()))).await;
+        // A queue is closed once the forwarding task stopped, i.e. its processor stopped consuming
+        let mut sent = sent.into_iter();
+        queues.retain(|_| sent.next().map_or(false, |res| res.is_ok()));
+        if queues.is_empty() {
+            return;
+        }
+    }
+
+    // The fetcher is done. Closing the queues lets the forwarding tasks drain and stop.
+    drop(queues);
+    for forwarder in forwarders {
+        let _ = forwarder.await;
+    }
+}
+
+/// Forwards the batches of one processor from its queue to its channel.
+async fn forward_batches(
+    queue: kanal::AsyncReceiver<Arc<TransactionsPBResponse>>,
+    processor_sender: ProcessorSender,
+) {
+    while let Ok(txn_pb) = queue.recv().await {
+        let batch = match trim_batch(txn_pb, processor_sender.starting_version) {
+            Some(batch) => batch,
+            None => continue,
+        };
+        if processor_sender.sender.send(batch).await.is_err() {
+            info!(
+                processor_name = processor_sender.processor_name,
+                service_type = PROCESSOR_SERVICE_TYPE,
+                "[Parser] Processor stopped consuming, no longer forwarding batches to it"
+            );
+            return;
+        }
+        FETCHER_THREAD_CHANNEL_SIZE
+            .with_label_values(&[processor_sender.processor_name])
+            .set(processor_sender.sender.len() as i64);
+    }
+
+    // The fetcher is done. Wait for the processor to drain its channel before closing it.
+    while !processor_sender.sender.is_empty() && !processor_sender.sender.is_disconnected() {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Returns the part of the batch at or after `starting_version`, if any. Whole batches are
+/// passed on as they are, only the transactions of a trimmed one are copied.
+fn trim_batch(
+    txn_pb: Arc<TransactionsPBResponse>,
+    starting_version: u64,
+) -> Option<Arc<TransactionsPBResponse>> {
+    if txn_pb.transactions.last()?.version < starting_version {
+        return None;
+    }
+    if txn_pb.transactions.first()?.version >= starting_version {
+        return Some(txn_pb);
+    }
+    let transactions = txn_pb
+        .transactions
+        .iter()
+        .filter(|txn| txn.version >= starting_version)
+        .cloned()
+        .collect::<Vec<_>>();
+    Some(Arc::new(TransactionsPBResponse {
+        size_in_bytes: transactions
+            .iter()
+            .map(|txn| txn.encoded_len() as u64)
+            .sum(),
+        transactions,
+        chain_id: txn_pb.chain_id,
+    }))
+}
+
 /// Given a config and a db pool, build a concrete instance of a processor.
 // As time goes on there might be other things that we need to provide to certain
 // processors. As that happens we can revist whether this function (which tends to
 // couple processors together based on their args) makes sense.
-pub fn build_processor(config: &ProcessorConfig, db_pool: PgDbPool) -> Processor {
+pub fn build_processor(
+    config: &ProcessorConfig,
+    db_pool: PgDbPool,
+    write_mode: BatchWriteMode,
+) -> Processor {
     match config {
         ProcessorConfig::AccountTransactionsProcessor => {
             Processor::from(AccountTransactionsProcessor::new(db_pool))
@@ -737,8 +1322,13 @@ pub fn build_processor(config: &ProcessorConfig, db_pool: PgDbPool) -> Processor
         ProcessorConfig::AnsProcessor(config) => {
             Processor::from(AnsProcessor::new(db_pool, config.clone()))
         },
-        ProcessorConfig::CoinProcessor => Processor::from(CoinProcessor::new(db_pool)),
-        ProcessorConfig::DefaultProcessor => Processor::from(DefaultProcessor::new(db_pool)),
+        ProcessorConfig::CoinProcessor => Processor::from(CoinProcessor::new(db_pool, write_mode)),
+        ProcessorConfig::DefaultProcessor => {
+            Processor::from(DefaultProcessor::new(db_pool, write_mode))
+        },
+        ProcessorConfig::DexProcessor(config) => {
+            Processor::from(DexProcessor::new(db_pool, write_mode, config.clone()))
+        },
         ProcessorConfig::LsProcessor(config) => {
             Processor::from(LsProcessor::new(db_pool, config.clone()))
         },
@@ -751,11 +1341,15 @@ pub fn build_processor(config: &ProcessorConfig, db_pool: PgDbPool) -> Processor
             Processor::from(NftMetadataProcessor::new(db_pool, config.clone()))
         },
         ProcessorConfig::ObjectsProcessor => Processor::from(ObjectsProcessor::new(db_pool)),
-        ProcessorConfig::StakeProcessor => Processor::from(StakeProcessor::new(db_pool)),
+        ProcessorConfig::StakeProcessor => {
+            Processor::from(StakeProcessor::new(db_pool, write_mode))
+        },
         ProcessorConfig::TokenProcessor(config) => {
             Processor::from(TokenProcessor::new(db_pool, config.clone()))
         },
-        ProcessorConfig::TokenV2Processor => Processor::from(TokenV2Processor::new(db_pool)),
+        ProcessorConfig::TokenV2Processor => {
+            Processor::from(TokenV2Processor::new(db_pool, write_mode))
+        },
         ProcessorConfig::UserTransactionProcessor => {
             Processor::from(UserTransactionProcessor::new(db_pool))
         },
@@ -7,7 +7,7 @@ use prometheus::{Encoder, TextEncoder};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{fs::File, io::Read, panic::PanicInfo, path::PathBuf, process};
 use tokio::runtime::Handle;
-use tracing::error;
+use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 use warp::{http::Response, Filter};
 
@@ -52,9 +52,15 @@ where
             error!("Probes and metrics handler unexpectedly exited");
             bail!("Probes and metrics handler unexpectedly exited");
         },
-        _ = main_task_handler => {
-            error!("Main task unexpectedly exited");
-            bail!("Main task unexpectedly exited");
+        res = main_task_handler => {
+            // The main task returns `Ok(())` when it shut down cleanly (e.g. on SIGTERM) and an
+            // error for any fatal condition, which we surface as a non-zero exit code.
+            let res = res.context("Main task panicked or was cancelled").and_then(|res| res);
+            match &res {
+                Err(e) => error!(error = ?e, "Main task exited with an error"),
+                _ => info!("Main task exited cleanly"),
+            }
+            res
         },
     }
 }